@@ -1,4 +1,5 @@
 use clap::{Parser};
+use crate::client::nick_policy::NickPolicy;
 
 #[derive(Parser, Debug, Clone)]
 #[command(name = "Rusty Chat Client")]
@@ -29,15 +30,13 @@ impl ClientConfig {
     pub fn get_display_name(&self, anonymous_id: u32) -> String {
         match &self.name {
             Some(name) => name.clone(),
-            None => format!("Anonymous#{}", anonymous_id)
+            None => NickPolicy::anonymous_name(anonymous_id)
         }
     }
 
     pub fn validate(&self) -> Result<(), String> {
         if let Some(name) = &self.name {
-            if name.len() < 2 || name.len() > 20 {
-                return Err("Name must be between 2 and 20 characters.".to_string());
-            }
+            NickPolicy::normalize(name).map_err(|e| format!("{}.", e))?;
         }
 
         let valid_color = ["red", "green", "blue", "yellow", "magenta", "cyan", "white"];
@@ -1,18 +1,73 @@
-use std::{collections::HashMap, net::SocketAddr, sync::Arc};
+use std::{collections::{BTreeMap, HashMap, HashSet}, net::{IpAddr, SocketAddr}, sync::Arc, time::Duration};
+use chrono::{DateTime, Utc};
 use tokio::sync::RwLock;
+use crate::client::nick_policy::NickPolicy;
+use crate::client::offline_queue::OfflineQueue;
+use crate::client::presence::{Presence, DEFAULT_IDLE_AFTER};
+use crate::client::read_markers::ReadMarkers;
+use crate::message::chat_message::ChatMessage;
+use crate::message::mention::find_mentions;
+
+/// How many previous nicks `/whois` remembers per client
+pub const NICK_HISTORY_SIZE: usize = 5;
+/// How many offline nicks can still receive whispers
+pub const DEFAULT_MAX_KNOWN_NAMES: usize = 10_000;
 
 #[derive(Clone, Debug)]
 pub struct ClientInfo {
     pub name: String,
     pub color_index: usize,
+    /// Most recent first
+    pub previous_names: Vec<String>,
+    pub connected_at: DateTime<Utc>,
+    pub last_active: DateTime<Utc>,
+    /// Status message set with /away
+    pub away: Option<String>,
+    /// Canonical nicks this client has whispered with, either way
+    pub whisper_peers: HashSet<String>,
+}
+
+impl ClientInfo {
+    pub fn presence(&self, idle_after: Duration) -> Presence {
+        if let Some(message) = &self.away {
+            return Presence::Away(message.clone());
+        }
+        let inactive = (Utc::now() - self.last_active).to_std().unwrap_or_default();
+        if inactive >= idle_after {
+            Presence::Idle
+        } else {
+            Presence::Online
+        }
+    }
 }
 
 pub type ClientMap = Arc<RwLock<HashMap<SocketAddr, ClientInfo>>>;
 
+/// Non-anonymous nicks in use or last used by someone
+#[derive(Debug, Default)]
+struct KnownNames {
+    /// Canonical -> display name, the address it was last taken from and when
+    by_name: HashMap<String, (String, IpAddr, u64)>,
+    /// When each name was last taken -> canonical, oldest first
+    by_taken: BTreeMap<u64, String>,
+    taken: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct ClientManager{
     clients: ClientMap,
     color_counter: Arc<RwLock<usize>>,
+    known_names: Arc<RwLock<KnownNames>>,
+    max_known_names: usize,
+    offline_whispers: OfflineQueue,
+    read_markers: ReadMarkers,
+    idle_after: Duration,
+}
+
+impl Default for ClientManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl ClientManager {
@@ -20,31 +75,136 @@ impl ClientManager {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
             color_counter: Arc::new(RwLock::new(0)),
+            known_names: Arc::new(RwLock::new(KnownNames::default())),
+            max_known_names: DEFAULT_MAX_KNOWN_NAMES,
+            offline_whispers: OfflineQueue::default(),
+            read_markers: ReadMarkers::new(),
+            idle_after: DEFAULT_IDLE_AFTER,
         }
     }
 
+    pub fn with_idle_timeout(mut self, idle_after: Duration) -> Self {
+        self.idle_after = idle_after;
+        self
+    }
+
+    pub fn with_offline_queue(mut self, max_per_sender: usize, max_age: Duration) -> Self {
+        self.offline_whispers = OfflineQueue::new(max_per_sender, max_age);
+        self
+    }
+
+    pub fn with_max_known_names(mut self, max_known_names: usize) -> Self {
+        self.max_known_names = max_known_names;
+        self
+    }
+
     pub fn get_clients(&self) -> ClientMap {
         self.clients.clone()
     }
 
+    /// Names are compared in canonical form, so "Alice" and "alice" can't coexist
     pub async fn is_name_available(&self, name: &str) -> bool {
+        let canonical = NickPolicy::canonical(name);
         let clients_read = self.clients.read().await;
-        clients_read.values().all(|info| info.name != name)
+        clients_read.values().all(|info| NickPolicy::canonical(&info.name) != canonical)
     }
 
     pub async fn register_client(&self, addr: SocketAddr, name: String) {
         let mut clients_write = self.clients.write().await;
         let mut color_counter = self.color_counter.write().await;
 
+        let now = Utc::now();
         let client_info = ClientInfo {
             name,
             color_index: *color_counter,
+            previous_names: Vec::new(),
+            connected_at: now,
+            last_active: now,
+            away: None,
+            whisper_peers: HashSet::new(),
         };
 
+        self.remember_name(&client_info.name, addr.ip()).await;
         clients_write.insert(addr, client_info);
         *color_counter += 1;
     }
 
+    async fn remember_name(&self, name: &str, ip: IpAddr) {
+        if NickPolicy::is_anonymous(name) {
+            return;
+        }
+        let mut known_write = self.known_names.write().await;
+        let known = &mut *known_write;
+        known.taken += 1;
+        if let Some((_, _, taken)) = known.by_name.insert(NickPolicy::canonical(name), (name.to_string(), ip, known.taken)) {
+            known.by_taken.remove(&taken);
+        }
+        known.by_taken.insert(known.taken, NickPolicy::canonical(name));
+        // Full: forget the name taken longest ago. Its whispers wait out their age.
+        if known.by_name.len() > self.max_known_names {
+            if let Some((_, oldest)) = known.by_taken.pop_first() {
+                known.by_name.remove(&oldest);
+            }
+        }
+    }
+
+    async fn forget_name(&self, name: &str) {
+        let mut known_write = self.known_names.write().await;
+        if let Some((_, _, taken)) = known_write.by_name.remove(&NickPolicy::canonical(name)) {
+            known_write.by_taken.remove(&taken);
+        }
+    }
+
+    /// Display name of a registered user, online or not
+    pub async fn get_known_name(&self, name: &str) -> Option<String> {
+        let known_read = self.known_names.read().await;
+        known_read.by_name.get(&NickPolicy::canonical(name)).map(|(display, _, _)| display.clone())
+    }
+
+    /// Keeps `message` for whoever next uses `recipient` from where it was last used
+    pub async fn queue_offline_whisper(&self, recipient: &str, message: ChatMessage) -> Result<(), String> {
+        let known_ip = self.known_names.read().await.by_name.get(&NickPolicy::canonical(recipient)).map(|(_, ip, _)| *ip);
+        let ip = known_ip.ok_or_else(|| format!("User '{}' not found", recipient))?;
+        self.offline_whispers.push(recipient, ip, message).await
+    }
+
+    /// Whispers waiting for the nick `addr` is using, if it's where that nick was last used
+    pub async fn take_offline_whispers(&self, addr: &SocketAddr) -> Vec<ChatMessage> {
+        match self.get_clients_name(addr).await {
+            Some(name) => self.offline_whispers.take(&name, addr.ip()).await,
+            None => Vec::new(),
+        }
+    }
+
+    pub async fn read_marker(&self, name: &str, room: &str) -> Option<u64> {
+        self.read_markers.get(name, room).await
+    }
+
+    /// Anonymous nicks are reused by strangers, so only registered ones keep markers
+    pub async fn mark_read(&self, addr: &SocketAddr, room: &str, id: u64) {
+        if let Some(name) = self.get_clients_name(addr).await {
+            if !NickPolicy::is_anonymous(&name) {
+                self.read_markers.mark(&name, room, id).await;
+            }
+        }
+    }
+
+    /// Display names of the online users mentioned in `content`
+    pub async fn resolve_mentions(&self, content: &str) -> Vec<String> {
+        let clients_read = self.clients.read().await;
+        let mut names: Vec<String> = Vec::new();
+        for mention in find_mentions(content) {
+            let canonical = NickPolicy::canonical(&mention);
+            let found = clients_read.values().find(|info| NickPolicy::canonical(&info.name) == canonical);
+            if let Some(info) = found {
+                if !names.contains(&info.name) {
+                    names.push(info.name.clone());
+                }
+            }
+        }
+        names
+    }
+
     pub async fn get_clients_name(&self, addr: &SocketAddr) -> Option<String> {
         let clients_read = self.clients.read().await;
         clients_read.get(addr).map(|info| info.name.clone())
@@ -55,10 +215,61 @@ impl ClientManager {
         clients_read.get(addr).cloned()
     }
 
+    pub async fn get_presence(&self, addr: &SocketAddr) -> Option<Presence> {
+        let clients_read = self.clients.read().await;
+        clients_read.get(addr).map(|info| info.presence(self.idle_after))
+    }
+
+    /// Records activity from `addr`
+    pub async fn touch(&self, addr: &SocketAddr) {
+        let mut clients_write = self.clients.write().await;
+        if let Some(info) = clients_write.get_mut(addr) {
+            info.last_active = Utc::now();
+        }
+    }
+
+    /// `None` clears the away status
+    pub async fn set_away(&self, addr: &SocketAddr, message: Option<String>) {
+        let mut clients_write = self.clients.write().await;
+        if let Some(info) = clients_write.get_mut(addr) {
+            info.away = message;
+        }
+    }
+
+    /// Records that `from` and `to` have a whisper conversation
+    pub async fn note_whisper(&self, from: &SocketAddr, to: &SocketAddr) {
+        let mut clients_write = self.clients.write().await;
+        let names = clients_write.get(from).map(|info| info.name.clone())
+            .zip(clients_write.get(to).map(|info| info.name.clone()));
+        if let Some((from_name, to_name)) = names {
+            if let Some(info) = clients_write.get_mut(from) {
+                info.whisper_peers.insert(NickPolicy::canonical(&to_name));
+            }
+            if let Some(info) = clients_write.get_mut(to) {
+                info.whisper_peers.insert(NickPolicy::canonical(&from_name));
+            }
+        }
+    }
+
+    pub async fn is_whisper_peer(&self, addr: &SocketAddr, name: &str) -> bool {
+        let clients_read = self.clients.read().await;
+        clients_read.get(addr).is_some_and(|info| info.whisper_peers.contains(&NickPolicy::canonical(name)))
+    }
+
+    /// USER_LIST entries with presence for `members`, see `Presence::encode_entry`
+    pub async fn user_list_entries(&self, members: &[SocketAddr]) -> Vec<String> {
+        let clients_read = self.clients.read().await;
+        members.iter()
+            .filter_map(|addr| clients_read.get(addr))
+            .map(|info| info.presence(self.idle_after).encode_entry(&info.name))
+            .collect()
+    }
+
     pub async fn get_client_by_name(&self, name: &str) -> Option<SocketAddr> {
+        let canonical = NickPolicy::canonical(name);
         let clients_read = self.clients.read().await;
         for (addr, info) in clients_read.iter() {
-            if info.name == name {
+            if NickPolicy::canonical(&info.name) == canonical {
                 return Some(*addr);
             }
         }
@@ -70,10 +281,112 @@ impl ClientManager {
         clients_write.remove(addr);
     }
 
+    /// Renames `addr`, carrying over its whisper conversations and the
+    /// whispers still queued for the old nick
     pub async fn update_client_name(&self, addr: SocketAddr, new_name: String) {
+        self.remember_name(&new_name, addr.ip()).await;
         let mut clients_write = self.clients.write().await;
-        if let Some(client_info) = clients_write.get_mut(&addr) {
-            client_info.name = new_name;
+        let Some(client_info) = clients_write.get_mut(&addr) else {
+            return;
+        };
+        let old_name = std::mem::replace(&mut client_info.name, new_name.clone());
+        client_info.previous_names.retain(|name| *name != client_info.name);
+        client_info.previous_names.insert(0, old_name.clone());
+        client_info.previous_names.truncate(NICK_HISTORY_SIZE);
+
+        let (old_canonical, new_canonical) = (NickPolicy::canonical(&old_name), NickPolicy::canonical(&new_name));
+        for info in clients_write.values_mut() {
+            if info.whisper_peers.remove(&old_canonical) {
+                info.whisper_peers.insert(new_canonical.clone());
+            }
         }
+        drop(clients_write);
+        // The old nick is free for anyone now, so nothing more gets queued for it
+        if old_canonical != new_canonical {
+            self.forget_name(&old_name).await;
+        }
+        self.offline_whispers.rename(&old_name, &new_name, addr.ip()).await;
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], port))
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_presence() {
+        let manager = ClientManager::new().with_idle_timeout(Duration::from_secs(60));
+        manager.register_client(addr(1), "alice".to_string()).await;
+        assert_eq!(manager.get_presence(&addr(1)).await, Some(Presence::Online));
+
+        manager.get_clients().write().await.get_mut(&addr(1)).unwrap().last_active =
+            Utc::now() - chrono::Duration::minutes(2);
+        assert_eq!(manager.get_presence(&addr(1)).await, Some(Presence::Idle));
+
+        manager.touch(&addr(1)).await;
+        assert_eq!(manager.get_presence(&addr(1)).await, Some(Presence::Online));
+
+        manager.set_away(&addr(1), Some("lunch".to_string())).await;
+        assert_eq!(manager.user_list_entries(&[addr(1)]).await, vec!["alice:away:lunch"]);
+        manager.set_away(&addr(1), None).await;
+        assert_eq!(manager.user_list_entries(&[addr(1), addr(2)]).await, vec!["alice:online:"]);
+    }
+
+    #[tokio::test]
+    async fn test_rename_keeps_whispers() {
+        let manager = ClientManager::new();
+        manager.register_client(addr(1), "alice".to_string()).await;
+        manager.register_client(addr(2), "bob".to_string()).await;
+        manager.note_whisper(&addr(1), &addr(2)).await;
+        let whisper = ChatMessage::whisper("hi".to_string(), addr(2), "bob".to_string(), None, "alice".to_string());
+        manager.queue_offline_whisper("alice", whisper).await.unwrap();
+
+        manager.update_client_name(addr(1), "Alicia".to_string()).await;
+        assert!(manager.is_whisper_peer(&addr(2), "alicia").await);
+        assert!(!manager.is_whisper_peer(&addr(2), "alice").await);
+        assert_eq!(manager.take_offline_whispers(&addr(1)).await.len(), 1);
+        assert_eq!(manager.get_known_name("alice").await, None);
+        assert_eq!(manager.get_known_name("ALICIA").await.as_deref(), Some("Alicia"));
+    }
+
+    #[tokio::test]
+    async fn test_whispers_wait_for_the_last_address() {
+        let stranger = SocketAddr::from(([10, 0, 0, 9], 1));
+        let manager = ClientManager::new().with_max_known_names(1);
+        manager.register_client(addr(1), "alice".to_string()).await;
+        manager.remove_client(&addr(1)).await;
+        let whisper = ChatMessage::whisper("hi".to_string(), addr(2), "bob".to_string(), None, "alice".to_string());
+        manager.queue_offline_whisper("alice", whisper).await.unwrap();
+
+        // Evicting the name leaves the whisper queued
+        manager.register_client(addr(3), "carol".to_string()).await;
+        assert_eq!(manager.get_known_name("alice").await, None);
+
+        manager.register_client(stranger, "alice".to_string()).await;
+        assert!(manager.take_offline_whispers(&stranger).await.is_empty());
+        manager.remove_client(&stranger).await;
+        manager.register_client(addr(4), "alice".to_string()).await;
+        assert_eq!(manager.take_offline_whispers(&addr(4)).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_known_names_are_bounded() {
+        let manager = ClientManager::new().with_max_known_names(2);
+        for (port, name) in [(1, "alice"), (2, "bob"), (3, "carol")] {
+            manager.register_client(addr(port), name.to_string()).await;
+        }
+        assert_eq!(manager.get_known_name("alice").await, None);
+        assert_eq!(manager.get_known_name("carol").await.as_deref(), Some("carol"));
+
+        // Taking a name again makes it the newest
+        manager.update_client_name(addr(2), "bob".to_string()).await;
+        manager.register_client(addr(4), "dave".to_string()).await;
+        assert_eq!(manager.get_known_name("bob").await.as_deref(), Some("bob"));
+        assert_eq!(manager.get_known_name("carol").await, None);
+    }
+}
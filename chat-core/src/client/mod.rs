@@ -1,2 +1,6 @@
 pub mod client_manager;
-pub mod client_config;
\ No newline at end of file
+pub mod client_config;
+pub mod nick_policy;
+pub mod offline_queue;
+pub mod presence;
+pub mod read_markers;
\ No newline at end of file
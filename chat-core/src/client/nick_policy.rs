@@ -0,0 +1,259 @@
+use std::fmt;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+use unicode_segmentation::UnicodeSegmentation;
+
+pub const MIN_NICK_LENGTH: usize = 2;
+pub const MAX_NICK_LENGTH: usize = 20;
+
+/// Compared against the canonical form, so "Admin" or "ＳＹＳＴＥＭ" are caught too
+const RESERVED_NAMES: &[&str] = &[
+    "system",
+    "server",
+    "admin",
+    "administrator",
+    "moderator",
+    "root",
+];
+
+/// Guest names are "Anonymous#<id>"; no chosen nick may start like one
+const RESERVED_PREFIX: &str = "anonymous";
+
+const ALLOWED_SYMBOLS: &[char] = &['_', '-', '.'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NickError {
+    TooShort,
+    TooLong,
+    InvalidCharacter(char),
+    MixedScripts,
+    Reserved,
+    /// The line sent wasn't UTF-8 text
+    InvalidEncoding,
+}
+
+impl fmt::Display for NickError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NickError::TooShort | NickError::TooLong => write!(
+                f,
+                "Name must be between {}-{} characters",
+                MIN_NICK_LENGTH, MAX_NICK_LENGTH
+            ),
+            NickError::InvalidCharacter(c) => write!(
+                f,
+                "'{}' is not allowed (use letters, digits, '_', '-' or '.')",
+                c.escape_default()
+            ),
+            NickError::MixedScripts => write!(f, "Name mixes letters from different alphabets"),
+            NickError::Reserved => write!(f, "This name is reserved"),
+            NickError::InvalidEncoding => write!(f, "Name must be valid UTF-8 text"),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Other,
+}
+
+impl Script {
+    fn of(c: char) -> Self {
+        match c as u32 {
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+            0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x400..=0x52F => Script::Cyrillic,
+            _ => Script::Other,
+        }
+    }
+}
+
+pub struct NickPolicy;
+
+impl NickPolicy {
+    /// Validates a requested nick and returns it in the form it will be displayed (NFC)
+    pub fn normalize(name: &str) -> Result<String, NickError> {
+        let name: String = name.trim().nfc().collect();
+
+        let length = name.graphemes(true).count();
+        if length < MIN_NICK_LENGTH {
+            return Err(NickError::TooShort);
+        }
+        if length > MAX_NICK_LENGTH {
+            return Err(NickError::TooLong);
+        }
+
+        for grapheme in name.graphemes(true) {
+            let mut chars = grapheme.chars();
+            if let Some(base) = chars.next() {
+                if !base.is_alphanumeric() && !ALLOWED_SYMBOLS.contains(&base) {
+                    return Err(NickError::InvalidCharacter(base));
+                }
+            }
+            // Only accents may follow the base character
+            if let Some(extra) = chars.find(|c| !is_combining_mark(*c)) {
+                return Err(NickError::InvalidCharacter(extra));
+            }
+        }
+
+        let mut script = None;
+        for c in name.chars().filter(|c| c.is_alphabetic()) {
+            let current = Script::of(c);
+            if current == Script::Other {
+                continue;
+            }
+            match &script {
+                None => script = Some(current),
+                Some(first) if *first != current => return Err(NickError::MixedScripts),
+                _ => {}
+            }
+        }
+
+        if Self::is_reserved(&name) {
+            return Err(NickError::Reserved);
+        }
+
+        Ok(name)
+    }
+
+    /// Form used to compare nicks. Two nicks with the same canonical form
+    /// can't be online at the same time. In order:
+    /// - NFKC, so full-width letters and ligatures become plain ones
+    /// - case folding: lowercasing plus the folds in `fold_case`
+    /// - Cyrillic and Greek letters that look like Latin ones, see `fold_confusable`
+    ///
+    /// Nothing else is folded: digits and Latin letters stay distinct, so
+    /// "bob1", "bobl" and "bobI" are three different nicks.
+    pub fn canonical(name: &str) -> String {
+        name.trim()
+            .nfkc()
+            .flat_map(char::to_lowercase)
+            .flat_map(Self::fold_case)
+            .map(Self::fold_confusable)
+            .collect()
+    }
+
+    /// Characters that can appear in a valid nick
+    pub fn is_nick_char(c: char) -> bool {
+        c.is_alphanumeric() || ALLOWED_SYMBOLS.contains(&c) || is_combining_mark(c)
+    }
+
+    pub fn is_reserved(name: &str) -> bool {
+        let canonical = Self::canonical(name);
+        RESERVED_NAMES.contains(&canonical.as_str()) || canonical.starts_with(RESERVED_PREFIX)
+    }
+
+    pub fn anonymous_name(id: u32) -> String {
+        format!("Anonymous#{}", id)
+    }
+
+    pub fn is_anonymous(name: &str) -> bool {
+        name.starts_with("Anonymous#")
+    }
+
+    /// Where full case folding differs from lowercasing
+    fn fold_case(c: char) -> impl Iterator<Item = char> {
+        let (folded, extra) = match c {
+            'ß' => ('s', Some('s')),
+            'ς' => ('σ', None),
+            'ϐ' => ('β', None),
+            'ϑ' => ('θ', None),
+            'ϕ' => ('φ', None),
+            'ϖ' => ('π', None),
+            'ϰ' => ('κ', None),
+            'ϱ' => ('ρ', None),
+            'ϵ' => ('ε', None),
+            other => (other, None),
+        };
+        std::iter::once(folded).chain(extra)
+    }
+
+    /// Applied after case folding, so only lowercase look-alikes are listed
+    fn fold_confusable(c: char) -> char {
+        match c {
+            // Cyrillic
+            'а' => 'a', 'с' => 'c', 'ԁ' => 'd', 'е' => 'e', 'һ' => 'h',
+            'і' => 'i', 'ј' => 'j', 'ӏ' => 'l', 'о' => 'o', 'р' => 'p',
+            'ԛ' => 'q', 'ѕ' => 's', 'ԝ' => 'w', 'х' => 'x', 'у' => 'y',
+            // Greek
+            'α' => 'a', 'ι' => 'i', 'κ' => 'k', 'ν' => 'v', 'ο' => 'o',
+            'ρ' => 'p', 'υ' => 'u', 'χ' => 'x',
+            other => other,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_length_counts_graphemes() {
+        assert!(NickPolicy::normalize("Zoë").is_ok());
+        // 20 accented letters are 40 code points but 20 graphemes
+        let accented = "e\u{301}".repeat(20);
+        assert!(NickPolicy::normalize(&accented).is_ok());
+        assert_eq!(NickPolicy::normalize("a"), Err(NickError::TooShort));
+        assert_eq!(NickPolicy::normalize(&"a".repeat(21)), Err(NickError::TooLong));
+    }
+
+    #[test]
+    fn test_rejects_invisible_and_symbols() {
+        assert_eq!(
+            NickPolicy::normalize("\u{200B}\u{200B}\u{200B}"),
+            Err(NickError::InvalidCharacter('\u{200B}'))
+        );
+        assert!(NickPolicy::normalize("bob smith").is_err());
+        assert!(NickPolicy::normalize("bob|smith").is_err());
+        assert!(NickPolicy::normalize("😀😀😀").is_err());
+        assert!(NickPolicy::normalize("bob_smith-2.0").is_ok());
+    }
+
+    #[test]
+    fn test_rejects_mixed_scripts() {
+        // Cyrillic 'а' in a Latin name
+        assert_eq!(NickPolicy::normalize("\u{430}lice"), Err(NickError::MixedScripts));
+        assert!(NickPolicy::normalize("Дмитрий").is_ok());
+    }
+
+    #[test]
+    fn test_reserved_names() {
+        assert_eq!(NickPolicy::normalize("SYSTEM"), Err(NickError::Reserved));
+        assert_eq!(NickPolicy::normalize("Admin"), Err(NickError::Reserved));
+        assert_eq!(NickPolicy::normalize("ａｄｍｉｎ"), Err(NickError::Reserved));
+        assert!(NickPolicy::normalize("Anonymous#3").is_err());
+        // Reserved as a prefix, not only because '#' can't be typed
+        assert_eq!(NickPolicy::normalize("Anonymous3"), Err(NickError::Reserved));
+        assert_eq!(NickPolicy::normalize("ANONYMOUS_bob"), Err(NickError::Reserved));
+        assert!(NickPolicy::normalize("anon").is_ok());
+    }
+
+    #[test]
+    fn test_canonical_form() {
+        assert_eq!(NickPolicy::canonical("Alice"), NickPolicy::canonical("alice"));
+        assert_eq!(NickPolicy::canonical("ALICE"), NickPolicy::canonical("alice"));
+        assert_eq!(NickPolicy::canonical("Вова"), NickPolicy::canonical("вова"));
+        assert_eq!(NickPolicy::canonical("ａｌｉｃｅ"), NickPolicy::canonical("alice"));
+        assert_eq!(NickPolicy::canonical("Zoe\u{308}"), NickPolicy::canonical("zoë"));
+        // Fully Cyrillic look-alike
+        assert_eq!(NickPolicy::canonical("\u{441}\u{43E}\u{440}\u{435}"), NickPolicy::canonical("cope"));
+        assert_ne!(NickPolicy::canonical("alice"), NickPolicy::canonical("alicia"));
+    }
+
+    #[test]
+    fn test_fold_set() {
+        // Full case folding, not just lowercasing
+        assert_eq!(NickPolicy::canonical("Straße"), "strasse");
+        assert_eq!(NickPolicy::canonical("STRASSE"), "strasse");
+        assert_eq!(NickPolicy::canonical("ΟΔΟΣ"), NickPolicy::canonical("οδος"));
+        // Digits and Latin letters are never folded into each other
+        assert_ne!(NickPolicy::canonical("bob1"), NickPolicy::canonical("bobl"));
+        assert_ne!(NickPolicy::canonical("b0b"), NickPolicy::canonical("bob"));
+        // Capital I lowercases to i, so it stays apart from l
+        assert_ne!(NickPolicy::canonical("AIice"), NickPolicy::canonical("Alice"));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{collections::{HashMap, VecDeque}, net::IpAddr, sync::Arc, time::Duration};
+use chrono::Utc;
+use tokio::sync::RwLock;
+use crate::client::nick_policy::NickPolicy;
+use crate::message::chat_message::ChatMessage;
+
+pub const DEFAULT_MAX_QUEUED: usize = 50;
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A whisper and the address its recipient last used their nick from
+#[derive(Debug, Clone)]
+struct Queued {
+    recipient_ip: IpAddr,
+    message: ChatMessage,
+}
+
+#[derive(Debug, Default)]
+struct Inboxes {
+    /// Keyed by canonical nick, oldest first
+    by_recipient: HashMap<String, VecDeque<Queued>>,
+    /// Whispers waiting per sender address, across all recipients
+    per_sender: HashMap<IpAddr, usize>,
+}
+
+impl Inboxes {
+    fn release(&mut self, message: &ChatMessage) {
+        let sender = message.sender_addr.ip();
+        if let Some(count) = self.per_sender.get_mut(&sender) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_sender.remove(&sender);
+            }
+        }
+    }
+}
+
+/// Whispers waiting for users that are offline. Nicks aren't owned, so a
+/// whisper only goes to someone using the nick from the address its recipient
+/// last used it from.
+#[derive(Debug, Clone)]
+pub struct OfflineQueue {
+    inboxes: Arc<RwLock<Inboxes>>,
+    max_per_sender: usize,
+    max_age: Duration,
+}
+
+impl Default for OfflineQueue {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_QUEUED, DEFAULT_MAX_AGE)
+    }
+}
+
+impl OfflineQueue {
+    pub fn new(max_per_sender: usize, max_age: Duration) -> Self {
+        Self {
+            inboxes: Arc::new(RwLock::new(Inboxes::default())),
+            max_per_sender,
+            max_age,
+        }
+    }
+
+    pub async fn push(&self, recipient: &str, recipient_ip: IpAddr, message: ChatMessage) -> Result<(), String> {
+        let mut inboxes = self.inboxes.write().await;
+        let sender = message.sender_addr.ip();
+        let waiting = |inboxes: &Inboxes| inboxes.per_sender.get(&sender).copied().unwrap_or(0);
+
+        if waiting(&inboxes) >= self.max_per_sender {
+            // Expired whispers count until swept, which is only worth doing here
+            let recipients: Vec<String> = inboxes.by_recipient.keys().cloned().collect();
+            for recipient in recipients {
+                self.drop_expired(&mut inboxes, &recipient);
+            }
+            if waiting(&inboxes) >= self.max_per_sender {
+                return Err("Too many of your whispers are waiting for offline users".to_string());
+            }
+        }
+        inboxes.by_recipient.entry(NickPolicy::canonical(recipient)).or_default()
+            .push_back(Queued { recipient_ip, message });
+        *inboxes.per_sender.entry(sender).or_default() += 1;
+        Ok(())
+    }
+
+    /// Removes and returns everything still fresh for `recipient` connecting
+    /// from `ip`, oldest first
+    pub async fn take(&self, recipient: &str, ip: IpAddr) -> Vec<ChatMessage> {
+        let mut inboxes = self.inboxes.write().await;
+        let canonical = NickPolicy::canonical(recipient);
+        self.drop_expired(&mut inboxes, &canonical);
+        let Some(queue) = inboxes.by_recipient.remove(&canonical) else {
+            return Vec::new();
+        };
+        let (taken, kept): (VecDeque<Queued>, VecDeque<Queued>) =
+            queue.into_iter().partition(|queued| queued.recipient_ip == ip);
+        if !kept.is_empty() {
+            inboxes.by_recipient.insert(canonical, kept);
+        }
+        for queued in &taken {
+            inboxes.release(&queued.message);
+        }
+        taken.into_iter().map(|queued| queued.message).collect()
+    }
+
+    /// Moves what waits for `old` at `ip` to `new`, after anything `new` already has
+    pub async fn rename(&self, old: &str, new: &str, ip: IpAddr) {
+        let (old, new) = (NickPolicy::canonical(old), NickPolicy::canonical(new));
+        if old == new {
+            return;
+        }
+        let mut inboxes = self.inboxes.write().await;
+        let Some(queue) = inboxes.by_recipient.remove(&old) else {
+            return;
+        };
+        let (moved, kept): (VecDeque<Queued>, VecDeque<Queued>) =
+            queue.into_iter().partition(|queued| queued.recipient_ip == ip);
+        if !kept.is_empty() {
+            inboxes.by_recipient.insert(old, kept);
+        }
+        if !moved.is_empty() {
+            inboxes.by_recipient.entry(new).or_default().extend(moved);
+        }
+    }
+
+    fn drop_expired(&self, inboxes: &mut Inboxes, recipient: &str) {
+        let Some(queue) = inboxes.by_recipient.get_mut(recipient) else {
+            return;
+        };
+        let now = Utc::now();
+        let (expired, fresh): (VecDeque<Queued>, VecDeque<Queued>) = std::mem::take(queue).into_iter()
+            .partition(|queued| (now - queued.message.timestamp).to_std().is_ok_and(|age| age > self.max_age));
+        if fresh.is_empty() {
+            inboxes.by_recipient.remove(recipient);
+        } else {
+            *queue = fresh;
+        }
+        for queued in &expired {
+            inboxes.release(&queued.message);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    const HOME: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 1));
+    const ELSEWHERE: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(10, 0, 0, 2));
+
+    fn whisper_from(sender: &str, content: &str) -> ChatMessage {
+        let addr: SocketAddr = format!("{}:1000", sender).parse().unwrap();
+        ChatMessage::whisper(content.to_string(), addr, "alice".to_string(), None, "bob".to_string())
+    }
+
+    fn whisper(content: &str) -> ChatMessage {
+        whisper_from("127.0.0.1", content)
+    }
+
+    #[tokio::test]
+    async fn test_delivers_in_order_once() {
+        let queue = OfflineQueue::default();
+        queue.push("Bob", HOME, whisper("one")).await.unwrap();
+        queue.push("bob", HOME, whisper("two")).await.unwrap();
+
+        let delivered: Vec<String> = queue.take("BOB", HOME).await.into_iter().map(|m| m.content).collect();
+        assert_eq!(delivered, vec!["one", "two"]);
+        assert!(queue.take("bob", HOME).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_only_delivered_where_the_nick_was_last_used() {
+        let queue = OfflineQueue::default();
+        queue.push("bob", HOME, whisper("hi")).await.unwrap();
+
+        assert!(queue.take("bob", ELSEWHERE).await.is_empty());
+        assert_eq!(queue.take("bob", HOME).await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_size_limit_is_per_sender() {
+        let queue = OfflineQueue::new(2, DEFAULT_MAX_AGE);
+        queue.push("bob", HOME, whisper("one")).await.unwrap();
+        queue.push("carol", HOME, whisper("two")).await.unwrap();
+        assert!(queue.push("dave", HOME, whisper("three")).await.is_err());
+
+        // Someone else can still reach bob, and delivery frees the quota
+        queue.push("bob", HOME, whisper_from("127.0.0.2", "other")).await.unwrap();
+        assert_eq!(queue.take("bob", HOME).await.len(), 2);
+        queue.push("dave", HOME, whisper("three")).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_messages_are_dropped() {
+        let queue = OfflineQueue::new(2, Duration::from_secs(60));
+        let mut old = whisper("old");
+        old.timestamp = Utc::now() - chrono::Duration::minutes(5);
+        queue.push("carol", HOME, old.clone()).await.unwrap();
+        queue.push("bob", HOME, old).await.unwrap();
+        // Expired whispers no longer count against the sender
+        queue.push("bob", HOME, whisper("new")).await.unwrap();
+
+        let delivered: Vec<String> = queue.take("bob", HOME).await.into_iter().map(|m| m.content).collect();
+        assert_eq!(delivered, vec!["new"]);
+        assert!(queue.take("carol", HOME).await.is_empty());
+    }
+}
@@ -0,0 +1,57 @@
+use std::time::Duration;
+use crate::message::protocol::{escape_field, unescape_field};
+
+/// Inactivity after which a user is shown as idle
+pub const DEFAULT_IDLE_AFTER: Duration = Duration::from_secs(5 * 60);
+pub const MAX_AWAY_MESSAGE_LENGTH: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Presence {
+    Online,
+    /// No activity for a while
+    Idle,
+    /// Set with /away, with an optional status message
+    Away(String),
+}
+
+impl Presence {
+    /// User list entry: "<name>:<online|idle|away>:<status message>"
+    pub fn encode_entry(&self, name: &str) -> String {
+        match self {
+            Presence::Online => format!("{}:online:", name),
+            Presence::Idle => format!("{}:idle:", name),
+            Presence::Away(message) => format!("{}:away:{}", name, escape_field(message)),
+        }
+    }
+
+    /// Reads an entry written by `encode_entry`. A bare name is online.
+    pub fn decode_entry(entry: &str) -> (String, Presence) {
+        let mut parts = entry.splitn(3, ':');
+        let name = parts.next().unwrap_or_default().to_string();
+        let presence = match parts.next() {
+            Some("idle") => Presence::Idle,
+            Some("away") => Presence::Away(unescape_field(parts.next().unwrap_or_default())),
+            _ => Presence::Online,
+        };
+        (name, presence)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry_round_trip() {
+        for presence in [Presence::Online, Presence::Idle, Presence::Away("lunch: back at 2, promise".to_string())] {
+            let entry = presence.encode_entry("alice");
+            assert_eq!(Presence::decode_entry(&entry), ("alice".to_string(), presence));
+        }
+    }
+
+    #[test]
+    fn test_bare_name_is_online() {
+        assert_eq!(Presence::decode_entry("bob"), ("bob".to_string(), Presence::Online));
+    }
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashMap, sync::Arc};
+use tokio::sync::RwLock;
+use crate::client::nick_policy::NickPolicy;
+
+/// Last message id each user has seen per room, keyed by canonical nick
+/// so it follows the user across sessions
+#[derive(Debug, Clone, Default)]
+pub struct ReadMarkers {
+    markers: Arc<RwLock<HashMap<String, HashMap<String, u64>>>>,
+}
+
+impl ReadMarkers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get(&self, name: &str, room: &str) -> Option<u64> {
+        let markers = self.markers.read().await;
+        markers.get(&NickPolicy::canonical(name))?.get(room).copied()
+    }
+
+    /// Moves the marker forward; reports from an older session can't move it back
+    pub async fn mark(&self, name: &str, room: &str, id: u64) {
+        let mut markers = self.markers.write().await;
+        let marker = markers.entry(NickPolicy::canonical(name))
+            .or_default()
+            .entry(room.to_string())
+            .or_insert(id);
+        *marker = (*marker).max(id);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_markers_follow_canonical_nick() {
+        let markers = ReadMarkers::new();
+        markers.mark("Alice", "general", 10).await;
+
+        assert_eq!(markers.get("alice", "general").await, Some(10));
+        assert_eq!(markers.get("alice", "dev").await, None);
+        assert_eq!(markers.get("bob", "general").await, None);
+    }
+
+    #[tokio::test]
+    async fn test_markers_only_move_forward() {
+        let markers = ReadMarkers::new();
+        markers.mark("alice", "general", 10).await;
+        markers.mark("alice", "general", 4).await;
+        assert_eq!(markers.get("alice", "general").await, Some(10));
+
+        markers.mark("alice", "general", 12).await;
+        assert_eq!(markers.get("alice", "general").await, Some(12));
+    }
+}
@@ -1,6 +1,5 @@
 use chat_core::server::server_config::ServerConfig;
 use chat_core::server::server::ChatServer;
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -11,7 +10,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_target(false)
         .init();
 
-    let config = ServerConfig::default();
+    let config = ServerConfig::from_env()?;
     let server = ChatServer::new(config);
     
     server.run().await
@@ -1,27 +1,58 @@
 use std::net::{IpAddr, SocketAddr, Ipv4Addr};
 use chrono::{DateTime, Utc};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum MessageType {
     Chat,
+    /// `/me` emote, shown as "* alice waves"
+    Action,
     Whisper,
     System,
     Command,
     UserList,
     RoomList,
     RoomJoin,
+    NickChange,
+    Edit,
+    Delete,
+    Reaction,
+    /// A message from another room that mentions the recipient
+    Mention,
+    /// `content` is "start" or "stop", never stored
+    Typing,
+    /// `content` is the room's new topic, empty when cleared
+    Topic,
 }
 
-#[derive(Debug, Clone)]
+/// Everyone who reacted to a message with one emoji
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reaction {
+    pub emoji: String,
+    pub users: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ChatMessage {
+    /// Assigned by the server when a room message is recorded
+    pub id: Option<u64>,
+    /// Id of the message this one answers
+    pub reply_to: Option<u64>,
     pub content: String,
     pub sender_addr: SocketAddr,
     pub sender_name: String,
     pub room: String,
     pub message_type: MessageType,
     pub target: Option<SocketAddr>,
+    /// Recipient nick of a whisper
+    pub target_name: Option<String>,
     pub color: String,
     pub timestamp: DateTime<Utc>,
+    pub edited: bool,
+    pub deleted: bool,
+    /// In the order they were first used
+    pub reactions: Vec<Reaction>,
+    /// Nicks of the online users addressed with `@nick`
+    pub mentions: Vec<String>,
 }
 
 impl ChatMessage {
@@ -35,90 +66,265 @@ impl ChatMessage {
         use crate::utils::color_manager::ColorGenerator;
         let color = ColorGenerator::generate_user_color(&sender_name);
         Self {
+            id: None,
+            reply_to: None,
             content, 
             sender_addr,
             sender_name,
             room,
             message_type: MessageType::Chat,
             target: None,
+            target_name: None,
             color,
-            timestamp: Utc::now()
+            timestamp: Utc::now(),
+            edited: false,
+            deleted: false,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
         }
     }
 
     pub fn system(content: String, room: String) -> Self {
         Self {
+            id: None,
+            reply_to: None,
             content,
             sender_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0,0,0,0)), 0),
             sender_name: "SYSTEM".to_string(),
             room,
             message_type: MessageType::System,
             target: None,
+            target_name: None,
             color: "#808080".to_string(),
             timestamp: Utc::now(),
+            edited: false,
+            deleted: false,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
         }
     }
 
+    /// A system line for `addr` alone, like the `/help` listing
+    pub fn notice(content: String, addr: SocketAddr) -> Self {
+        Self {
+            target: Some(addr),
+            ..Self::system(content, String::new())
+        }
+    }
+
+    /// `target` is `None` while the recipient is offline
     pub fn whisper(
         content: String,
         sender_addr: SocketAddr,
         sender_name: String,
-        target: SocketAddr,
+        target: Option<SocketAddr>,
+        target_name: String,
     ) -> Self {
 
         use crate::utils::color_manager::ColorGenerator;
         let color = ColorGenerator::generate_user_color(&sender_name);
 
         Self {
+            id: None,
+            reply_to: None,
             content,
             sender_addr,
             sender_name,
             room: "private".to_string(),
             message_type: MessageType::Whisper,
-            target: Some(target),
+            target,
+            target_name: Some(target_name),
             color,
             timestamp: Utc::now(),
+            edited: false,
+            deleted: false,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
         }
     }
 
     pub fn user_list(users: Vec<String>, room: String) -> Self {
         let content = users.join(",");
         Self {
+            id: None,
+            reply_to: None,
             content,
             sender_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0,0,0,0)), 0),
             sender_name: "SYSTEM".to_string(),
             room,
             message_type: MessageType::UserList,
             target: None,
+            target_name: None,
             color: "#808080".to_string(),
             timestamp: Utc::now(),
+            edited: false,
+            deleted: false,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
         }
     }
 
     pub fn room_list(rooms: Vec<String>) -> Self {
         let content = rooms.join(",");
         Self {
+            id: None,
+            reply_to: None,
             content,
             sender_addr: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0,0,0,0)), 0),
             sender_name: "SYSTEM".to_string(),
             room: "system".to_string(),
             message_type: MessageType::RoomList,
             target: None,
+            target_name: None,
             color: "#808080".to_string(),
             timestamp: Utc::now(),
+            edited: false,
+            deleted: false,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
         }
     }
 
     pub fn room_joined(room_name: String, addr: SocketAddr) -> Self {
         Self {
+            id: None,
+            reply_to: None,
             content: room_name.clone(),
             sender_addr: addr,
             sender_name: "SYSTEM".to_string(),
             room: room_name,
             message_type: MessageType::RoomJoin,
             target: Some(addr),
+            target_name: None,
             color: "#808080".to_string(),
             timestamp: Utc::now(),
+            edited: false,
+            deleted: false,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
         }
     }
-}
\ No newline at end of file
+
+    /// `sender_name` holds the previous nick and `content` the new one
+    pub fn nick_changed(old_name: String, new_name: String, addr: SocketAddr, room: String) -> Self {
+        Self {
+            id: None,
+            reply_to: None,
+            content: new_name,
+            sender_addr: addr,
+            sender_name: old_name,
+            room,
+            message_type: MessageType::NickChange,
+            target: None,
+            target_name: None,
+            color: "#808080".to_string(),
+            timestamp: Utc::now(),
+            edited: false,
+            deleted: false,
+            reactions: Vec::new(),
+            mentions: Vec::new(),
+        }
+    }
+
+    /// Replacement content for message `id`, applied in place by clients
+    pub fn edit(id: u64, content: String, editor_addr: SocketAddr, room: String) -> Self {
+        let mut msg = Self::system(content, room);
+        msg.id = Some(id);
+        msg.sender_addr = editor_addr;
+        msg.message_type = MessageType::Edit;
+        msg.edited = true;
+        msg
+    }
+
+    /// A `/me` emote posted to a room
+    pub fn action(content: String, sender_addr: SocketAddr, sender_name: String, room: String) -> Self {
+        let mut msg = Self::new(content, sender_addr, sender_name, room);
+        msg.message_type = MessageType::Action;
+        msg
+    }
+
+    pub fn typing(name: String, addr: SocketAddr, room: String, started: bool) -> Self {
+        let mut msg = Self::system(if started { "start" } else { "stop" }.to_string(), room);
+        msg.sender_addr = addr;
+        msg.sender_name = name;
+        msg.message_type = MessageType::Typing;
+        msg
+    }
+
+    pub fn topic(topic: Option<String>, addr: SocketAddr, room: String) -> Self {
+        let mut msg = Self::system(topic.unwrap_or_default(), room);
+        msg.sender_addr = addr;
+        msg.message_type = MessageType::Topic;
+        msg
+    }
+
+    pub fn mentions_user(&self, name: &str) -> bool {
+        self.mentions.iter().any(|m| m == name)
+    }
+
+    /// Delta for the reactions of message `id`: `sender_name` added `content`,
+    /// or removed it when `deleted` is set
+    pub fn reaction(id: u64, emoji: String, user: String, added: bool, room: String) -> Self {
+        let mut msg = Self::system(emoji, room);
+        msg.id = Some(id);
+        msg.sender_name = user;
+        msg.message_type = MessageType::Reaction;
+        msg.deleted = !added;
+        msg
+    }
+
+    /// Adds or removes `user`'s `emoji`. Returns true when it was added.
+    pub fn toggle_reaction(&mut self, emoji: &str, user: &str) -> bool {
+        let added = match self.reactions.iter_mut().find(|r| r.emoji == emoji) {
+            Some(reaction) => match reaction.users.iter().position(|u| u == user) {
+                Some(idx) => {
+                    reaction.users.remove(idx);
+                    false
+                }
+                None => {
+                    reaction.users.push(user.to_string());
+                    true
+                }
+            },
+            None => {
+                self.reactions.push(Reaction {
+                    emoji: emoji.to_string(),
+                    users: vec![user.to_string()],
+                });
+                true
+            }
+        };
+        self.reactions.retain(|r| !r.users.is_empty());
+        added
+    }
+
+    /// Tombstone for message `id`
+    pub fn delete(id: u64, editor_addr: SocketAddr, room: String) -> Self {
+        let mut msg = Self::system(String::new(), room);
+        msg.id = Some(id);
+        msg.sender_addr = editor_addr;
+        msg.message_type = MessageType::Delete;
+        msg.deleted = true;
+        msg
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toggle_reaction() {
+        let mut msg = ChatMessage::system("hi".to_string(), "general".to_string());
+
+        assert!(msg.toggle_reaction("👍", "alice"));
+        assert!(msg.toggle_reaction("👍", "bob"));
+        assert!(msg.toggle_reaction("🎉", "alice"));
+        assert_eq!(msg.reactions[0].users, vec!["alice", "bob"]);
+
+        assert!(!msg.toggle_reaction("👍", "alice"));
+        assert!(!msg.toggle_reaction("🎉", "alice"));
+        assert_eq!(msg.reactions, vec![Reaction { emoji: "👍".to_string(), users: vec!["bob".to_string()] }]);
+    }
+}
@@ -1,13 +1,33 @@
-use crate::server::room_manager::RoomManager;
+use crate::server::room_manager::{JoinError, RoomManager};
+use crate::server::room_policy::RoomPolicy;
 use crate::client::client_manager::ClientManager;
+use crate::client::nick_policy::NickPolicy;
+use crate::client::presence::{Presence, MAX_AWAY_MESSAGE_LENGTH};
 use std::net::SocketAddr;
+use std::time::Duration;
 use crate::message::chat_message::ChatMessage;
+use crate::message::command_registry;
+use unicode_segmentation::UnicodeSegmentation;
 
 pub enum CommandResult {
     ChangeNick(String),
     JoinRoom(String, Option<String>),
     CreateRoom(String, Option<String>),
     InviteUser(String, String),
+    SetMemberLimit(Option<usize>),      // None turns the limit off
+    SetSlowMode(Option<u64>),           // Seconds between posts, None turns it off
+    SetTopic(Option<String>),           // None clears it
+    RoomInfo(Option<String>),
+    Whois(String),
+    Away(Option<String>),               // Optional status message
+    Here,
+    Typing(bool),                       // Started or stopped composing
+    MarkRead(String, u64),              // Room and last message id displayed
+    Reply(u64, String),                 // Parent message id and text
+    Me(String),                         // Emote text
+    React(u64, String),                 // Message id and emoji, toggles
+    EditMessage(u64, String),
+    DeleteMessage(u64),
     ListUsers,
     ListRooms,
     Whisper(String, String),            // Target name e message
@@ -17,6 +37,29 @@ pub enum CommandResult {
     InvalidCommand(String),
 }
 
+/// Why a command was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum CommandError {
+    /// Sent back to the user as a system line
+    Message(String),
+    /// The room is locked and the password was missing or `wrong`
+    PasswordRequired { room: String, wrong: bool },
+    /// `/create` was refused, with the reason
+    CreateRefused(String),
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Message(message)
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::Message(message.to_string())
+    }
+}
+
 pub struct CommandProcessor;
 
 impl CommandProcessor {
@@ -32,7 +75,8 @@ impl CommandProcessor {
             return Some(CommandResult::InvalidCommand("Empty command".to_string()));
         }
 
-        match parts[0] {
+        let command = command_registry::find(parts[0]).map_or(parts[0], |spec| spec.name);
+        match command {
             "nick" => {
                 if parts.len() < 2 {
                     return Some(CommandResult::InvalidCommand(
@@ -74,26 +118,129 @@ impl CommandProcessor {
                 Some(CommandResult::InviteUser(username, room_name))
             }
 
+            "limit" => {
+                match parts.get(1).map(|arg| Self::parse_optional_number(arg)) {
+                    Some(Some(limit)) => Some(CommandResult::SetMemberLimit(limit.map(|n| n as usize))),
+                    _ => Some(CommandResult::InvalidCommand(
+                        "Usage: /limit <max_members|off>".to_string()
+                    )),
+                }
+            }
+
+            "slowmode" => {
+                match parts.get(1).map(|arg| Self::parse_optional_number(arg)) {
+                    Some(Some(seconds)) => Some(CommandResult::SetSlowMode(seconds)),
+                    _ => Some(CommandResult::InvalidCommand(
+                        "Usage: /slowmode <seconds|off>".to_string()
+                    )),
+                }
+            }
+
+            "topic" => {
+                match Self::text_after(input, 1).trim() {
+                    "" => Some(CommandResult::InvalidCommand("Usage: /topic <text|off>".to_string())),
+                    "off" => Some(CommandResult::SetTopic(None)),
+                    topic => Some(CommandResult::SetTopic(Some(topic.to_string()))),
+                }
+            }
+
+            "info" => Some(CommandResult::RoomInfo(parts.get(1).map(|s| s.to_string()))),
+
+            "whois" => {
+                if parts.len() < 2 {
+                    return Some(CommandResult::InvalidCommand(
+                        "Usage: /whois <user>".to_string()
+                    ));
+                }
+                Some(CommandResult::Whois(parts[1].to_string()))
+            }
+
+            "reply" => {
+                match (parts.get(1).and_then(|id| Self::parse_message_id(id)), parts.len() > 2) {
+                    (Some(id), true) => Some(CommandResult::Reply(id, Self::text_after(input, 2))),
+                    _ => Some(CommandResult::InvalidCommand(
+                        "Usage: /reply <message_id> <text>".to_string()
+                    )),
+                }
+            }
+
+            "me" => {
+                if parts.len() > 1 {
+                    Some(CommandResult::Me(Self::text_after(input, 1)))
+                } else {
+                    Some(CommandResult::InvalidCommand("Usage: /me <action>".to_string()))
+                }
+            }
+
+            "react" => {
+                match (parts.get(1).and_then(|id| Self::parse_message_id(id)), parts.get(2)) {
+                    (Some(id), Some(emoji)) => Some(CommandResult::React(id, emoji.to_string())),
+                    _ => Some(CommandResult::InvalidCommand(
+                        "Usage: /react <message_id> <emoji>".to_string()
+                    )),
+                }
+            }
+
+            "edit" => {
+                match (parts.get(1).and_then(|id| Self::parse_message_id(id)), parts.len() > 2) {
+                    (Some(id), true) => Some(CommandResult::EditMessage(id, Self::text_after(input, 2))),
+                    _ => Some(CommandResult::InvalidCommand(
+                        "Usage: /edit <message_id> <new text>".to_string()
+                    )),
+                }
+            }
+
+            "delete" => {
+                match parts.get(1).and_then(|id| Self::parse_message_id(id)) {
+                    Some(id) => Some(CommandResult::DeleteMessage(id)),
+                    None => Some(CommandResult::InvalidCommand(
+                        "Usage: /delete <message_id>".to_string()
+                    )),
+                }
+            }
+
+            "away" => {
+                let message = parts[1..].join(" ");
+                Some(CommandResult::Away((!message.is_empty()).then_some(message)))
+            }
+
+            "here" => Some(CommandResult::Here),
+
+            "typing" => match parts.get(1) {
+                Some(&"start") => Some(CommandResult::Typing(true)),
+                Some(&"stop") => Some(CommandResult::Typing(false)),
+                _ => Some(CommandResult::InvalidCommand(
+                    "Usage: /typing <start|stop>".to_string()
+                )),
+            },
+
+            "read" => match (parts.get(1), parts.get(2).and_then(|id| Self::parse_message_id(id))) {
+                (Some(room), Some(id)) => Some(CommandResult::MarkRead(room.to_string(), id)),
+                _ => Some(CommandResult::InvalidCommand(
+                    "Usage: /read <room> <message id>".to_string()
+                )),
+            },
+
             "list" => Some(CommandResult::ListUsers),
 
             "rooms" => Some(CommandResult::ListRooms),
 
-            "whisper" | "w" => {
+            "whisper" => {
                 if parts.len() < 3 {
                     return Some(CommandResult::InvalidCommand(
                         "Usage: /w <user> <message>".to_string()
                     ));
                 }
                 let target = parts[1].to_string();
-                let msg = parts[2..].join(" ");
+                let msg = Self::text_after(input, 2);
                 Some(CommandResult::Whisper(target, msg))
             },
 
-            "leave" | "back" => Some(CommandResult::LeaveToGeneral),
+            "leave" => Some(CommandResult::LeaveToGeneral),
 
-            "quit" | "exit" => Some(CommandResult::Quit),
+            "quit" => Some(CommandResult::Quit),
 
-            "help" | "?" => Some(CommandResult::Help),
+            "help" => Some(CommandResult::Help),
 
             _ => Some(CommandResult::InvalidCommand(
                 format!("Unknown command: {}", parts[0])
@@ -102,38 +249,114 @@ impl CommandProcessor {
 
     }
 
+    /// Free text after the first `words` words, the command name included,
+    /// with its spacing and line breaks kept
+    fn text_after(input: &str, words: usize) -> String {
+        let mut rest = input[1..].trim_start();
+        for _ in 0..words {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            rest = rest[end..].trim_start();
+        }
+        rest.trim_end().to_string()
+    }
+
+    /// Parses "off"/"0" as `None` and a positive number as `Some(n)`
+    fn parse_optional_number(arg: &str) -> Option<Option<u64>> {
+        if arg.eq_ignore_ascii_case("off") {
+            return Some(None);
+        }
+        match arg.parse::<u64>() {
+            Ok(0) => Some(None),
+            Ok(n) => Some(Some(n)),
+            Err(_) => None,
+        }
+    }
+
+
+    /// A reaction is a single symbol, not a word. ASCII is refused too: it
+    /// holds the frame separators ('|', ',', ':') and the escape character.
+    fn is_reaction(emoji: &str) -> bool {
+        emoji.graphemes(true).count() == 1
+            && !emoji.is_ascii()
+            && !emoji.chars().any(|c| c.is_alphanumeric())
+    }
+
+    /// Accepts "42" as well as "#42", the way ids are shown
+    fn parse_message_id(arg: &str) -> Option<u64> {
+        arg.trim_start_matches('#').parse().ok()
+    }
+
+
+    /// "45s", "12m", "3h 5m", "2d 4h"
+    fn format_elapsed(elapsed: chrono::Duration) -> String {
+        let secs = elapsed.num_seconds().max(0);
+        match secs {
+            0..=59 => format!("{}s", secs),
+            60..=3599 => format!("{}m", secs / 60),
+            3600..=86399 => format!("{}h {}m", secs / 3600, secs % 3600 / 60),
+            _ => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+        }
+    }
+
+    /// Fresh user list of the caller's room, broadcast after a presence change
+    async fn room_user_list(
+        addr: SocketAddr,
+        client_manager: &ClientManager,
+        room_manager: &RoomManager,
+    ) -> Result<Option<ChatMessage>, CommandError> {
+        let room_name = room_manager.get_user_room(&addr).await
+            .ok_or_else(|| "✗ You are not in a room".to_string())?;
+        let members = room_manager.get_room_members(&room_name).await;
+        let users = client_manager.user_list_entries(&members).await;
+        Ok(Some(ChatMessage::user_list(users, room_name)))
+    }
 
     pub async fn execute(
         result: CommandResult,
         addr: SocketAddr,
         client_manager: &ClientManager,
         room_manager: &RoomManager
-    ) -> Result<Option<ChatMessage>, String> {
+    ) -> Result<Option<ChatMessage>, CommandError> {
         match result {
             CommandResult::ChangeNick(new_name) => {
-                if client_manager.is_name_available(&new_name).await {
-                    client_manager.update_client_name(addr, new_name.clone()).await;
-                    Ok(None)
-                } else {
-                    Err("✗ Name already taken".to_string())
+                let new_name = NickPolicy::normalize(&new_name)
+                    .map_err(|e| format!("✗ Invalid name: {}", e))?;
+                if let Some(owner) = client_manager.get_client_by_name(&new_name).await {
+                    if owner != addr {
+                        return Err("✗ Name already taken".into());
+                    }
                 }
+                let old_name = client_manager.get_clients_name(&addr).await
+                    .ok_or_else(|| "✗ You are not registered".to_string())?;
+                if old_name == new_name {
+                    return Err(format!("✗ You are already called {}", new_name).into());
+                }
+
+                client_manager.update_client_name(addr, new_name.clone()).await;
+                let room = room_manager.get_user_room(&addr).await.unwrap_or_default();
+                Ok(Some(ChatMessage::nick_changed(old_name, new_name, addr, room)))
             },
 
             CommandResult::JoinRoom(room, password) => {
+                // Join before leaving, so a refused join keeps us where we were
                 let current_room = room_manager.get_user_room(&addr).await;
-                if let Some(curr) = &current_room {
-                    room_manager.leave_room(curr, &addr).await;
-                }
                 match room_manager.join_room(&room, addr, password.as_deref()).await {
                     Ok(_) => {
+                        if let Some(curr) = current_room.filter(|curr| *curr != room) {
+                            room_manager.leave_room(&curr, &addr).await;
+                        }
                         let msg = ChatMessage::room_joined(room, addr);
                         Ok(Some(msg))
                     }
-                    Err(e) => Err(format!("✗ {}", e)),
+                    Err(JoinError::PasswordRequired) => Err(CommandError::PasswordRequired { room, wrong: false }),
+                    Err(JoinError::WrongPassword) => Err(CommandError::PasswordRequired { room, wrong: true }),
+                    Err(e) => Err(format!("✗ {}", e).into()),
                 }
             }
 
             CommandResult::CreateRoom(room_name, password) => {
+                RoomPolicy::validate_name(&room_name)
+                    .map_err(|e| CommandError::CreateRefused(e.to_string()))?;
                 match room_manager.create_room(room_name.clone(), password.clone(), addr).await {
                     Ok(_) => {
                         // Leaves room first
@@ -147,17 +370,17 @@ impl CommandProcessor {
                                 let msg = ChatMessage::room_joined(room_name, addr);
                                 Ok(Some(msg))
                             },
-                            Err(e) => Err(format!("✗ Room created but failed to join: {}", e))
+                            Err(e) => Err(CommandError::CreateRefused(format!("Room created but failed to join: {}", e)))
                         }
                     }
-                    Err(e) => Err(format!("x {}", e))
+                    Err(e) => Err(CommandError::CreateRefused(e)),
                 }
             },
 
             CommandResult::LeaveToGeneral => {
                 if let Some(curr_room) = room_manager.get_user_room(&addr).await {
                     if curr_room == "general" {
-                        return Err("You are already in 'general' room".to_string());
+                        return Err("You are already in 'general' room".into());
                     }
                     room_manager.leave_room(&curr_room, &addr).await;
                     match room_manager.join_room("general", addr, None).await {
@@ -165,23 +388,23 @@ impl CommandProcessor {
                             let msg = ChatMessage::room_joined("general".to_string(), addr);
                             Ok(Some(msg))
                         }
-                        Err(e) => Err(format!("✗ Error returning to general: {}", e)),
+                        Err(e) => Err(format!("✗ Error returning to general: {}", e).into()),
                     }
                 } else {
-                    Err("✗ You are not in any room".to_string())
+                    Err("✗ You are not in any room".into())
                 }
             }
 
             CommandResult::InviteUser(username, room_name) => {
                 let room_info = room_manager.get_room_info(&room_name).await;
                 if room_info.is_none() {
-                    return Err(format!("✗ Room '{}' does not exist", room_name));
+                    return Err(format!("✗ Room '{}' does not exist", room_name).into());
                 }
 
                 let (owner_addr, password) = room_info.unwrap();
                 // Verify if is the owner who is inviting
                 if owner_addr != addr {
-                    return Err("✗ Only the owner of this room can invite users".to_string());
+                    return Err("✗ Only the owner of this room can invite users".into());
                 }
 
                 // Search the user
@@ -189,42 +412,238 @@ impl CommandProcessor {
                     if let Some(sender_name) = client_manager.get_clients_name(&addr).await {
                         let invite_msg = if let Some(pwd) = password {
                             format!(
-                                "\n📨 {} invited you to join room '{}'. Password: {}\nUse: /join {} {}\n",
-                                sender_name, room_name, pwd, room_name, pwd
+                                "📨 {} invited you to join room '{}'. Use: /join {} {}",
+                                sender_name, room_name, room_name, pwd
                             )
                         } else {
                             format!(
-                                "📨 {} invited you to join room '{}'\nUse: /join {}",
+                                "📨 {} invited you to join room '{}'. Use: /join {}",
                                 sender_name, room_name, room_name
                             )
                         };
 
+                        let target_name = client_manager.get_clients_name(&target_addr).await
+                            .unwrap_or(username);
                         let whisper_msg = ChatMessage::whisper(
                             invite_msg,
                             addr,
                             sender_name,
-                            target_addr,
+                            Some(target_addr),
+                            target_name,
                         );
                         return Ok(Some(whisper_msg));
                     }
                 }
-                Err(format!("✗ User '{}' not found", username))
+                Err(format!("✗ User '{}' not found", username).into())
+            }
+
+            CommandResult::SetMemberLimit(limit) => {
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                room_manager.set_max_members(&room_name, addr, limit).await
+                    .map_err(|e| format!("✗ {}", e))?;
+                match limit {
+                    Some(max) => Err(format!("✓ Room '{}' is now limited to {} members", room_name, max).into()),
+                    None => Err(format!("✓ Member limit removed from '{}'", room_name).into()),
+                }
+            }
+
+            CommandResult::SetSlowMode(seconds) => {
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                let interval = seconds.map(Duration::from_secs);
+                room_manager.set_slow_mode(&room_name, addr, interval).await
+                    .map_err(|e| format!("✗ {}", e))?;
+                match seconds {
+                    Some(secs) => Err(format!("✓ Slow mode in '{}': one message every {}s", room_name, secs).into()),
+                    None => Err(format!("✓ Slow mode disabled in '{}'", room_name).into()),
+                }
+            }
+
+            CommandResult::SetTopic(topic) => {
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                if let Some(topic) = &topic {
+                    RoomPolicy::validate_topic(topic).map_err(|e| format!("✗ {}", e))?;
+                }
+                room_manager.set_topic(&room_name, addr, topic.clone()).await
+                    .map_err(|e| format!("✗ {}", e))?;
+                Ok(Some(ChatMessage::topic(topic, addr, room_name)))
+            }
+
+            CommandResult::RoomInfo(room) => {
+                let room_name = match room {
+                    Some(name) => name,
+                    None => room_manager.get_user_room(&addr).await
+                        .ok_or_else(|| "✗ You are not in a room".to_string())?,
+                };
+                let (owner_addr, password) = room_manager.get_room_info(&room_name).await
+                    .ok_or_else(|| format!("✗ Room '{}' does not exist", room_name))?;
+                let settings = room_manager.get_room_settings(&room_name).await.unwrap_or_default();
+                let members = room_manager.get_room_members(&room_name).await.len();
+
+                let owner = client_manager.get_clients_name(&owner_addr).await
+                    .unwrap_or_else(|| "server".to_string());
+                let capacity = match settings.max_members {
+                    Some(max) => format!("{}/{}", members, max),
+                    None => format!("{}", members),
+                };
+                let slow_mode = match settings.slow_mode {
+                    Some(interval) => format!("{}s", interval.as_secs()),
+                    None => "off".to_string(),
+                };
+
+                let mut info = format!(
+                    "Room '{}' | owner: {} | members: {} | slow mode: {} | password: {}",
+                    room_name,
+                    owner,
+                    capacity,
+                    slow_mode,
+                    if password.is_some() { "yes" } else { "no" },
+                );
+                if let Some(topic) = settings.topic {
+                    info.push_str(&format!(" | topic: {}", topic));
+                }
+                Err(info.into())
+            }
+
+            CommandResult::Whois(name) => {
+                let target_addr = client_manager.get_client_by_name(&name).await
+                    .ok_or_else(|| format!("✗ User '{}' not found", name))?;
+                let info = client_manager.get_client_info(&target_addr).await
+                    .ok_or_else(|| format!("✗ User '{}' not found", name))?;
+                let room = room_manager.get_user_room(&target_addr).await
+                    .unwrap_or_else(|| "-".to_string());
+                let previous = if info.previous_names.is_empty() {
+                    "none".to_string()
+                } else {
+                    info.previous_names.join(", ")
+                };
+                let status = match client_manager.get_presence(&target_addr).await {
+                    Some(Presence::Away(message)) if !message.is_empty() => format!("away ({})", message),
+                    Some(Presence::Away(_)) => "away".to_string(),
+                    Some(Presence::Idle) => "idle".to_string(),
+                    _ => "online".to_string(),
+                };
+                let now = chrono::Utc::now();
+
+                Err(format!(
+                    "{} | {} | room: {} | connected: {} ago | last active: {} ago | previously: {}",
+                    info.name,
+                    status,
+                    room,
+                    Self::format_elapsed(now - info.connected_at),
+                    Self::format_elapsed(now - info.last_active),
+                    previous,
+                ).into())
+            }
+
+            CommandResult::Reply(parent_id, content) => {
+                let sender_name = client_manager.get_clients_name(&addr).await
+                    .ok_or_else(|| "✗ You are not registered".to_string())?;
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                if room_manager.get_message(&room_name, parent_id).await.is_none() {
+                    return Err(format!("✗ Message #{} not found in '{}'", parent_id, room_name).into());
+                }
+                room_manager.register_post(&room_name, addr).await
+                    .map_err(|e| format!("✗ {}", e))?;
+
+                let mentions = client_manager.resolve_mentions(&content).await;
+                let mut reply = ChatMessage::new(content, addr, sender_name, room_name);
+                reply.reply_to = Some(parent_id);
+                reply.mentions = mentions;
+                Ok(Some(room_manager.record_message(reply).await))
+            }
+
+            CommandResult::Me(content) => {
+                let sender_name = client_manager.get_clients_name(&addr).await
+                    .ok_or_else(|| "✗ You are not registered".to_string())?;
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                room_manager.register_post(&room_name, addr).await
+                    .map_err(|e| format!("✗ {}", e))?;
+
+                let mentions = client_manager.resolve_mentions(&content).await;
+                let mut action = ChatMessage::action(content, addr, sender_name, room_name);
+                action.mentions = mentions;
+                Ok(Some(room_manager.record_message(action).await))
+            }
+
+            CommandResult::React(id, emoji) => {
+                if !Self::is_reaction(&emoji) {
+                    return Err(format!("✗ '{}' is not a single emoji", emoji).into());
+                }
+                let sender_name = client_manager.get_clients_name(&addr).await
+                    .ok_or_else(|| "✗ You are not registered".to_string())?;
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                room_manager.toggle_reaction(&room_name, id, &emoji, &sender_name).await
+                    .map(Some)
+                    .map_err(|e| format!("✗ {}", e).into())
+            }
+
+            CommandResult::EditMessage(id, content) => {
+                let name = client_manager.get_clients_name(&addr).await
+                    .ok_or_else(|| "✗ You are not registered".to_string())?;
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                room_manager.edit_message(&room_name, id, addr, &name, content).await
+                    .map(Some)
+                    .map_err(|e| format!("✗ {}", e).into())
+            }
+
+            CommandResult::DeleteMessage(id) => {
+                let name = client_manager.get_clients_name(&addr).await
+                    .ok_or_else(|| "✗ You are not registered".to_string())?;
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                room_manager.delete_message(&room_name, id, addr, &name).await
+                    .map(Some)
+                    .map_err(|e| format!("✗ {}", e).into())
+            }
+
+            CommandResult::Away(message) => {
+                let message = message.unwrap_or_default();
+                if message.chars().count() > MAX_AWAY_MESSAGE_LENGTH {
+                    return Err(format!("✗ Away message is limited to {} characters", MAX_AWAY_MESSAGE_LENGTH).into());
+                }
+                client_manager.set_away(&addr, Some(message)).await;
+                Self::room_user_list(addr, client_manager, room_manager).await
+            }
+
+            CommandResult::Here => {
+                client_manager.set_away(&addr, None).await;
+                Self::room_user_list(addr, client_manager, room_manager).await
+            }
+
+            CommandResult::Typing(started) => {
+                let name = client_manager.get_clients_name(&addr).await
+                    .ok_or_else(|| "✗ You are not registered".to_string())?;
+                let room_name = room_manager.get_user_room(&addr).await
+                    .ok_or_else(|| "✗ You are not in a room".to_string())?;
+                Ok(Some(ChatMessage::typing(name, addr, room_name, started)))
+            }
+
+            CommandResult::MarkRead(room_name, id) => {
+                if room_manager.get_room_info(&room_name).await.is_none() {
+                    return Err(format!("✗ Room '{}' not found", room_name).into());
+                }
+                // Nothing past what was posted can have been read
+                if let Some(latest) = room_manager.latest_message_id(&room_name).await {
+                    client_manager.mark_read(&addr, &room_name, id.min(latest)).await;
+                }
+                Ok(None)
             }
 
             CommandResult::ListUsers => {
                 if let Some(room_name) = room_manager.get_user_room(&addr).await {
                     let members = room_manager.get_room_members(&room_name).await;
-                    let mut users = Vec::new();
-
-                    for member_addr in members {
-                        if let Some(name) = client_manager.get_clients_name(&member_addr).await {
-                            users.push(name);
-                        }
-                    }
+                    let users = client_manager.user_list_entries(&members).await;
                     let msg = ChatMessage::user_list(users, room_name);
                     Ok(Some(msg))
                 } else {
-                    Err("You are not in a room".to_string())
+                    Err("You are not in a room".into())
                 }
             },
 
@@ -236,44 +655,102 @@ impl CommandProcessor {
                     let lock = if protected {"🔒"} else { "" };
                     output.push_str(&format!("  {} {} ({} users)\n", lock, name, count));
                 }
-                Err(output)
+                Err(output.into())
             },
 
             CommandResult::Whisper(target_name, message) => {
+                let sender_name = client_manager.get_clients_name(&addr).await
+                    .ok_or_else(|| "✗ You are not registered".to_string())?;
+
                 // Search user by name
                 if let Some(target_addr) = client_manager.get_client_by_name(&target_name).await {
-                    if let Some(sender_name) = client_manager.get_clients_name(&addr).await {
-                        let whisper_msg = ChatMessage::whisper(
-                            message,
-                            addr,
-                            sender_name,
-                            target_addr,
-                        );
-                        return Ok(Some(whisper_msg));
-                    }
+                    let target_name = client_manager.get_clients_name(&target_addr).await
+                        .unwrap_or(target_name);
+                    client_manager.note_whisper(&addr, &target_addr).await;
+                    let whisper_msg = ChatMessage::whisper(
+                        message,
+                        addr,
+                        sender_name,
+                        Some(target_addr),
+                        target_name,
+                    );
+                    return Ok(Some(whisper_msg));
+                }
+
+                // Known but offline: keep it for their next login from the same address.
+                // The returned copy has no target, which tells the connection it was deferred.
+                if let Some(known_name) = client_manager.get_known_name(&target_name).await {
+                    let queued = ChatMessage::whisper(message, addr, sender_name, None, known_name.clone());
+                    client_manager.queue_offline_whisper(&known_name, queued.clone()).await
+                        .map_err(|e| format!("✗ {}", e))?;
+                    return Ok(Some(queued));
                 }
-                return Err(format!("User '{}' not found", target_name));
+                Err(format!("User '{}' not found", target_name).into())
             },
 
             CommandResult::Quit => Ok(None),
 
-            CommandResult::Help => Err(
-                String::from(
-                    "\n\n--------------- Available Commands ---------------\n\
-                    /nick <name>            - Change your nickname\n\
-                    /create <room> [pwd]    - Create a new room\n\
-                    /join <room> [pwd]      - Join a room\n\
-                    /leave or /back         - Return to general room\n\
-                    /invite <user> <room>   - Invite user to your room\n\
-                    /list                   - List users in current room\n\
-                    /rooms                  - List all rooms\n\
-                    /w <user> <msg>         - Send private message\n\
-                    /help                   - Show this help\n\
-                    /quit                   - Exit chat\n\
-                    --------------------------------------------------\n\n"
-                )),
+            CommandResult::Help => Ok(Some(ChatMessage::notice(command_registry::help_text(), addr))),
 
-            CommandResult::InvalidCommand(msg) => Err(format!("✗ {}",msg)),
+            CommandResult::InvalidCommand(msg) => Err(format!("✗ {}",msg).into()),
         }
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn run(input: &str, addr: SocketAddr, clients: &ClientManager, rooms: &RoomManager) -> Result<Option<ChatMessage>, CommandError> {
+        let command = CommandProcessor::parse(input).expect("a command");
+        CommandProcessor::execute(command, addr, clients, rooms).await
+    }
+
+    #[test]
+    fn test_parse_me() {
+        // Spacing inside the emote is kept
+        assert!(matches!(CommandProcessor::parse("/me waves  at  everyone"), Some(CommandResult::Me(text)) if text == "waves  at  everyone"));
+        assert!(matches!(CommandProcessor::parse("/me   "), Some(CommandResult::InvalidCommand(usage)) if usage == "Usage: /me <action>"));
+    }
+
+    #[test]
+    fn test_parse_leave_and_here() {
+        assert!(matches!(CommandProcessor::parse("/leave"), Some(CommandResult::LeaveToGeneral)));
+        assert!(matches!(CommandProcessor::parse("/back"), Some(CommandResult::LeaveToGeneral)));
+        assert!(matches!(CommandProcessor::parse("/here"), Some(CommandResult::Here)));
+    }
+
+    #[tokio::test]
+    async fn test_reactions_are_single_emoji() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 1000));
+        let clients = ClientManager::new();
+        let rooms = RoomManager::new();
+        clients.register_client(addr, "alice".to_string()).await;
+        rooms.join_room("general", addr, None).await.unwrap();
+        let msg = rooms.record_message(ChatMessage::new("hi".into(), addr, "alice".into(), "general".into())).await;
+        let id = msg.id.unwrap();
+
+        for emoji in ["|", ",", "%", ":", "a", "ok"] {
+            let result = run(&format!("/react {} {}", id, emoji), addr, &clients, &rooms).await;
+            assert!(result.is_err(), "{:?} was accepted", emoji);
+        }
+        let reaction = run(&format!("/react {} 👍", id), addr, &clients, &rooms).await.unwrap().unwrap();
+        assert_eq!(reaction.content, "👍");
+    }
+
+    #[tokio::test]
+    async fn test_read_marker_stops_at_the_newest_message() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 1000));
+        let clients = ClientManager::new();
+        let rooms = RoomManager::new();
+        clients.register_client(addr, "alice".to_string()).await;
+
+        run("/read general 7", addr, &clients, &rooms).await.unwrap();
+        assert_eq!(clients.read_marker("alice", "general").await, None);
+
+        let msg = rooms.record_message(ChatMessage::new("hi".into(), addr, "alice".into(), "general".into())).await;
+        run("/read general 999999", addr, &clients, &rooms).await.unwrap();
+        assert_eq!(clients.read_marker("alice", "general").await, msg.id);
+    }
+}
@@ -0,0 +1,91 @@
+/// A command users type: what `/help` lists, what the parser accepts and
+/// what clients offer in completion and their command palette
+#[derive(Debug, PartialEq)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Other names the parser accepts
+    pub aliases: &'static [&'static str],
+    /// Argument hint, `<required> [optional]`
+    pub args: &'static str,
+    pub description: &'static str,
+}
+
+impl CommandSpec {
+    /// "/join <room> [password]"
+    pub fn usage(&self) -> String {
+        if self.args.is_empty() {
+            format!("/{}", self.name)
+        } else {
+            format!("/{} {}", self.name, self.args)
+        }
+    }
+
+    /// The aliases first, so shorthands come up before the full name
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        self.aliases.iter().copied().chain(std::iter::once(self.name))
+    }
+}
+
+/// In the order `/help` lists them. `/typing` and `/read` are sent by the
+/// TUI on its own and left out.
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec { name: "nick", aliases: &[], args: "<name>", description: "Change your nickname" },
+    CommandSpec { name: "whois", aliases: &[], args: "<user>", description: "Show who a user is" },
+    CommandSpec { name: "create", aliases: &[], args: "<room> [password]", description: "Create a new room and join it" },
+    CommandSpec { name: "join", aliases: &[], args: "<room> [password]", description: "Join a room" },
+    CommandSpec { name: "leave", aliases: &["back"], args: "", description: "Return to the general room" },
+    CommandSpec { name: "away", aliases: &[], args: "[message]", description: "Mark yourself as away" },
+    CommandSpec { name: "here", aliases: &[], args: "", description: "Clear your away status" },
+    CommandSpec { name: "invite", aliases: &[], args: "<user> <room>", description: "Invite a user to your room" },
+    CommandSpec { name: "info", aliases: &[], args: "[room]", description: "Show room settings" },
+    CommandSpec { name: "limit", aliases: &[], args: "<n|off>", description: "Set the member limit (owner)" },
+    CommandSpec { name: "slowmode", aliases: &[], args: "<secs|off>", description: "Set slow mode (owner)" },
+    CommandSpec { name: "topic", aliases: &[], args: "<text|off>", description: "Set the room topic (owner)" },
+    CommandSpec { name: "list", aliases: &[], args: "", description: "List users in the current room" },
+    CommandSpec { name: "rooms", aliases: &[], args: "", description: "List all rooms" },
+    CommandSpec { name: "whisper", aliases: &["w"], args: "<user> <message>", description: "Send a private message" },
+    CommandSpec { name: "me", aliases: &[], args: "<action>", description: "Describe what you are doing" },
+    CommandSpec { name: "reply", aliases: &[], args: "<id> <text>", description: "Reply to a message" },
+    CommandSpec { name: "react", aliases: &[], args: "<id> <emoji>", description: "Add or remove a reaction" },
+    CommandSpec { name: "edit", aliases: &[], args: "<id> <text>", description: "Edit one of your messages" },
+    CommandSpec { name: "delete", aliases: &[], args: "<id>", description: "Delete one of your messages" },
+    CommandSpec { name: "help", aliases: &["?"], args: "", description: "Show this help" },
+    CommandSpec { name: "quit", aliases: &["exit"], args: "", description: "Leave the chat" },
+];
+
+/// The command a typed name or alias stands for
+pub fn find(name: &str) -> Option<&'static CommandSpec> {
+    COMMANDS.iter().find(|spec| spec.names().any(|n| n == name))
+}
+
+/// The `/help` listing, one command per line
+pub fn help_text() -> String {
+    let width = COMMANDS.iter().map(|spec| spec.usage().chars().count()).max().unwrap_or(0);
+    let mut help = String::from("Available commands:");
+    for spec in COMMANDS {
+        help.push_str(&format!("\n{:<width$}  {}", spec.usage(), spec.description, width = width));
+    }
+    help
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_by_name_or_alias() {
+        assert_eq!(find("w").map(|spec| spec.name), Some("whisper"));
+        assert_eq!(find("join").map(|spec| spec.name), Some("join"));
+        assert_eq!(find("back").map(|spec| spec.name), Some("leave"));
+        assert_eq!(find("typing"), None);
+    }
+
+    #[test]
+    fn test_help_lists_every_command() {
+        let help = help_text();
+        assert_eq!(help.lines().count(), COMMANDS.len() + 1);
+        assert!(help.contains("/join <room> [password]"));
+        assert!(help.lines().all(|line| !line.ends_with(' ')));
+    }
+}
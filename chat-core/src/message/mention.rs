@@ -0,0 +1,55 @@
+use crate::client::nick_policy::NickPolicy;
+
+/// Nicks written as `@nick` in `content`, in order and without duplicates.
+/// An '@' preceded by a letter or digit (an e-mail address) is not a mention.
+pub fn find_mentions(content: &str) -> Vec<String> {
+    let mut mentions: Vec<String> = Vec::new();
+    let mut previous: Option<char> = None;
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((idx, c)) = chars.next() {
+        let starts_mention = c == '@' && !previous.is_some_and(|p| p.is_alphanumeric());
+        previous = Some(c);
+        if !starts_mention {
+            continue;
+        }
+
+        let start = idx + c.len_utf8();
+        let mut end = start;
+        while let Some(&(next_idx, next)) = chars.peek() {
+            if !NickPolicy::is_nick_char(next) {
+                break;
+            }
+            end = next_idx + next.len_utf8();
+            previous = Some(next);
+            chars.next();
+        }
+
+        // "@bob." at the end of a sentence
+        let name = content[start..end].trim_end_matches(['.', '-']);
+        if !name.is_empty() && !mentions.iter().any(|m| m == name) {
+            mentions.push(name.to_string());
+        }
+    }
+    mentions
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_mentions() {
+        assert_eq!(find_mentions("hey @bob, ask @Zoë."), vec!["bob", "Zoë"]);
+        assert_eq!(find_mentions("@alice @alice"), vec!["alice"]);
+        assert_eq!(find_mentions("(@carol_2)"), vec!["carol_2"]);
+    }
+
+    #[test]
+    fn test_ignores_non_mentions() {
+        assert!(find_mentions("mail me at bob@example.com").is_empty());
+        assert!(find_mentions("@ alone").is_empty());
+        assert!(find_mentions("no mentions here").is_empty());
+    }
+}
@@ -1,2 +1,5 @@
 pub mod chat_message;
-pub mod command_processor;
\ No newline at end of file
+pub mod command_processor;
+pub mod command_registry;
+pub mod mention;
+pub mod protocol;
\ No newline at end of file
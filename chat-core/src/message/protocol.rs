@@ -0,0 +1,95 @@
+/// Escape sequences of a structured field, '%' first so it can be undone
+const FIELD_ESCAPES: &[(char, &str)] = &[
+    ('%', "%25"),
+    ('|', "%7C"),
+    (',', "%2C"),
+    (':', "%3A"),
+    ('\n', "%0A"),
+];
+
+/// Only what would end a line early
+const LINE_ESCAPES: &[(char, &str)] = &[('%', "%25"), ('\n', "%0A")];
+
+/// Escapes the characters that separate frames, fields and list entries,
+/// so free text can be carried inside a structured field.
+pub fn escape_field(text: &str) -> String {
+    escape(text, FIELD_ESCAPES)
+}
+
+/// Reverses `escape_field`. Unknown sequences are kept as they are.
+pub fn unescape_field(text: &str) -> String {
+    unescape(text, FIELD_ESCAPES)
+}
+
+/// Escapes line breaks so multi-line text travels as one line, in messages
+/// sent by clients and in the content of the frames sent back.
+pub fn escape_line(text: &str) -> String {
+    escape(text, LINE_ESCAPES)
+}
+
+/// Reverses `escape_line`. Other '%' sequences, like a typed "%7C", are kept.
+pub fn unescape_line(text: &str) -> String {
+    unescape(text, LINE_ESCAPES)
+}
+
+fn escape(text: &str, escapes: &[(char, &str)]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match escapes.iter().find(|(plain, _)| *plain == c) {
+            Some((_, code)) => escaped.push_str(code),
+            None => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn unescape(text: &str, escapes: &[(char, &str)]) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(pos) = rest.find('%') {
+        unescaped.push_str(&rest[..pos]);
+        let code = rest.get(pos..pos + 3);
+        match escapes.iter().find(|(_, escaped)| Some(*escaped) == code) {
+            Some((plain, _)) => {
+                unescaped.push(*plain);
+                rest = &rest[pos + 3..];
+            }
+            None => {
+                unescaped.push('%');
+                rest = &rest[pos + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = "lunch | back at 1:30, maybe 100%\nor later";
+        let escaped = escape_field(text);
+        assert!(!escaped.contains(['|', ',', ':', '\n']));
+        assert_eq!(unescape_field(&escaped), text);
+    }
+
+    #[test]
+    fn test_unknown_escapes_are_kept() {
+        assert_eq!(unescape_field("50%"), "50%");
+        assert_eq!(unescape_field("%zz"), "%zz");
+        assert_eq!(unescape_field("é%2Cè"), "é,è");
+    }
+
+    #[test]
+    fn test_line_escaping_only_touches_line_breaks() {
+        let text = "fn main() {\n    println!(\"100%|ok\");\n}";
+        let escaped = escape_line(text);
+        assert_eq!(escaped, "fn main() {%0A    println!(\"100%25|ok\");%0A}");
+        assert_eq!(unescape_line(&escaped), text);
+        assert_eq!(unescape_line("a%7Cb"), "a%7Cb");
+    }
+}
@@ -1,20 +1,31 @@
 use std::net::SocketAddr;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncWriteExt, BufReader};
 use tokio::net::TcpStream;
 use tokio::select;
+use tokio::time::{interval, Duration};
 use tokio::sync::broadcast::{Receiver, Sender};
 
 use crate::client::client_manager::ClientManager;
+use crate::client::nick_policy::{NickError, NickPolicy};
+use crate::client::presence::Presence;
 use crate::message::chat_message::ChatMessage;
 use crate::message::chat_message::MessageType;
-use crate::message::command_processor::{CommandProcessor, CommandResult};
+use crate::message::command_processor::{CommandError, CommandProcessor, CommandResult};
+use crate::message::protocol::{escape_line, unescape_line};
+use crate::server::line_reader::{validate_content, LineRead, LineReader};
+use crate::server::rate_limiter::{RateDecision, RateLimiter};
 use crate::server::room_manager::RoomManager;
+use crate::server::server_config::ServerConfig;
+
+/// How often a connection checks whether its user went idle
+const PRESENCE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
 
 struct MessageLoopContext {
     client_manager: ClientManager,
     room_manager: RoomManager,
     addr: SocketAddr,
     message_sender: Sender<ChatMessage>,
+    config: ServerConfig,
 }
 
 pub struct ClientConnection {
@@ -25,9 +36,11 @@ pub struct ClientConnection {
     message_sender: Sender<ChatMessage>,
     message_receiver: Receiver<ChatMessage>,
     anonymous_id: u32,
+    config: ServerConfig,
 }
 
 impl ClientConnection {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         stream: TcpStream,
         addr: SocketAddr,
@@ -36,12 +49,14 @@ impl ClientConnection {
         message_sender: Sender<ChatMessage>,
         message_receiver: Receiver<ChatMessage>,
         anonymous_id: u32,
+        config: ServerConfig,
     ) -> Self {
         Self {
-            stream, addr, client_manager, room_manager, message_sender, message_receiver, anonymous_id
+            stream, addr, client_manager, room_manager, message_sender, message_receiver, anonymous_id, config
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn register_client_name(
         client_manager: &ClientManager,
         room_manager: &RoomManager,
@@ -49,7 +64,7 @@ impl ClientConnection {
         anonymous_id: u32,
         buf_reader: &mut BufReader<tokio::net::tcp::ReadHalf<'_>>,
         writer: &mut tokio::net::tcp::WriteHalf<'_>,
-        input: &mut String,
+        line_reader: &mut LineReader,
         message_sender: &Sender<ChatMessage>,
     ) -> Result<(), Box<dyn std::error::Error>>{
         writer.write_all(b"\n=== Welcome to Rusty Chat ===\n").await?;
@@ -58,22 +73,31 @@ impl ClientConnection {
         loop {
             writer.write_all(format!("Name (press Enter for Anonymous#{}): ", anonymous_id).as_bytes()).await?;
 
-            input.clear();
-            buf_reader.read_line(input).await?;
-
-            let name = input.trim().to_string();
+            let name = match line_reader.read_line(buf_reader).await? {
+                LineRead::Line(line) => line.trim().to_string(),
+                LineRead::Eof => return Err("Client left before choosing a name".into()),
+                LineRead::TooLong => {
+                    writer.write_all(format!("Invalid name: {}. Try again.\n", NickError::TooLong).as_bytes()).await?;
+                    continue;
+                }
+                LineRead::InvalidUtf8 => {
+                    writer.write_all(format!("Invalid name: {}. Try again.\n", NickError::InvalidEncoding).as_bytes()).await?;
+                    continue;
+                }
+            };
 
             let final_name = if name.is_empty() {
-                format!("Anonymous#{}", anonymous_id)
+                NickPolicy::anonymous_name(anonymous_id)
             } else {
-                name
+                match NickPolicy::normalize(&name) {
+                    Ok(valid_name) => valid_name,
+                    Err(e) => {
+                        writer.write_all(format!("Invalid name: {}. Try again.\n", e).as_bytes()).await?;
+                        continue;
+                    }
+                }
             };
 
-            if final_name.len() < 2 || final_name.len() > 20 {
-                writer.write_all(b"Name must be between 2-20 characters. Try again.\n").await?;
-                continue;
-            }
-
             if client_manager.is_name_available(&final_name).await {
                 client_manager.register_client(addr, final_name.clone()).await;
                 let _ = room_manager.join_room("general", addr, None).await;
@@ -98,15 +122,9 @@ impl ClientConnection {
         message_sender: &Sender<ChatMessage>,
     ) {
         let member_addrs = room_manager.get_room_members(room_name).await;
-        let mut usernames = Vec::new();
-        
-        for addr in member_addrs {
-            if let Some(username) = client_manager.get_clients_name(&addr).await {
-                usernames.push(username);
-            }
-        }
+        let entries = client_manager.user_list_entries(&member_addrs).await;
 
-        let user_list_msg = ChatMessage::user_list(usernames, room_name.to_string());
+        let user_list_msg = ChatMessage::user_list(entries, room_name.to_string());
         let _ = message_sender.send(user_list_msg);
     }
 
@@ -129,12 +147,123 @@ impl ClientConnection {
         let _ = message_sender.send(room_list_msg);
     }
 
+    /// `/read` and `/typing` are sent by clients on their own, not typed by
+    /// the user: they don't make the user active or count against their
+    /// flood limits
+    fn is_background(message: &str) -> bool {
+        matches!(
+            CommandProcessor::parse(message),
+            Some(CommandResult::MarkRead(..) | CommandResult::Typing(_))
+        )
+    }
+
+    /// The WARNING for a line the flood limits refused, `None` when it's allowed
+    fn rate_warning(decision: &RateDecision) -> Option<String> {
+        match decision {
+            RateDecision::Allow => None,
+            RateDecision::Warn => Some("WARNING|You are sending messages too fast. Slow down or you will be muted.\n".to_string()),
+            RateDecision::Mute(duration) => Some(format!("WARNING|Flood protection: you are muted for {}s.\n", duration.as_secs())),
+            RateDecision::Muted(remaining) => Some(format!("WARNING|You are muted for another {}s.\n", remaining.as_millis().div_ceil(1000))),
+            RateDecision::Disconnect => Some("WARNING|Disconnected for flooding.\n".to_string()),
+        }
+    }
+
+    /// Broadcasts the room's user list when our presence changed since it was last shown
+    async fn refresh_presence(ctx: &MessageLoopContext, shown_presence: &mut Option<Presence>) {
+        let presence = ctx.client_manager.get_presence(&ctx.addr).await;
+        if presence == *shown_presence {
+            return;
+        }
+        *shown_presence = presence;
+        if let Some(room) = ctx.room_manager.get_user_room(&ctx.addr).await {
+            Self::broadcast_user_list(&ctx.room_manager, &ctx.client_manager, &room, &ctx.message_sender).await;
+        }
+    }
+
+    /// "CHAT|<id>|<reply_to>|<mentions>|<timestamp>|<sender>|<color>|<content>",
+    /// reply_to empty when not a reply, mentions comma separated.
+    /// `/me` emotes use the same fields under "ACTION".
+    /// Content is escaped with `escape_line` in every frame that carries it.
+    fn chat_frame(msg: &ChatMessage) -> String {
+        let kind = match msg.message_type {
+            MessageType::Action => "ACTION",
+            _ => "CHAT",
+        };
+        format!("{}|{}|{}|{}|{}|{}|{}|{}\n",
+            kind,
+            msg.id.unwrap_or_default(),
+            msg.reply_to.map(|id| id.to_string()).unwrap_or_default(),
+            msg.mentions.join(","),
+            msg.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            msg.sender_name,
+            msg.color,
+            escape_line(&msg.content)
+        )
+    }
+
+    /// "EDIT|<id>|<content>"
+    fn edit_frame(msg: &ChatMessage) -> String {
+        format!("EDIT|{}|{}\n", msg.id.unwrap_or_default(), escape_line(&msg.content))
+    }
+
+    /// Replays the room history between "HISTORY|<room>" and
+    /// "HISTORY_END|<room>|<read marker>", edits and reactions included.
+    /// The marker is empty when the user has never read the room.
+    async fn send_room_history(
+        ctx: &MessageLoopContext,
+        writer: &mut tokio::net::tcp::WriteHalf<'_>,
+        room: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut frames = format!("HISTORY|{}\n", room);
+        for msg in ctx.room_manager.get_history(room).await {
+            let id = msg.id.unwrap_or_default();
+            frames.push_str(&Self::chat_frame(&msg));
+            if msg.edited {
+                frames.push_str(&Self::edit_frame(&msg));
+            }
+            for reaction in &msg.reactions {
+                for user in &reaction.users {
+                    frames.push_str(&format!("REACTION|{}|{}|{}|+\n", id, reaction.emoji, user));
+                }
+            }
+        }
+
+        let marker = match ctx.client_manager.get_clients_name(&ctx.addr).await {
+            Some(name) => ctx.client_manager.read_marker(&name, room).await,
+            None => None,
+        };
+        frames.push_str(&format!(
+            "HISTORY_END|{}|{}\n",
+            room,
+            marker.map(|id| id.to_string()).unwrap_or_default()
+        ));
+        writer.write_all(frames.as_bytes()).await?;
+        Ok(())
+    }
+
+    /// "SYSTEM|<content>", escaped like every frame content: clients unescape
+    /// all of them
+    fn system_frame(content: &str) -> String {
+        format!("SYSTEM|{}\n", escape_line(content))
+    }
+
+    /// "WHISPER|<timestamp>|<from>|<to>|<color>|<content>"
+    fn whisper_frame(msg: &ChatMessage) -> String {
+        format!("WHISPER|{}|{}|{}|{}|{}\n",
+            msg.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            msg.sender_name,
+            msg.target_name.as_deref().unwrap_or_default(),
+            msg.color,
+            escape_line(&msg.content)
+        )
+    }
+
     async fn message_loop_static(
         ctx: MessageLoopContext,
         message_receiver: &mut Receiver<ChatMessage>,
         buf_reader: &mut BufReader<tokio::net::tcp::ReadHalf<'_>>,
         writer: &mut tokio::net::tcp::WriteHalf<'_>,
-        input: &mut String,
+        line_reader: &mut LineReader,
     ) -> Result<(), Box<dyn std::error::Error>> {
 
         // Send initial list of the rooms that can connect
@@ -155,29 +284,87 @@ impl ClientConnection {
         }
 
 
-        // Send user list
+        // Send user list and what was said before we arrived
         {
             if let Some(room_name) = ctx.room_manager.get_user_room(&ctx.addr).await {
                 let members = ctx.room_manager.get_room_members(&room_name).await;
-                let mut users = Vec::new();
-                for member_addr in members {
-                    if let Some(name) = ctx.client_manager.get_clients_name(&member_addr).await {
-                        users.push(name);
-                    }
-                }
+                let users = ctx.client_manager.user_list_entries(&members).await;
                 let formatted = format!("USER_LIST|{}\n", users.join(","));
                 writer.write_all(formatted.as_bytes()).await?;
+                Self::send_room_history(&ctx, writer, &room_name).await?;
             }
         }
 
+        let mut rate_limiter = RateLimiter::new(ctx.config.rate_limit.clone());
+        // Budget of the frames clients send on their own, see `is_background`
+        let mut background_limiter = RateLimiter::new(ctx.config.rate_limit.clone());
+        // Presence last shown to the room, to notice when we go idle or come back
+        let mut shown_presence = ctx.client_manager.get_presence(&ctx.addr).await;
+        let mut presence_check = interval(PRESENCE_CHECK_INTERVAL);
+
+        // Deliver whispers received while offline
+        let queued = ctx.client_manager.take_offline_whispers(&ctx.addr).await;
+        if !queued.is_empty() {
+            let formatted = Self::system_frame(&format!("✉ {} whisper(s) arrived while you were offline", queued.len()));
+            writer.write_all(formatted.as_bytes()).await?;
+        }
+        for whisper in queued {
+            writer.write_all(Self::whisper_frame(&whisper).as_bytes()).await?;
+        }
+
         loop {
             select! {
-                result = buf_reader.read_line(input) => {
+                result = line_reader.read_line(buf_reader) => {
                     match result {
-                        Ok(0) => break,
-                        Ok(_) => {
-                            let message = input.trim().to_string();
+                        Ok(LineRead::Eof) => break,
+                        Ok(discarded @ (LineRead::TooLong | LineRead::InvalidUtf8)) => {
+                            // Discarded lines count against the flood limits like any other
+                            let decision = rate_limiter.check(ctx.config.buffer_size);
+                            if let Some(warning) = Self::rate_warning(&decision) {
+                                writer.write_all(warning.as_bytes()).await?;
+                                if decision == RateDecision::Disconnect {
+                                    break;
+                                }
+                                continue;
+                            }
+                            let formatted = match discarded {
+                                LineRead::TooLong => format!(
+                                    "WARNING|Line too long (max {} bytes). It was discarded.\n",
+                                    ctx.config.buffer_size
+                                ),
+                                _ => "WARNING|Message is not valid UTF-8. It was discarded.\n".to_string(),
+                            };
+                            writer.write_all(formatted.as_bytes()).await?;
+                        }
+                        Ok(LineRead::Line(line)) => {
+                            // Clients escape the line breaks of multi-line messages
+                            let message = unescape_line(line.trim()).trim().to_string();
                             if !message.is_empty() {
+                                if Self::is_background(&message) {
+                                    // Dropped quietly: no user is waiting on them
+                                    if background_limiter.check(message.len()) != RateDecision::Allow {
+                                        continue;
+                                    }
+                                } else {
+                                    let decision = rate_limiter.check(message.len());
+                                    if let Some(warning) = Self::rate_warning(&decision) {
+                                        writer.write_all(warning.as_bytes()).await?;
+                                        if decision == RateDecision::Disconnect {
+                                            break;
+                                        }
+                                        continue;
+                                    }
+
+                                    ctx.client_manager.touch(&ctx.addr).await;
+                                    Self::refresh_presence(&ctx, &mut shown_presence).await;
+                                }
+
+                                if let Err(e) = validate_content(&message, ctx.config.max_message_length, ctx.config.max_message_lines) {
+                                    let formatted = format!("WARNING|{}\n", e);
+                                    writer.write_all(formatted.as_bytes()).await?;
+                                    continue;
+                                }
+
                                 // Check if it's a command
                                 if message.starts_with('/') {
                                     if let Some(cmd_result) = CommandProcessor::parse(&message) {
@@ -200,14 +387,16 @@ impl ClientConnection {
 
                                                                 // Send user list
                                                                 let members = ctx.room_manager.get_room_members(&response_msg.content).await;
-                                                                let mut users = Vec::new();
-                                                                for member_addr in members {
-                                                                    if let Some(name) = ctx.client_manager.get_clients_name(&member_addr).await {
-                                                                        users.push(name);
-                                                                    }
-                                                                }
+                                                                let users = ctx.client_manager.user_list_entries(&members).await;
                                                                 let user_list = format!("USER_LIST|{}\n", users.join(","));
                                                                 writer.write_all(user_list.as_bytes()).await?;
+
+                                                                if let Some(topic) = ctx.room_manager.get_room_settings(&response_msg.content).await.and_then(|settings| settings.topic) {
+                                                                    let formatted = format!("TOPIC|{}|{}\n", response_msg.content, topic);
+                                                                    writer.write_all(formatted.as_bytes()).await?;
+                                                                }
+
+                                                                Self::send_room_history(&ctx, writer, &response_msg.content).await?;
                                                             }
                                                         }
 
@@ -216,24 +405,61 @@ impl ClientConnection {
 
                                                     }
                                                     MessageType::Whisper => {
-                                                        // Whisper is handled in broadcast
+                                                        // Echo to the sender, the target gets it from the broadcast
+                                                        writer.write_all(Self::whisper_frame(&response_msg).as_bytes()).await?;
+                                                        if response_msg.target.is_some() {
+                                                            let _ = ctx.message_sender.send(response_msg);
+                                                        } else {
+                                                            // Nicks aren't reserved, so say when it will really arrive
+                                                            let target_name = response_msg.target_name.as_deref().unwrap_or_default();
+                                                            let formatted = Self::system_frame(&format!(
+                                                                "✉ {0} is offline. Your whisper will be delivered when {0} next logs in from the address they last used.",
+                                                                target_name
+                                                            ));
+                                                            writer.write_all(formatted.as_bytes()).await?;
+                                                        }
+                                                    }
+                                                    MessageType::Chat | MessageType::Action => {
+                                                        // Replies and emotes: echo like a normal post, then broadcast
+                                                        writer.write_all(Self::chat_frame(&response_msg).as_bytes()).await?;
                                                         let _ = ctx.message_sender.send(response_msg);
                                                     }
                                                     MessageType::UserList => {
                                                         // Broadcast updated user list
+                                                        shown_presence = ctx.client_manager.get_presence(&ctx.addr).await;
                                                         let _ = ctx.message_sender.send(response_msg);
                                                     }
+                                                    MessageType::NickChange => {
+                                                        let room = response_msg.room.clone();
+                                                        let _ = ctx.message_sender.send(response_msg);
+                                                        Self::broadcast_user_list(&ctx.room_manager, &ctx.client_manager, &room, &ctx.message_sender).await;
+                                                    }
+                                                    MessageType::System if response_msg.target == Some(ctx.addr) => {
+                                                        // Replies meant for this user only, like /help
+                                                        let formatted = Self::system_frame(&response_msg.content);
+                                                        writer.write_all(formatted.as_bytes()).await?;
+                                                    }
                                                     _ => {
                                                         let _ = ctx.message_sender.send(response_msg);
                                                     }
                                                 }
                                             }
                                             Ok(None) => {
-                                                // Command executed without response (like /help shown locally)
+                                                // Command executed without response (like /quit)
+                                            }
+                                            Err(CommandError::PasswordRequired { room, wrong }) => {
+                                                // Lets the client ask for the password and try again
+                                                let reason = if wrong { "wrong" } else { "missing" };
+                                                let formatted = format!("PASSWORD_REQUIRED|{}|{}\n", room, reason);
+                                                writer.write_all(formatted.as_bytes()).await?;
                                             }
-                                            Err(msg) => {
+                                            Err(CommandError::CreateRefused(reason)) => {
+                                                let formatted = format!("CREATE_FAILED|{}\n", reason);
+                                                writer.write_all(formatted.as_bytes()).await?;
+                                            }
+                                            Err(CommandError::Message(msg)) => {
                                                 // Command error (send as SYSTEM message)
-                                                let formatted = format!("SYSTEM|{}\n", msg);
+                                                let formatted = Self::system_frame(&msg);
                                                 writer.write_all(formatted.as_bytes()).await?;
                                             }
                                         }
@@ -246,21 +472,23 @@ impl ClientConnection {
                                     // Normal message (TUI-focused)
                                     if let Some(sender_name) = ctx.client_manager.get_clients_name(&ctx.addr).await {
                                         if let Some(room) = ctx.room_manager.get_user_room(&ctx.addr).await {
-                                            let chat_msg = ChatMessage::new(
+                                            if let Err(e) = ctx.room_manager.register_post(&room, ctx.addr).await {
+                                                let formatted = Self::system_frame(&format!("✗ {}", e));
+                                                writer.write_all(formatted.as_bytes()).await?;
+                                                continue;
+                                            }
+
+                                            let mut chat_msg = ChatMessage::new(
                                                 message.clone(),
                                                 ctx.addr,
                                                 sender_name.clone(),
                                                 room,
                                             );
+                                            chat_msg.mentions = ctx.client_manager.resolve_mentions(&message).await;
+                                            let chat_msg = ctx.room_manager.record_message(chat_msg).await;
                                             
                                             //  Echo back to sender
-                                            let formatted = format!("CHAT|{}|{}|{}|{}\n",
-                                                chat_msg.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                                                chat_msg.sender_name,
-                                                chat_msg.color,
-                                                chat_msg.content
-                                            );
-                                            writer.write_all(formatted.as_bytes()).await?;
+                                            writer.write_all(Self::chat_frame(&chat_msg).as_bytes()).await?;
                                             
                                             // Broadcast to others
                                             let _ = ctx.message_sender.send(chat_msg);
@@ -268,7 +496,6 @@ impl ClientConnection {
                                     }
                                 }
                             }
-                            input.clear();
                         },
 
                         Err(e) => {
@@ -277,6 +504,9 @@ impl ClientConnection {
                         }
                     }
                 },
+                _ = presence_check.tick() => {
+                    Self::refresh_presence(&ctx, &mut shown_presence).await;
+                }
                 Ok(chat_msg) = message_receiver.recv() => {
                     match chat_msg.message_type {
                         MessageType::UserList => {
@@ -293,34 +523,90 @@ impl ClientConnection {
                             let formatted = format!("ROOM_LIST|{}\n", chat_msg.content);
                             writer.write_all(formatted.as_bytes()).await?;
                         }
-                        MessageType::Chat => {
+                        MessageType::Chat | MessageType::Action => {
                             // Only receives from the same room
                             if let Some(my_room) = ctx.room_manager.get_user_room(&ctx.addr).await {
-                                if chat_msg.room == my_room && chat_msg.sender_addr != ctx.addr {
-                                    // Send structured message: "CHAT|<timestamp>|<sender>|<color>|<content>"
-                                    let formatted = format!("CHAT|{}|{}|{}|{}\n",
-                                        chat_msg.timestamp.format("%Y-%m-%d %H:%M:%S"),
-                                        chat_msg.sender_name,
-                                        chat_msg.color,
-                                        chat_msg.content
-                                    );
-                                    writer.write_all(formatted.as_bytes()).await?;
+                                if chat_msg.room == my_room {
+                                    if chat_msg.sender_addr != ctx.addr {
+                                        writer.write_all(Self::chat_frame(&chat_msg).as_bytes()).await?;
+                                    }
+                                } else if let Some(my_name) = ctx.client_manager.get_clients_name(&ctx.addr).await {
+                                    // Mentioned from another room
+                                    if chat_msg.mentions_user(&my_name) {
+                                        let formatted = format!("MENTION|{}|{}|{}|{}|{}|{}\n",
+                                            chat_msg.room,
+                                            chat_msg.id.unwrap_or_default(),
+                                            chat_msg.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                                            chat_msg.sender_name,
+                                            chat_msg.color,
+                                            escape_line(&chat_msg.content)
+                                        );
+                                        writer.write_all(formatted.as_bytes()).await?;
+                                    }
                                 }
                             }
                         }
+                        MessageType::NickChange => {
+                            // Everyone in the room and everyone whispering with them,
+                            // and always the user who changed
+                            let my_room = ctx.room_manager.get_user_room(&ctx.addr).await;
+                            if chat_msg.sender_addr == ctx.addr
+                                || my_room.as_deref() == Some(chat_msg.room.as_str())
+                                || ctx.client_manager.is_whisper_peer(&ctx.addr, &chat_msg.content).await
+                            {
+                                let formatted = format!("NICK|{}|{}\n", chat_msg.sender_name, chat_msg.content);
+                                writer.write_all(formatted.as_bytes()).await?;
+                            }
+                        }
+                        // Only to the others in the room
+                        MessageType::Typing if chat_msg.sender_addr != ctx.addr => {
+                            let my_room = ctx.room_manager.get_user_room(&ctx.addr).await;
+                            if my_room.as_deref() == Some(chat_msg.room.as_str()) {
+                                let formatted = format!("TYPING|{}|{}\n", chat_msg.sender_name, chat_msg.content);
+                                writer.write_all(formatted.as_bytes()).await?;
+                            }
+                        }
+                        MessageType::Reaction => {
+                            let my_room = ctx.room_manager.get_user_room(&ctx.addr).await;
+                            if my_room.as_deref() == Some(chat_msg.room.as_str()) {
+                                // "REACTION|<id>|<emoji>|<user>|<+|->"
+                                let formatted = format!("REACTION|{}|{}|{}|{}\n",
+                                    chat_msg.id.unwrap_or_default(),
+                                    chat_msg.content,
+                                    chat_msg.sender_name,
+                                    if chat_msg.deleted { "-" } else { "+" },
+                                );
+                                writer.write_all(formatted.as_bytes()).await?;
+                            }
+                        }
+                        MessageType::Edit | MessageType::Delete => {
+                            // Applied in place by everyone in the room, the author included
+                            let my_room = ctx.room_manager.get_user_room(&ctx.addr).await;
+                            if my_room.as_deref() == Some(chat_msg.room.as_str()) {
+                                let formatted = match chat_msg.message_type {
+                                    MessageType::Edit => Self::edit_frame(&chat_msg),
+                                    _ => format!("DELETE|{}\n", chat_msg.id.unwrap_or_default()),
+                                };
+                                writer.write_all(formatted.as_bytes()).await?;
+                            }
+                        }
+                        MessageType::Topic => {
+                            // Everyone in the room, the owner who set it included
+                            let my_room = ctx.room_manager.get_user_room(&ctx.addr).await;
+                            if my_room.as_deref() == Some(chat_msg.room.as_str()) {
+                                let formatted = format!("TOPIC|{}|{}\n", chat_msg.room, chat_msg.content);
+                                writer.write_all(formatted.as_bytes()).await?;
+                            }
+                        }
                         MessageType::System => {
-                            let formatted = format!("SYSTEM|{}\n", chat_msg.content);
+                            let formatted = Self::system_frame(&chat_msg.content);
                             writer.write_all(formatted.as_bytes()).await?;
                         }
                         MessageType::Whisper => {
                             // Send whisper to target
                             if let Some(target) = chat_msg.target {
                                 if target == ctx.addr {
-                                    let formatted = format!("SYSTEM|[Whisper from {}] {}\n",
-                                        chat_msg.sender_name,
-                                        chat_msg.content
-                                    );
-                                    writer.write_all(formatted.as_bytes()).await?;
+                                    writer.write_all(Self::whisper_frame(&chat_msg).as_bytes()).await?;
                                 }
                             }
                         }
@@ -337,7 +623,7 @@ impl ClientConnection {
     pub async fn handler(mut self) {
         let (reader, mut writer) = self.stream.split();
         let mut buf_reader = BufReader::new(reader);
-        let mut input = String::new();
+        let mut line_reader = LineReader::new(self.config.buffer_size);
 
         // Register the client
         if let Err(e) = Self::register_client_name(
@@ -347,13 +633,12 @@ impl ClientConnection {
             self.anonymous_id,
             &mut buf_reader,
             &mut writer,
-            &mut input,
+            &mut line_reader,
             &self.message_sender,
         ).await {
             eprintln!("Error trying to register client: {}", e);
             return;
         }
-        input.clear();
 
         let _ = writer.write_all(b"\n").await;
 
@@ -363,6 +648,7 @@ impl ClientConnection {
             room_manager: self.room_manager.clone(),
             addr: self.addr,
             message_sender: self.message_sender.clone(),
+            config: self.config.clone(),
         };
         let mut message_receiver = self.message_receiver;
 
@@ -372,7 +658,7 @@ impl ClientConnection {
             &mut message_receiver,
             &mut buf_reader,
             &mut writer,
-            &mut input
+            &mut line_reader,
         ).await {
             eprintln!("Error in the message loop: {}", e);
         }
@@ -386,4 +672,126 @@ impl ClientConnection {
             Self::broadcast_user_list(&self.room_manager, &self.client_manager, &room, &self.message_sender).await;
         }
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncBufReadExt;
+    use tokio::net::TcpListener;
+    use tokio::sync::broadcast;
+
+    /// Polls `check` until it holds, failing after a second
+    async fn eventually<F, Fut>(mut check: F)
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = bool>,
+    {
+        for _ in 0..100 {
+            if check().await {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("condition never held");
+    }
+
+    #[test]
+    fn test_system_frames_are_escaped() {
+        assert_eq!(ClientConnection::system_frame("✗ 100% full\nretry"), "SYSTEM|✗ 100%25 full%0Aretry\n");
+    }
+
+    #[test]
+    fn test_action_frame() {
+        let addr = SocketAddr::from(([127, 0, 0, 1], 1000));
+        let mut action = ChatMessage::action("waves\nhello".into(), addr, "alice".into(), "general".into());
+        action.id = Some(7);
+        let frame = ClientConnection::chat_frame(&action);
+        assert!(frame.starts_with("ACTION|7|||"));
+        assert!(frame.ends_with(&format!("|alice|{}|waves%0Ahello\n", action.color)));
+    }
+
+    #[tokio::test]
+    async fn test_read_reports_keep_idle_users_idle() {
+        let clients = ClientManager::new().with_idle_timeout(Duration::from_secs(60));
+        let rooms = RoomManager::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let (sender, _) = broadcast::channel(16);
+
+        let server = {
+            let (clients, rooms, sender) = (clients.clone(), rooms.clone(), sender.clone());
+            tokio::spawn(async move {
+                let (stream, addr) = listener.accept().await.unwrap();
+                let receiver = sender.subscribe();
+                ClientConnection::new(stream, addr, clients, rooms, sender, receiver, 1, ServerConfig::default())
+                    .handler().await;
+            })
+        };
+
+        let mut stream = TcpStream::connect(server_addr).await.unwrap();
+        let addr = stream.local_addr().unwrap();
+        stream.write_all(b"alice\n").await.unwrap();
+        eventually(|| async { clients.get_clients_name(&addr).await.is_some() }).await;
+
+        let said = rooms.record_message(ChatMessage::new("hi".into(), addr, "bob".into(), "general".into())).await;
+        clients.get_clients().write().await.get_mut(&addr).unwrap().last_active =
+            chrono::Utc::now() - chrono::Duration::minutes(2);
+
+        // Far more than the flood limits allow for typed lines
+        for _ in 0..20 {
+            stream.write_all(format!("/read general {}\n/typing stop\n", said.id.unwrap()).as_bytes()).await.unwrap();
+        }
+        eventually(|| async { clients.read_marker("alice", "general").await == said.id }).await;
+        assert_eq!(clients.get_presence(&addr).await, Some(Presence::Idle));
+
+        // Still connected and not muted: a typed message goes through
+        stream.write_all(b"hello\n").await.unwrap();
+        eventually(|| async { clients.get_presence(&addr).await == Some(Presence::Online) }).await;
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn test_flood_of_long_lines_disconnects() {
+        let clients = ClientManager::new();
+        let rooms = RoomManager::new();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let server_addr = listener.local_addr().unwrap();
+        let (sender, _) = broadcast::channel(16);
+        let config = ServerConfig { buffer_size: 64, ..ServerConfig::default() };
+
+        let server = tokio::spawn(async move {
+            let (stream, addr) = listener.accept().await.unwrap();
+            let receiver = sender.subscribe();
+            ClientConnection::new(stream, addr, clients, rooms, sender.clone(), receiver, 1, config)
+                .handler().await;
+        });
+
+        let stream = TcpStream::connect(server_addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"alice\n").await.unwrap();
+        let long_line = format!("{}\n", "x".repeat(200));
+
+        // Every discarded line is answered with one WARNING until the server hangs up
+        let mut warnings = Vec::new();
+        for _ in 0..50 {
+            writer.write_all(long_line.as_bytes()).await.unwrap();
+            let warning = loop {
+                let line = lines.next_line().await.unwrap().expect("connection closed without a warning");
+                if line.starts_with("WARNING|") {
+                    break line;
+                }
+            };
+            warnings.push(warning);
+            if warnings.last().unwrap() == "WARNING|Disconnected for flooding." {
+                break;
+            }
+        }
+        assert_eq!(warnings.last().unwrap(), "WARNING|Disconnected for flooding.");
+        assert!(warnings[0].starts_with("WARNING|Line too long"));
+        assert!(lines.next_line().await.unwrap().is_none());
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+use std::io;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+#[derive(Debug, PartialEq)]
+pub enum LineRead {
+    Line(String),
+    /// The line went over the limit and was discarded up to its newline
+    TooLong,
+    InvalidUtf8,
+    Eof,
+}
+
+/// Newline framing with an upper bound, so a client that never sends
+/// '\n' can't make the server buffer unbounded input.
+///
+/// `read_line` is cancel safe: bytes are only consumed from the reader
+/// once they are copied into the internal buffer, so it can be polled
+/// inside `select!`.
+#[derive(Debug)]
+pub struct LineReader {
+    buf: Vec<u8>,
+    max_len: usize,
+    overflowed: bool,
+}
+
+impl LineReader {
+    pub fn new(max_len: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            max_len,
+            overflowed: false,
+        }
+    }
+
+    pub async fn read_line<R>(&mut self, reader: &mut R) -> io::Result<LineRead>
+    where
+        R: AsyncBufRead + Unpin,
+    {
+        loop {
+            let available = reader.fill_buf().await?;
+            if available.is_empty() {
+                if self.buf.is_empty() && !self.overflowed {
+                    return Ok(LineRead::Eof);
+                }
+                // Unterminated last line
+                return Ok(self.finish_line());
+            }
+
+            let (chunk, used, complete) = match available.iter().position(|&b| b == b'\n') {
+                Some(pos) => (&available[..pos], pos + 1, true),
+                None => (available, available.len(), false),
+            };
+
+            if !self.overflowed {
+                if self.buf.len() + chunk.len() > self.max_len {
+                    self.overflowed = true;
+                    self.buf = Vec::new();
+                } else {
+                    self.buf.extend_from_slice(chunk);
+                }
+            }
+            reader.consume(used);
+
+            if complete {
+                return Ok(self.finish_line());
+            }
+        }
+    }
+
+    fn finish_line(&mut self) -> LineRead {
+        if self.overflowed {
+            self.overflowed = false;
+            return LineRead::TooLong;
+        }
+
+        let mut bytes = std::mem::take(&mut self.buf);
+        if bytes.last() == Some(&b'\r') {
+            bytes.pop();
+        }
+        match String::from_utf8(bytes) {
+            Ok(line) => LineRead::Line(line),
+            Err(_) => LineRead::InvalidUtf8,
+        }
+    }
+}
+
+/// Checks the content of a message before it is processed.
+/// Line breaks and tabs are the only control characters let through.
+pub fn validate_content(content: &str, max_chars: usize, max_lines: usize) -> Result<(), String> {
+    let length = content.chars().count();
+    if length > max_chars {
+        return Err(format!(
+            "Message too long ({} characters, max {}). It was not sent.",
+            length, max_chars
+        ));
+    }
+    let lines = content.split('\n').count();
+    if lines > max_lines {
+        return Err(format!(
+            "Message has too many lines ({}, max {}). It was not sent.",
+            lines, max_lines
+        ));
+    }
+    if content.chars().any(|c| c.is_control() && c != '\n' && c != '\t') {
+        return Err("Message contains control characters. It was not sent.".to_string());
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::BufReader;
+
+    async fn read_all(input: &[u8], max_len: usize, capacity: usize) -> Vec<LineRead> {
+        let mut reader = BufReader::with_capacity(capacity, input);
+        let mut line_reader = LineReader::new(max_len);
+        let mut lines = Vec::new();
+        loop {
+            let line = line_reader.read_line(&mut reader).await.unwrap();
+            if line == LineRead::Eof {
+                break;
+            }
+            lines.push(line);
+        }
+        lines
+    }
+
+    #[tokio::test]
+    async fn test_splits_lines() {
+        let lines = read_all(b"hello\r\nworld\nlast", 16, 4).await;
+        assert_eq!(lines, vec![
+            LineRead::Line("hello".to_string()),
+            LineRead::Line("world".to_string()),
+            LineRead::Line("last".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_over_long_line_is_discarded() {
+        let lines = read_all(b"0123456789abcdef\nok\n", 8, 4).await;
+        assert_eq!(lines, vec![LineRead::TooLong, LineRead::Line("ok".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8() {
+        let lines = read_all(b"\xff\xfe\nok\n", 16, 16).await;
+        assert_eq!(lines, vec![LineRead::InvalidUtf8, LineRead::Line("ok".to_string())]);
+    }
+
+    #[test]
+    fn test_validate_content() {
+        assert!(validate_content("héllo 👋", 10, 1).is_ok());
+        assert!(validate_content("way too long", 5, 1).is_err());
+        assert!(validate_content("bell\x07", 10, 1).is_err());
+        assert!(validate_content("esc\x1b[31m", 20, 1).is_err());
+        assert!(validate_content("two\nlines", 20, 2).is_ok());
+        assert!(validate_content("a\nb\nc", 20, 2).is_err());
+        assert!(validate_content("crlf\r\n", 20, 2).is_err());
+        assert!(validate_content("name\tvalue", 20, 1).is_ok());
+    }
+}
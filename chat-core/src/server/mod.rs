@@ -2,4 +2,7 @@ pub mod connection;
 pub mod server;
 pub mod room;
 pub mod room_manager;
-pub mod server_config;
\ No newline at end of file
+pub mod room_policy;
+pub mod server_config;
+pub mod rate_limiter;
+pub mod line_reader;
\ No newline at end of file
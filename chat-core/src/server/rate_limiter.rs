@@ -0,0 +1,243 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Thresholds applied to every line a client sends
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+    pub messages_per_sec: f64,
+    pub message_burst: f64,
+    pub bytes_per_sec: f64,
+    pub byte_burst: f64,
+    /// How long a client stays muted after a repeated violation
+    pub mute_duration: Duration,
+    /// Violations older than this are forgotten
+    pub violation_window: Duration,
+    /// Violations inside the window that get the client disconnected
+    pub max_violations: usize,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            messages_per_sec: 2.0,
+            message_burst: 5.0,
+            bytes_per_sec: 2048.0,
+            byte_burst: 8192.0,
+            mute_duration: Duration::from_secs(30),
+            violation_window: Duration::from_secs(60),
+            max_violations: 4,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RateDecision {
+    Allow,
+    /// First violation: the line is dropped and the client is warned
+    Warn,
+    /// Repeated violation: the client has just been muted for this long
+    Mute(Duration),
+    /// The client is still muted for this long
+    Muted(Duration),
+    /// Sustained abuse
+    Disconnect,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: f64, refill_per_sec: f64) -> Self {
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    fn has(&self, amount: f64) -> bool {
+        self.tokens >= amount
+    }
+
+    fn take(&mut self, amount: f64) {
+        self.tokens -= amount;
+    }
+}
+
+/// Per-connection flood protection: one bucket for lines, one for bytes
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    messages: TokenBucket,
+    bytes: TokenBucket,
+    violations: VecDeque<Instant>,
+    muted_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            messages: TokenBucket::new(config.message_burst, config.messages_per_sec),
+            bytes: TokenBucket::new(config.byte_burst, config.bytes_per_sec),
+            violations: VecDeque::new(),
+            muted_until: None,
+            config,
+        }
+    }
+
+    /// Accounts for a line of `len` bytes and decides what to do with it
+    pub fn check(&mut self, len: usize) -> RateDecision {
+        let now = Instant::now();
+        self.messages.refill(now);
+        self.bytes.refill(now);
+
+        let within_limits = self.messages.has(1.0) && self.bytes.has(len as f64);
+        if within_limits {
+            self.messages.take(1.0);
+            self.bytes.take(len as f64);
+        }
+
+        if let Some(until) = self.muted_until {
+            if now < until {
+                // Flooding while muted still counts towards a disconnect
+                if !within_limits && self.register_violation(now) >= self.config.max_violations {
+                    return RateDecision::Disconnect;
+                }
+                return RateDecision::Muted(until - now);
+            }
+            self.muted_until = None;
+        }
+
+        if within_limits {
+            return RateDecision::Allow;
+        }
+
+        match self.register_violation(now) {
+            1 => RateDecision::Warn,
+            n if n >= self.config.max_violations => RateDecision::Disconnect,
+            _ => {
+                self.muted_until = Some(now + self.config.mute_duration);
+                RateDecision::Mute(self.config.mute_duration)
+            }
+        }
+    }
+
+    fn register_violation(&mut self, now: Instant) -> usize {
+        while let Some(first) = self.violations.front() {
+            if now.duration_since(*first) >= self.config.violation_window {
+                self.violations.pop_front();
+            } else {
+                break;
+            }
+        }
+        self.violations.push_back(now);
+        self.violations.len()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RateLimitConfig {
+        RateLimitConfig {
+            messages_per_sec: 1.0,
+            message_burst: 3.0,
+            bytes_per_sec: 100.0,
+            byte_burst: 200.0,
+            mute_duration: Duration::from_secs(10),
+            violation_window: Duration::from_secs(60),
+            max_violations: 3,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_burst_then_warn() {
+        let mut limiter = RateLimiter::new(config());
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check(10), RateDecision::Allow);
+        }
+        assert_eq!(limiter.check(10), RateDecision::Warn);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(limiter.check(10), RateDecision::Allow);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_byte_budget() {
+        let mut limiter = RateLimiter::new(config());
+
+        assert_eq!(limiter.check(150), RateDecision::Allow);
+        assert_eq!(limiter.check(150), RateDecision::Warn);
+
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(limiter.check(150), RateDecision::Allow);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_repeat_violation_mutes_then_recovers() {
+        let mut limiter = RateLimiter::new(config());
+
+        for _ in 0..3 {
+            limiter.check(10);
+        }
+        assert_eq!(limiter.check(10), RateDecision::Warn);
+        assert_eq!(limiter.check(10), RateDecision::Mute(Duration::from_secs(10)));
+
+        // Well-paced lines are still dropped while muted
+        tokio::time::advance(Duration::from_secs(4)).await;
+        assert_eq!(limiter.check(10), RateDecision::Muted(Duration::from_secs(6)));
+
+        tokio::time::advance(Duration::from_secs(6)).await;
+        assert_eq!(limiter.check(10), RateDecision::Allow);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_sustained_abuse_disconnects() {
+        let mut limiter = RateLimiter::new(config());
+
+        let mut decisions = Vec::new();
+        for _ in 0..10 {
+            let decision = limiter.check(10);
+            decisions.push(decision.clone());
+            if decision == RateDecision::Disconnect {
+                break;
+            }
+        }
+
+        assert_eq!(decisions.last(), Some(&RateDecision::Disconnect));
+        assert!(decisions.contains(&RateDecision::Warn));
+        assert!(decisions.contains(&RateDecision::Mute(Duration::from_secs(10))));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_old_violations_are_forgotten() {
+        let mut limiter = RateLimiter::new(config());
+
+        for _ in 0..3 {
+            limiter.check(10);
+        }
+        assert_eq!(limiter.check(10), RateDecision::Warn);
+
+        tokio::time::advance(Duration::from_secs(61)).await;
+        for _ in 0..3 {
+            assert_eq!(limiter.check(10), RateDecision::Allow);
+        }
+        assert_eq!(limiter.check(10), RateDecision::Warn);
+    }
+}
@@ -1,4 +1,19 @@
+use std::collections::{HashMap, VecDeque};
 use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::time::Instant;
+use crate::message::chat_message::ChatMessage;
+
+/// Recent messages kept per room, so they can still be edited or deleted
+pub const HISTORY_SIZE: usize = 200;
+
+/// Owner-adjustable limits and topic of a room
+#[derive(Clone, Debug, Default)]
+pub struct RoomSettings {
+    pub max_members: Option<usize>,
+    pub slow_mode: Option<Duration>,
+    pub topic: Option<String>,
+}
 
 #[derive(Clone, Debug)]
 pub struct Room {
@@ -6,6 +21,9 @@ pub struct Room {
     pub password: Option<String>,
     pub members: Vec<SocketAddr>,
     pub owner: SocketAddr,
+    pub settings: RoomSettings,
+    pub history: VecDeque<ChatMessage>,
+    last_post: HashMap<SocketAddr, Instant>,
 }
 
 
@@ -16,6 +34,9 @@ impl Room {
             password,
             members: Vec::new(),
             owner,
+            settings: RoomSettings::default(),
+            history: VecDeque::new(),
+            last_post: HashMap::new(),
         }
     }
 
@@ -30,6 +51,13 @@ impl Room {
         }
     }
 
+    pub fn is_full(&self) -> bool {
+        match self.settings.max_members {
+            Some(max) => self.members.len() >= max,
+            None => false,
+        }
+    }
+
     pub fn add_member(&mut self, addr: SocketAddr) {
         if !self.members.contains(&addr) {
             self.members.push(addr)
@@ -39,9 +67,58 @@ impl Room {
     pub fn remove_member(&mut self, addr: &SocketAddr) {
         self.members.retain(|&a| a != *addr);
     }
-}
 
+    pub fn record_message(&mut self, message: ChatMessage) {
+        if self.history.len() >= HISTORY_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(message);
+    }
 
+    /// A message still in the history that hasn't been deleted
+    pub fn find_message(&self, id: u64) -> Option<&ChatMessage> {
+        self.history.iter().find(|msg| msg.id == Some(id) && !msg.deleted)
+    }
+
+    /// Finds a live message that `requester` is allowed to change: one posted
+    /// from their connection, or any of them for the room owner and `moderator`s.
+    /// Nicks aren't owned, so a nick alone never proves authorship.
+    pub fn editable_message(
+        &mut self,
+        id: u64,
+        requester: SocketAddr,
+        moderator: bool,
+    ) -> Result<&mut ChatMessage, String> {
+        let owner = self.owner;
+        let message = self.history.iter_mut()
+            .find(|msg| msg.id == Some(id) && !msg.deleted)
+            .ok_or_else(|| format!("Message #{} not found in '{}'", id, self.name))?;
+        if message.sender_addr != requester && owner != requester && !moderator {
+            return Err("You can only change your own messages".to_string());
+        }
+        Ok(message)
+    }
 
+    /// Records a post from `addr`, or returns how long it still has to wait
+    /// when slow mode is on. The owner is never slowed down.
+    pub fn register_post(&mut self, addr: SocketAddr) -> Result<(), Duration> {
+        let Some(interval) = self.settings.slow_mode else {
+            return Ok(());
+        };
+        if addr == self.owner {
+            return Ok(());
+        }
 
+        let now = Instant::now();
+        if let Some(last) = self.last_post.get(&addr) {
+            let elapsed = now.duration_since(*last);
+            if elapsed < interval {
+                return Err(interval - elapsed);
+            }
+        }
 
+        self.last_post.retain(|_, last| now.duration_since(*last) < interval);
+        self.last_post.insert(addr, now);
+        Ok(())
+    }
+}
@@ -1,15 +1,51 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
 use tokio::sync::RwLock;
-use crate::server::room::Room;
+use crate::server::room::{Room, RoomSettings};
+use crate::server::server_config::Moderator;
+use crate::message::chat_message::ChatMessage;
 
 
 pub type RoomMap = Arc<RwLock<HashMap<String, Room>>>;
 
+/// Why a join was refused
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinError {
+    NoSuchRoom(String),
+    PasswordRequired,
+    WrongPassword,
+    Full { room: String, members: usize, max: usize },
+}
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinError::NoSuchRoom(room) => write!(f, "Room '{}' does not exist", room),
+            JoinError::PasswordRequired => write!(f, "Room requires a password!"),
+            JoinError::WrongPassword => write!(f, "Incorrect password!"),
+            JoinError::Full { room, members, max } => {
+                write!(f, "Room '{}' is full ({}/{} members)", room, members, max)
+            }
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct RoomManager {
-    rooms: RoomMap
+    rooms: RoomMap,
+    next_message_id: Arc<AtomicU64>,
+    /// Users that can edit or delete any message, in every room
+    moderators: Arc<Vec<Moderator>>,
+}
+
+impl Default for RoomManager {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl RoomManager {
@@ -24,9 +60,20 @@ impl RoomManager {
 
         Self {
             rooms: Arc::new(RwLock::new(rooms)),
+            next_message_id: Arc::new(AtomicU64::new(1)),
+            moderators: Arc::new(Vec::new()),
         }
     }
 
+    pub fn with_moderators(mut self, moderators: Vec<Moderator>) -> Self {
+        self.moderators = Arc::new(moderators);
+        self
+    }
+
+    fn is_moderator(&self, addr: SocketAddr, name: &str) -> bool {
+        self.moderators.iter().any(|moderator| moderator.matches(addr, name))
+    }
+
     pub async fn create_room(
         &self,
         name: String,
@@ -47,20 +94,28 @@ impl RoomManager {
         room_name: &str,
         addr: SocketAddr,
         password: Option<&str>,
-    ) -> Result<(), String> {
+    ) -> Result<(), JoinError> {
         let mut rooms = self.rooms.write().await;
         let room = rooms.get_mut(room_name)
-            .ok_or_else(|| format!("Room '{}' does not exist", room_name))?;
+            .ok_or_else(|| JoinError::NoSuchRoom(room_name.to_string()))?;
 
         // Verify if password is necessary
         if room.is_password_protected() {
             match password {
                 Some(pwd) if room.verify_password(pwd) => {},
-                Some(_) => return Err("Incorrect password!".to_string()),
-                None => return Err("Room requires a password!".to_string()),
+                Some(_) => return Err(JoinError::WrongPassword),
+                None => return Err(JoinError::PasswordRequired),
             }
         }
 
+        if !room.members.contains(&addr) && room.is_full() {
+            return Err(JoinError::Full {
+                room: room_name.to_string(),
+                members: room.members.len(),
+                max: room.settings.max_members.unwrap_or_default(),
+            });
+        }
+
         room.add_member(addr);
         Ok(())
     }
@@ -105,6 +160,158 @@ impl RoomManager {
         rooms.get(room_name).map(|room| (room.owner, room.password.clone()))
     }
 
+    pub async fn get_room_settings(&self, room_name: &str) -> Option<RoomSettings> {
+        let rooms = self.rooms.read().await;
+        rooms.get(room_name).map(|room| room.settings.clone())
+    }
+
+    pub async fn set_max_members(
+        &self,
+        room_name: &str,
+        requester: SocketAddr,
+        max_members: Option<usize>,
+    ) -> Result<(), String> {
+        if max_members == Some(0) {
+            return Err("Member limit must be at least 1".to_string());
+        }
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.get_mut(room_name)
+            .ok_or_else(|| format!("Room '{}' does not exist", room_name))?;
+        if room.owner != requester {
+            return Err("Only the owner can change the member limit".to_string());
+        }
+        room.settings.max_members = max_members;
+        Ok(())
+    }
+
+    pub async fn set_slow_mode(
+        &self,
+        room_name: &str,
+        requester: SocketAddr,
+        interval: Option<Duration>,
+    ) -> Result<(), String> {
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.get_mut(room_name)
+            .ok_or_else(|| format!("Room '{}' does not exist", room_name))?;
+        if room.owner != requester {
+            return Err("Only the owner can change slow mode".to_string());
+        }
+        room.settings.slow_mode = interval;
+        Ok(())
+    }
+
+    pub async fn set_topic(
+        &self,
+        room_name: &str,
+        requester: SocketAddr,
+        topic: Option<String>,
+    ) -> Result<(), String> {
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.get_mut(room_name)
+            .ok_or_else(|| format!("Room '{}' does not exist", room_name))?;
+        if room.owner != requester {
+            return Err("Only the owner can change the topic".to_string());
+        }
+        room.settings.topic = topic;
+        Ok(())
+    }
+
+    /// Checks slow mode before `addr` posts in `room_name`
+    pub async fn register_post(&self, room_name: &str, addr: SocketAddr) -> Result<(), String> {
+        let mut rooms = self.rooms.write().await;
+        let Some(room) = rooms.get_mut(room_name) else {
+            return Ok(());
+        };
+        room.register_post(addr).map_err(|remaining| {
+            format!(
+                "Slow mode is on: wait {}s before posting again",
+                // Rounded up, but a whole number of seconds stays as it is
+                remaining.as_millis().div_ceil(1000)
+            )
+        })
+    }
+
+    /// Gives `message` the next id and keeps it in its room's history
+    pub async fn record_message(&self, mut message: ChatMessage) -> ChatMessage {
+        message.id = Some(self.next_message_id.fetch_add(1, Ordering::Relaxed));
+        let mut rooms = self.rooms.write().await;
+        if let Some(room) = rooms.get_mut(&message.room) {
+            room.record_message(message.clone());
+        }
+        message
+    }
+
+    /// Recent messages of a room, deleted ones left out
+    pub async fn get_history(&self, room_name: &str) -> Vec<ChatMessage> {
+        let rooms = self.rooms.read().await;
+        rooms.get(room_name)
+            .map(|room| room.history.iter().filter(|msg| !msg.deleted).cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub async fn get_message(&self, room_name: &str, id: u64) -> Option<ChatMessage> {
+        let rooms = self.rooms.read().await;
+        rooms.get(room_name)?.find_message(id).cloned()
+    }
+
+    /// Id of the newest message kept for `room_name`, deleted ones included
+    pub async fn latest_message_id(&self, room_name: &str) -> Option<u64> {
+        let rooms = self.rooms.read().await;
+        rooms.get(room_name)?.history.iter().rev().find_map(|msg| msg.id)
+    }
+
+    pub async fn edit_message(
+        &self,
+        room_name: &str,
+        id: u64,
+        requester: SocketAddr,
+        requester_name: &str,
+        content: String,
+    ) -> Result<ChatMessage, String> {
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.get_mut(room_name)
+            .ok_or_else(|| format!("Room '{}' does not exist", room_name))?;
+        let message = room.editable_message(id, requester, self.is_moderator(requester, requester_name))?;
+        message.content = content.clone();
+        message.edited = true;
+        Ok(ChatMessage::edit(id, content, requester, room_name.to_string()))
+    }
+
+    pub async fn delete_message(
+        &self,
+        room_name: &str,
+        id: u64,
+        requester: SocketAddr,
+        requester_name: &str,
+    ) -> Result<ChatMessage, String> {
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.get_mut(room_name)
+            .ok_or_else(|| format!("Room '{}' does not exist", room_name))?;
+        let message = room.editable_message(id, requester, self.is_moderator(requester, requester_name))?;
+        message.content.clear();
+        message.reactions.clear();
+        message.deleted = true;
+        Ok(ChatMessage::delete(id, requester, room_name.to_string()))
+    }
+
+    /// Toggles `user`'s reaction on a message and returns the delta to broadcast
+    pub async fn toggle_reaction(
+        &self,
+        room_name: &str,
+        id: u64,
+        emoji: &str,
+        user: &str,
+    ) -> Result<ChatMessage, String> {
+        let mut rooms = self.rooms.write().await;
+        let room = rooms.get_mut(room_name)
+            .ok_or_else(|| format!("Room '{}' does not exist", room_name))?;
+        let message = room.history.iter_mut()
+            .find(|msg| msg.id == Some(id) && !msg.deleted)
+            .ok_or_else(|| format!("Message #{} not found in '{}'", id, room_name))?;
+        let added = message.toggle_reaction(emoji, user);
+        Ok(ChatMessage::reaction(id, emoji.to_string(), user.to_string(), added, room_name.to_string()))
+    }
+
     pub async fn delete_room(&self, room_name: &str, requester: SocketAddr) -> Result<(), String> {
         if room_name == "general" {
             return Err("Cannot delete this room".to_string());
@@ -118,4 +325,128 @@ impl RoomManager {
         rooms.remove(room_name);
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port)
+    }
+
+    #[tokio::test]
+    async fn test_locked_room_needs_its_password() {
+        let manager = RoomManager::new();
+        manager.create_room("vault".to_string(), Some("s3cret".to_string()), addr(1)).await.unwrap();
+
+        assert_eq!(manager.join_room("vault", addr(2), None).await, Err(JoinError::PasswordRequired));
+        assert_eq!(manager.join_room("vault", addr(2), Some("guess")).await, Err(JoinError::WrongPassword));
+        assert!(manager.get_room_members("vault").await.is_empty());
+
+        assert!(manager.join_room("vault", addr(2), Some("s3cret")).await.is_ok());
+        assert_eq!(manager.join_room("nowhere", addr(2), None).await, Err(JoinError::NoSuchRoom("nowhere".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_join_rejected_when_room_is_full() {
+        let manager = RoomManager::new();
+        let owner = addr(1);
+        manager.create_room("small".to_string(), None, owner).await.unwrap();
+        manager.set_max_members("small", owner, Some(2)).await.unwrap();
+
+        assert!(manager.join_room("small", addr(2), None).await.is_ok());
+        assert!(manager.join_room("small", addr(3), None).await.is_ok());
+
+        let err = manager.join_room("small", addr(4), None).await.unwrap_err();
+        assert_eq!(err.to_string(), "Room 'small' is full (2/2 members)");
+
+        // Members already inside can join again
+        assert!(manager.join_room("small", addr(2), None).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_only_owner_changes_settings() {
+        let manager = RoomManager::new();
+        manager.create_room("mine".to_string(), None, addr(1)).await.unwrap();
+
+        assert!(manager.set_max_members("mine", addr(2), Some(5)).await.is_err());
+        assert!(manager.set_slow_mode("mine", addr(2), Some(Duration::from_secs(5))).await.is_err());
+        assert!(manager.set_max_members("mine", addr(1), Some(0)).await.is_err());
+        assert!(manager.set_max_members("mine", addr(1), Some(5)).await.is_ok());
+
+        let settings = manager.get_room_settings("mine").await.unwrap();
+        assert_eq!(settings.max_members, Some(5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_mode_limits_posting_rate() {
+        let manager = RoomManager::new();
+        let owner = addr(1);
+        let member = addr(2);
+        manager.create_room("calm".to_string(), None, owner).await.unwrap();
+        manager.set_slow_mode("calm", owner, Some(Duration::from_secs(10))).await.unwrap();
+
+        assert!(manager.register_post("calm", member).await.is_ok());
+        let err = manager.register_post("calm", member).await.unwrap_err();
+        assert_eq!(err, "Slow mode is on: wait 10s before posting again");
+        tokio::time::advance(Duration::from_millis(500)).await;
+        let err = manager.register_post("calm", member).await.unwrap_err();
+        assert_eq!(err, "Slow mode is on: wait 10s before posting again");
+
+        // The owner is exempt
+        assert!(manager.register_post("calm", owner).await.is_ok());
+        assert!(manager.register_post("calm", owner).await.is_ok());
+
+        tokio::time::advance(Duration::from_millis(9500)).await;
+        assert!(manager.register_post("calm", member).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_message_ids_are_monotonic() {
+        let manager = RoomManager::new();
+        let first = manager.record_message(ChatMessage::new("a".into(), addr(1), "alice".into(), "general".into())).await;
+        let second = manager.record_message(ChatMessage::new("b".into(), addr(1), "alice".into(), "general".into())).await;
+        assert!(second.id > first.id);
+        assert!(manager.get_message("general", second.id.unwrap()).await.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_edit_and_delete_permissions() {
+        let manager = RoomManager::new();
+        let owner = addr(1);
+        let author = addr(2);
+        let other = addr(3);
+        manager.create_room("den".to_string(), None, owner).await.unwrap();
+        let msg = manager.record_message(ChatMessage::new("hi".into(), author, "bob".into(), "den".into())).await;
+        let id = msg.id.unwrap();
+
+        assert!(manager.edit_message("den", id, other, "carol", "hacked".into()).await.is_err());
+        let edit = manager.edit_message("den", id, author, "bob", "hello".into()).await.unwrap();
+        assert_eq!(edit.content, "hello");
+        assert!(edit.edited);
+
+        // A nick alone doesn't make it theirs
+        assert!(manager.edit_message("den", id, addr(4), "bob", "mine now".into()).await.is_err());
+
+        // The owner moderates the room
+        assert!(manager.delete_message("den", id, owner, "alice").await.is_ok());
+        let err = manager.edit_message("den", id, author, "bob", "again".into()).await.unwrap_err();
+        assert!(err.contains("not found"));
+    }
+
+    #[tokio::test]
+    async fn test_moderators_cover_general() {
+        let moderator = SocketAddr::from(([10, 0, 0, 1], 4000));
+        let manager = RoomManager::new().with_moderators(vec!["Mod@10.0.0.1".parse().unwrap()]);
+        let msg = manager.record_message(ChatMessage::new("spam".into(), addr(2), "bob".into(), "general".into())).await;
+        let id = msg.id.unwrap();
+
+        assert!(manager.delete_message("general", id, addr(3), "carol").await.is_err());
+        // Both the nick and the address have to match
+        assert!(manager.delete_message("general", id, addr(3), "mod").await.is_err());
+        assert!(manager.delete_message("general", id, moderator, "carol").await.is_err());
+        assert!(manager.delete_message("general", id, moderator, "mod").await.is_ok());
+    }
+}
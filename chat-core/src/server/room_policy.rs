@@ -0,0 +1,86 @@
+use std::fmt;
+
+pub const MIN_ROOM_NAME_LENGTH: usize = 2;
+pub const MAX_ROOM_NAME_LENGTH: usize = 24;
+pub const MAX_TOPIC_LENGTH: usize = 120;
+
+const ALLOWED_SYMBOLS: &[char] = &['_', '-', '.'];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoomNameError {
+    TooShort,
+    TooLong,
+    InvalidCharacter(char),
+}
+
+impl fmt::Display for RoomNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoomNameError::TooShort | RoomNameError::TooLong => write!(
+                f,
+                "Room name must be between {}-{} characters",
+                MIN_ROOM_NAME_LENGTH, MAX_ROOM_NAME_LENGTH
+            ),
+            RoomNameError::InvalidCharacter(c) => write!(
+                f,
+                "'{}' is not allowed in a room name (use letters, digits, '_', '-' or '.')",
+                c.escape_default()
+            ),
+        }
+    }
+}
+
+pub struct RoomPolicy;
+
+impl RoomPolicy {
+    /// Room names travel as they are in commands and in the room and
+    /// mention frames, so they stay to one word without separators
+    pub fn validate_name(name: &str) -> Result<(), RoomNameError> {
+        let length = name.chars().count();
+        if length < MIN_ROOM_NAME_LENGTH {
+            return Err(RoomNameError::TooShort);
+        }
+        if length > MAX_ROOM_NAME_LENGTH {
+            return Err(RoomNameError::TooLong);
+        }
+        match name.chars().find(|c| !c.is_alphanumeric() && !ALLOWED_SYMBOLS.contains(c)) {
+            Some(c) => Err(RoomNameError::InvalidCharacter(c)),
+            None => Ok(()),
+        }
+    }
+
+    /// A topic is one line of text shown under the room name
+    pub fn validate_topic(topic: &str) -> Result<(), String> {
+        if topic.chars().count() > MAX_TOPIC_LENGTH {
+            return Err(format!("Topic is limited to {} characters", MAX_TOPIC_LENGTH));
+        }
+        if topic.chars().any(char::is_control) {
+            return Err("Topic must fit on one line".to_string());
+        }
+        Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_room_names() {
+        assert!(RoomPolicy::validate_name("rust-lang.br").is_ok());
+        assert!(RoomPolicy::validate_name("café").is_ok());
+        assert_eq!(RoomPolicy::validate_name("a"), Err(RoomNameError::TooShort));
+        assert_eq!(RoomPolicy::validate_name(&"a".repeat(25)), Err(RoomNameError::TooLong));
+        assert_eq!(RoomPolicy::validate_name("two words"), Err(RoomNameError::InvalidCharacter(' ')));
+        assert_eq!(RoomPolicy::validate_name("a|b"), Err(RoomNameError::InvalidCharacter('|')));
+        assert_eq!(RoomPolicy::validate_name("vault🔒"), Err(RoomNameError::InvalidCharacter('🔒')));
+    }
+
+    #[test]
+    fn test_topics() {
+        assert!(RoomPolicy::validate_topic("Weekly sync: notes in the wiki").is_ok());
+        assert!(RoomPolicy::validate_topic(&"a".repeat(121)).is_err());
+        assert!(RoomPolicy::validate_topic("two\nlines").is_err());
+    }
+}
@@ -17,10 +17,14 @@ pub struct ChatServer {
 
 impl ChatServer {
     pub fn new(config: ServerConfig) -> Self {
+        let client_manager = ClientManager::new()
+            .with_offline_queue(config.offline_queue_size, config.offline_message_ttl)
+            .with_idle_timeout(config.idle_timeout);
+        let room_manager = RoomManager::new().with_moderators(config.moderators.clone());
         Self {
             config,
-            client_manager: ClientManager::new(),
-            room_manager: RoomManager::new(),
+            client_manager,
+            room_manager,
         }
     }
 
@@ -41,6 +45,7 @@ impl ChatServer {
             let room_manager = self.room_manager.clone();
             let message_sender = sender.clone();
             let message_receiver = sender.subscribe();
+            let config = self.config.clone();
             let anon_id = anonymous_counter;
             anonymous_counter += 1;
 
@@ -53,6 +58,7 @@ impl ChatServer {
                     message_sender,
                     message_receiver,
                     anon_id,
+                    config,
                 );
                 connection.handler().await;
                 debug!("❌ Client disconnected: {}", addr);
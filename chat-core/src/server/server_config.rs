@@ -1,15 +1,176 @@
+use std::fmt::Display;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use crate::client::{offline_queue, presence};
+use crate::client::nick_policy::NickPolicy;
+use crate::server::rate_limiter::RateLimitConfig;
+
+#[derive(Clone, Debug)]
 pub struct ServerConfig {
     pub address: String,
+    /// Longest line read from a client, in bytes, see `line_budget`
     pub buffer_size: usize,
     pub max_clients: usize,
+    /// Longest chat message accepted, in characters
+    pub max_message_length: usize,
+    /// Most lines in one multi-line message
+    pub max_message_lines: usize,
+    pub rate_limit: RateLimitConfig,
+    /// Whispers each sender can have waiting for offline users
+    pub offline_queue_size: usize,
+    /// Queued whispers older than this are dropped
+    pub offline_message_ttl: Duration,
+    /// Inactivity after which a user is shown as idle
+    pub idle_timeout: Duration,
+    /// Users that can edit or delete any message, in every room
+    pub moderators: Vec<Moderator>,
 }
 
-impl ServerConfig {
-    pub fn default() -> Self {
+/// A nick that moderates every room while connected from `ip`, written `nick@ip`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Moderator {
+    /// Canonical form
+    nick: String,
+    ip: IpAddr,
+}
+
+impl Moderator {
+    pub fn matches(&self, addr: SocketAddr, name: &str) -> bool {
+        addr.ip() == self.ip && NickPolicy::canonical(name) == self.nick
+    }
+}
+
+impl FromStr for Moderator {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (nick, ip) = s.trim().rsplit_once('@')
+            .ok_or_else(|| format!("'{}' is not nick@address", s.trim()))?;
+        let nick = NickPolicy::normalize(nick).map_err(|e| format!("'{}': {}", nick, e))?;
+        let ip = ip.parse().map_err(|e| format!("'{}': {}", ip, e))?;
+        Ok(Self { nick: NickPolicy::canonical(&nick), ip })
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
         Self {
             address: "0.0.0.0:4556".to_string(),
-            buffer_size: 1024,
+            buffer_size: line_budget(500),
             max_clients: 32,
+            max_message_length: 500,
+            max_message_lines: 20,
+            rate_limit: RateLimitConfig::default(),
+            offline_queue_size: offline_queue::DEFAULT_MAX_QUEUED,
+            offline_message_ttl: offline_queue::DEFAULT_MAX_AGE,
+            idle_timeout: presence::DEFAULT_IDLE_AFTER,
+            moderators: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// The defaults, with the settings given in `RUSTATUI_*` environment variables
+    pub fn from_env() -> Result<Self, String> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// The defaults, with the settings `var` has a value for
+    pub fn from_vars(var: impl Fn(&str) -> Option<String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let limits = &mut config.rate_limit;
+        set(&var, "RUSTATUI_RATE_MESSAGES", &mut limits.messages_per_sec)?;
+        set(&var, "RUSTATUI_RATE_BURST", &mut limits.message_burst)?;
+        set(&var, "RUSTATUI_RATE_BYTES", &mut limits.bytes_per_sec)?;
+        set(&var, "RUSTATUI_RATE_BYTE_BURST", &mut limits.byte_burst)?;
+        set(&var, "RUSTATUI_MAX_VIOLATIONS", &mut limits.max_violations)?;
+        let mut mute_secs = limits.mute_duration.as_secs();
+        set(&var, "RUSTATUI_MUTE_SECS", &mut mute_secs)?;
+        limits.mute_duration = Duration::from_secs(mute_secs);
+        let mut window_secs = limits.violation_window.as_secs();
+        set(&var, "RUSTATUI_VIOLATION_WINDOW_SECS", &mut window_secs)?;
+        limits.violation_window = Duration::from_secs(window_secs);
+        if let Some(moderators) = var("RUSTATUI_MODERATORS") {
+            config.moderators = moderators.split(',')
+                .filter(|entry| !entry.trim().is_empty())
+                .map(str::parse)
+                .collect::<Result<_, String>>()
+                .map_err(|e| format!("RUSTATUI_MODERATORS: {}", e))?;
+        }
+
+        let rates = [limits.messages_per_sec, limits.message_burst, limits.bytes_per_sec, limits.byte_burst];
+        if rates.iter().any(|rate| !rate.is_finite() || *rate <= 0.0) {
+            return Err("Rate limits must be positive numbers".to_string());
         }
+        Ok(config)
     }
-}
\ No newline at end of file
+}
+
+/// Room for the command in front of a message, e.g. `/whisper <nick> `
+const COMMAND_PREFIX_BYTES: usize = 256;
+
+/// Bytes a line needs to carry a message of `max_message_length` characters.
+/// A character takes at most 4 bytes in UTF-8 and escaping only turns `%`
+/// and line breaks into 3, so 4 bytes a character covers the escaped form.
+pub const fn line_budget(max_message_length: usize) -> usize {
+    max_message_length * 4 + COMMAND_PREFIX_BYTES
+}
+
+/// Overwrites `setting` with the value of `name`, when it has one
+fn set<T>(var: &impl Fn(&str) -> Option<String>, name: &str, setting: &mut T) -> Result<(), String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    if let Some(value) = var(name) {
+        *setting = value.trim().parse().map_err(|e| format!("{}: {}", name, e))?;
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::message::protocol::escape_line;
+
+    fn with_vars(vars: &[(&str, &str)]) -> Result<ServerConfig, String> {
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        ServerConfig::from_vars(|name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn test_rate_limits_from_vars() {
+        let config = with_vars(&[("RUSTATUI_RATE_MESSAGES", "0.5"), ("RUSTATUI_MUTE_SECS", " 90 ")]).unwrap();
+        assert_eq!(config.rate_limit.messages_per_sec, 0.5);
+        assert_eq!(config.rate_limit.mute_duration, Duration::from_secs(90));
+        assert_eq!(config.rate_limit.message_burst, RateLimitConfig::default().message_burst);
+
+        assert!(with_vars(&[("RUSTATUI_MAX_VIOLATIONS", "many")]).unwrap_err().starts_with("RUSTATUI_MAX_VIOLATIONS"));
+        assert!(with_vars(&[("RUSTATUI_RATE_BURST", "0")]).is_err());
+    }
+
+    #[test]
+    fn test_lines_fit_the_longest_message() {
+        let config = ServerConfig::default();
+        let emoji = "👋".repeat(config.max_message_length);
+        assert!(format!("/whisper somebody {}", emoji).len() <= config.buffer_size);
+        let escaped = escape_line(&"%\n".repeat(config.max_message_length / 2));
+        assert!(format!("/reply 123456 {}", escaped).len() <= config.buffer_size);
+    }
+
+    #[test]
+    fn test_moderators_from_vars() {
+        let config = with_vars(&[("RUSTATUI_MODERATORS", "Alice@10.0.0.1, bob@::1,")]).unwrap();
+        let alice = SocketAddr::from(([10, 0, 0, 1], 5000));
+        assert_eq!(config.moderators.len(), 2);
+        assert!(config.moderators[0].matches(alice, "alice"));
+        assert!(!config.moderators[0].matches(alice, "bob"));
+        assert!(!config.moderators[0].matches(SocketAddr::from(([10, 0, 0, 2], 5000)), "alice"));
+
+        assert!(with_vars(&[("RUSTATUI_MODERATORS", "10.0.0.1")]).is_err());
+        assert!(with_vars(&[("RUSTATUI_MODERATORS", "alice@somewhere")]).is_err());
+    }
+}
@@ -4,6 +4,7 @@ impl Colors {
     // ANSI Code for colors
     pub const RESET: &'static str = "\x1b[0m";
     pub const BOLD: &'static str = "\x1b[1m";
+    pub const ITALIC: &'static str = "\x1b[3m";
 
     // Basic colors
     pub const RED: &'static str = "\x1b[31m";
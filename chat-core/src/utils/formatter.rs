@@ -16,17 +16,35 @@ pub fn format_message(msg: &ChatMessage) -> String {
 
     match msg.message_type {
         MessageType::Chat => {
+            let reply = match msg.reply_to {
+                Some(parent) => format!("↪ #{} ", parent),
+                None => String::new(),
+            };
             format!(
-                "{}[{}]{} {}{}{}: {}\n",
+                "{}[{}]{} {}{}{}: {}{}\n",
                 Colors::BOLD,
                 timestamp,
                 Colors::RESET,
                 user_color_ansi,
                 msg.sender_name,
                 Colors::RESET,
+                reply,
                 msg.content
             )
         }
+        MessageType::Action => {
+            format!(
+                "{}[{}]{} {}{}* {} {}{}\n",
+                Colors::BOLD,
+                timestamp,
+                Colors::RESET,
+                Colors::ITALIC,
+                user_color_ansi,
+                msg.sender_name,
+                msg.content,
+                Colors::RESET
+            )
+        }
         MessageType::System => {
             format!(
                 "{}[SYSTEM] {}{}\n",
@@ -63,6 +81,68 @@ pub fn format_message(msg: &ChatMessage) -> String {
         MessageType::RoomJoin => {
             String::new()
         }
+        MessageType::Typing => {
+            String::new()
+        }
+        MessageType::Edit => {
+            format!(
+                "{}[SYSTEM] Message #{} edited: {}{}\n",
+                Colors::SYSTEM,
+                msg.id.unwrap_or_default(),
+                msg.content,
+                Colors::RESET
+            )
+        }
+        MessageType::Delete => {
+            format!(
+                "{}[SYSTEM] Message #{} deleted{}\n",
+                Colors::SYSTEM,
+                msg.id.unwrap_or_default(),
+                Colors::RESET
+            )
+        }
+        MessageType::Reaction => {
+            format!(
+                "{}[SYSTEM] {} {} {} on #{}{}\n",
+                Colors::SYSTEM,
+                msg.sender_name,
+                if msg.deleted { "removed" } else { "reacted" },
+                msg.content,
+                msg.id.unwrap_or_default(),
+                Colors::RESET
+            )
+        }
+        MessageType::Mention => {
+            format!(
+                "{}[@ {} in {}] {}{}\n",
+                Colors::WHISPER,
+                msg.sender_name,
+                msg.room,
+                msg.content,
+                Colors::RESET
+            )
+        }
+        MessageType::NickChange => {
+            format!(
+                "{}[SYSTEM] {} is now known as {}{}\n",
+                Colors::SYSTEM,
+                msg.sender_name,
+                msg.content,
+                Colors::RESET
+            )
+        }
+        MessageType::Topic if msg.content.is_empty() => {
+            format!("{}[SYSTEM] Topic of {} cleared{}\n", Colors::SYSTEM, msg.room, Colors::RESET)
+        }
+        MessageType::Topic => {
+            format!(
+                "{}[SYSTEM] Topic of {}: {}{}\n",
+                Colors::SYSTEM,
+                msg.room,
+                msg.content,
+                Colors::RESET
+            )
+        }
     }
 }
 
@@ -1,6 +1,8 @@
 use crate::state::state::{AppPage, ConnectionStatus,AppState, InputMode, FocusedField};
 use crate::state::action::Action;
 use crate::client::tui_client::TuiClient;
+use std::time::Instant;
+use chat_core::message::chat_message::ChatMessage;
 use tokio::sync::mpsc;
 
 pub struct App {
@@ -9,6 +11,12 @@ pub struct App {
     action_rx: mpsc::UnboundedReceiver<Action>,
 }
 
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl App {
     pub fn new() -> Self {
         let (action_tx, action_rx) = mpsc::unbounded_channel();
@@ -24,34 +32,50 @@ impl App {
         self.action_tx.clone()
     }
 
-    pub async fn tick(&mut self) {
-        // Collect messages
-        let messages: Vec<_> = if let Some(client) = &mut self.state.client {
-            let mut msgs = Vec::new();
-            while let Ok(message) = client.message_rx.try_recv() {
-                msgs.push(message);
-            }
-            msgs
-        } else {
-            Vec::new()
-        };
-
-        // Add message after borrow is done
-        for message in messages {
-            self.state.add_message(message);
-        }
+    /// Waits until an action is queued, by the user or the server
+    pub async fn next_action(&mut self) -> Option<Action> {
+        self.action_rx.recv().await
+    }
 
-        // Process missing actions
+    /// Handles an action along with everything queued behind it, so a burst
+    /// of messages costs a single redraw
+    pub async fn update(&mut self, action: Action) {
+        self.handle_action(action).await;
         while let Ok(action) = self.action_rx.try_recv() {
             self.handle_action(action).await;
         }
+        self.report_read(false).await;
+    }
+
+    /// Catches up on what only time changes: read reports held back by the
+    /// throttle and typing notices gone stale. Tells whether to redraw.
+    pub async fn housekeep(&mut self) -> bool {
+        self.report_read(false).await;
+        self.state.expire_typing(Instant::now())
+    }
+
+    /// Asks the server to move us to `room`, with its password when one is kept
+    async fn join_room(&mut self, room: &str) {
+        self.stop_typing().await;
+        if let Some(client) = &self.state.client {
+            let _ = client.send_message(&self.state.join_command(room)).await;
+        }
+    }
+
+    /// Tells the server how far we have read in the current room
+    async fn report_read(&mut self, force: bool) {
+        if let Some((room, id)) = self.state.read_update(Instant::now(), force)
+            && let Some(client) = &self.state.client {
+            let _ = client.send_message(&format!("/read {} {}", room, id)).await;
+        }
     }
 
     async fn connect(&mut self) {
-        if self.state.server_address.is_empty() || self.state.username.is_empty() {
+        if self.state.server_address.is_empty() || self.state.username_input.is_empty() {
             self.state.connection_status = ConnectionStatus::Error("Missing address or username".to_string());
             return;
         }
+        self.state.username = self.state.username_input.to_string();
         self.state.connection_status = ConnectionStatus::Connecting;
 
         match TuiClient::connect(&self.state.server_address, self.state.username.clone(), self.action_tx.clone()).await {
@@ -71,7 +95,16 @@ impl App {
             return;
         }
 
-        let message = self.state.message_input.clone();
+        let message = match self.state.outgoing_line() {
+            Ok(message) => message,
+            Err(reason) => {
+                // Kept in the box to fix; the notice lands in the pane being shown
+                let room = self.state.get_current_room().to_string();
+                self.state.add_message(ChatMessage::system(reason, room));
+                return;
+            }
+        };
+        self.state.input_history.push(&self.state.message_input);
 
         if let Some(client) = &self.state.client {
             // Send to the server - server will echo it back
@@ -81,13 +114,24 @@ impl App {
         }
 
         self.state.clear_input();
+        self.state.reply_to = None;
+        // Receivers drop the notice when the message arrives
+        self.state.typing_sent_at = None;
     }
 
-    fn handle_text_input(field: &mut String, input: &str) {
-        if input == "\x08" {
-            field.pop();
-        } else {
-            field.push_str(input);
+    /// Ends our typing notice in the current room before a join moves us out of it
+    async fn stop_typing(&mut self) {
+        if self.state.end_typing()
+            && let Some(client) = &self.state.client {
+            let _ = client.send_message("/typing stop").await;
+        }
+    }
+
+    async fn send_typing(&mut self) {
+        if let Some(typing) = self.state.typing_update(Instant::now())
+            && let Some(client) = &self.state.client {
+            let command = if typing { "/typing start" } else { "/typing stop" };
+            let _ = client.send_message(command).await;
         }
     }
 
@@ -99,10 +143,30 @@ impl App {
                 FocusedField::ConnectButton => FocusedField::ServerAddress,
                 _ => FocusedField::ServerAddress,
             },
-            AppPage::Chat => FocusedField::MessageInput,
+            AppPage::Chat => self.toggle_message_list_focus(),
         };
     }
 
+    /// On the chat page Tab switches between the input and selecting messages
+    fn start_editing(&mut self) {
+        self.state.focused_field = FocusedField::MessageInput;
+        self.state.selected_message = None;
+        self.state.input_mode = InputMode::Editing;
+    }
+
+    fn toggle_message_list_focus(&mut self) -> FocusedField {
+        match self.state.focused_field {
+            FocusedField::MessageList => {
+                self.state.selected_message = None;
+                FocusedField::MessageInput
+            }
+            _ => {
+                self.state.select_previous();
+                FocusedField::MessageList
+            }
+        }
+    }
+
     fn focus_previous(&mut self) {
         self.state.focused_field = match self.state.current_page {
             AppPage::Connection => match self.state.focused_field {
@@ -111,10 +175,16 @@ impl App {
                 FocusedField::ConnectButton => FocusedField::Username,
                 _ => FocusedField::ConnectButton,
             },
-            AppPage::Chat => FocusedField::MessageInput,
+            AppPage::Chat => self.toggle_message_list_focus(),
         };
     }
     async fn handle_action(&mut self, action: Action) {
+        // Editing the line settles the completion shown
+        if matches!(action, Action::UpdateMessageInput(_) | Action::HistoryPrevious
+            | Action::HistoryNext | Action::SearchHistory | Action::FocusNext | Action::FocusPrevious
+            | Action::InsertMention(_)) {
+            self.state.completion = None;
+        }
         match action {
             Action::Quit => {
                 if let Some(client) = &self.state.client {
@@ -131,62 +201,144 @@ impl App {
                 self.state.connection_status = ConnectionStatus::Disconnected;
                 self.state.current_page = AppPage::Connection;
             },
+            // Enter takes the highlighted completion rather than sending
+            Action::SendMessage if self.state.completion.is_some() => {
+                self.state.completion = None;
+            }
+            // Enter takes the history match rather than sending
+            Action::SendMessage if self.state.history_search.is_some() => {
+                self.state.accept_history_search();
+            }
             Action::SendMessage => {
                 self.send_message().await;
             },
-            Action::UpdateServerAddress(input) => {
-                Self::handle_text_input(&mut self.state.server_address, &input);
+            Action::UpdateServerAddress(command) => {
+                self.state.server_address.apply(command);
                 // Reset error status when user edits
                 if matches!(self.state.connection_status, ConnectionStatus::Error(_)) {
                     self.state.connection_status = ConnectionStatus::Disconnected;
                 }
             },
-            Action::UpdateUsername(input) => {
-                Self::handle_text_input(&mut self.state.username, &input);
+            Action::UpdateUsername(command) => {
+                self.state.username_input.apply(command);
                 // Reset error status when user edits
                 if matches!(self.state.connection_status, ConnectionStatus::Error(_)) {
                     self.state.connection_status = ConnectionStatus::Disconnected;
                 }
             },
-            Action::UpdateMessageInput(input) => {
-                Self::handle_text_input(&mut self.state.message_input, &input);
+            Action::UpdateMessageInput(command) if self.state.history_search.is_some() => {
+                self.state.edit_history_search(command);
+            }
+            Action::UpdateMessageInput(command) => {
+                self.state.message_input.apply(command);
+                self.send_typing().await;
+            }
+            Action::HistoryPrevious => self.state.history_previous(),
+            Action::HistoryNext => self.state.history_next(),
+            Action::SearchHistory => self.state.search_history(),
+            Action::Complete(forward) => self.state.complete(forward),
+            // Esc puts back the word a completion replaced
+            Action::ToggleInputMode if self.state.completion.is_some() => {
+                self.state.cancel_completion();
+            }
+            // Esc ends a history search before leaving editing mode
+            Action::ToggleInputMode if self.state.history_search.is_some() => {
+                self.state.cancel_history_search();
             }
             Action::ToggleInputMode => {
                 self.state.input_mode = match self.state.input_mode {
                     InputMode::Normal => {
-                        match self.state.current_page{
-                            AppPage::Chat => {
-                                self.state.focused_field = FocusedField::MessageInput;
-                            },
-                            _ => {}
+                        if self.state.current_page == AppPage::Chat {
+                            self.state.focused_field = FocusedField::MessageInput;
+                            self.state.selected_message = None;
                         }
                         InputMode::Editing
                     },
-                    InputMode::Editing => InputMode::Normal,
+                    InputMode::Editing => {
+                        self.state.reply_to = None;
+                        InputMode::Normal
+                    },
                 };
             }
+            Action::StartEditing => self.start_editing(),
             Action::NextRoom => {
-                self.state.next_room();
-                if let Some(client) = &self.state.client {
-                    if let Some(room) = &self.state.current_room {
-                        let _ = client.change_room(room).await;
-                    }
+                self.report_read(true).await;
+                if let Some(room) = self.state.next_room() {
+                    self.join_room(&room).await;
                 }
             },
             Action::PreviousRoom => {
-                self.state.previous_room();
-                if let Some(client) = &self.state.client {
-                    if let Some(room) = &self.state.current_room {
-                        let _ = client.change_room(room).await;
-                    }
+                self.report_read(true).await;
+                if let Some(room) = self.state.previous_room() {
+                    self.join_room(&room).await;
+                }
+            },
+            Action::SelectSidebarEntry(index) => {
+                self.report_read(true).await;
+                if let Some(room) = self.state.select_sidebar(index) {
+                    self.join_room(&room).await;
+                }
+            },
+            Action::OpenDirectMessage(peer) => {
+                self.report_read(true).await;
+                self.state.open_dm(&peer);
+            },
+            Action::InsertMention(nick) => {
+                self.state.insert_mention(&nick);
+                self.start_editing();
+                self.send_typing().await;
+            },
+            Action::CloseDirectMessage => {
+                if let Some(peer) = self.state.active_dm.take() {
+                    self.state.conversations.retain(|c| c.peer != peer);
+                    self.state.close_dm();
                 }
             },
+            Action::SelectPreviousMessage => {
+                self.state.select_previous()
+            },
+            Action::SelectNextMessage => {
+                self.state.select_next()
+            },
+            Action::ReplyToSelected if self.state.start_reply() => {
+                self.state.selected_message = None;
+                self.state.focused_field = FocusedField::MessageInput;
+                self.state.input_mode = InputMode::Editing;
+            },
+            Action::ToggleThread => {
+                self.state.toggle_thread()
+            },
+            Action::ToggleMentions => {
+                self.state.toggle_mentions()
+            },
+            Action::TerminalFocusChanged(focused) => {
+                self.state.set_terminal_focus(focused)
+            },
+            Action::ReactToSelected(emoji) => {
+                if let Some(id) = self.state.selected_id()
+                    && let Some(client) = &self.state.client {
+                    let _ = client.send_message(&format!("/react {} {}", id, emoji)).await;
+                }
+            },
+            Action::ReactionChanged { id, emoji, user, added } => {
+                self.state.apply_reaction(id, &emoji, &user, added);
+            },
+            Action::MessageEdited(id, content) => {
+                self.state.edit_message(id, content);
+            },
+            Action::MessageDeleted(id) => {
+                self.state.delete_message(id);
+            },
             Action::ScrollUp => {
                 self.state.scroll_up()
             },
             Action::ScrollDown => {
                 self.state.scroll_down()
             },
+            Action::PageUp => self.state.page_up(),
+            Action::PageDown => self.state.page_down(),
+            Action::ScrollToTop => self.state.scroll_to_top(),
+            Action::ScrollToBottom => self.state.scroll_to_bottom(),
             Action::FocusNext => {
                 self.focus_next()
             },
@@ -194,35 +346,183 @@ impl App {
                 self.focus_previous()
             },
             Action::UpdateUserList(users) => {
+                self.state.presence = users.iter().cloned().collect();
                 // Filter out current user to avoid duplication
                 self.state.users_in_room = users.into_iter()
+                    .map(|(name, _)| name)
                     .filter(|u| u != &self.state.username)
                     .collect();
             },
+            Action::TypingChanged(name, typing) => {
+                self.state.set_typing(name, typing, Instant::now());
+            },
+            Action::NickChanged(old_name, new_name) => {
+                if self.state.username == old_name {
+                    self.state.username = new_name.clone();
+                }
+                for user in self.state.users_in_room.iter_mut() {
+                    if *user == old_name {
+                        *user = new_name.clone();
+                    }
+                }
+                self.state.users_in_room.retain(|u| u != &self.state.username);
+                if let Some(presence) = self.state.presence.remove(&old_name) {
+                    self.state.presence.insert(new_name.clone(), presence);
+                }
+                self.state.rename_peer(&old_name, &new_name);
+            },
             Action::UpdateRoomList(rooms) => {
                 self.state.available_rooms = rooms;
             },
             Action::CreateRoom(room_name, password) => {
+                self.stop_typing().await;
                 if let Some(client) = &self.state.client {
                     let command = match password {
                         Some(pwd) => format!("/create {} {}", room_name, pwd),
                         None => format!("/create {}", room_name),
                     };
-                    let _ = client.send_message(&command).await;
+                    if let Err(e) = client.send_message(&command).await
+                        && let Some(form) = &mut self.state.create_room {
+                        form.refused(e.to_string());
+                    }
                 }
             },
 
             Action::JoinRoomWithPassword(room_name, password) => {
+                self.stop_typing().await;
                 if let Some(client) = &self.state.client {
                     let _ = client.send_message(&format!("/join {} {}", room_name, password)).await;
                 }
             },
+            Action::ToggleHelp => self.state.toggle_help(),
+            Action::ScrollHelp(down) => self.state.scroll_help(down),
+            Action::OpenCommandPalette => self.state.open_command_palette(),
+            Action::CommandPaletteInput(command) => {
+                if let Some(palette) = &mut self.state.command_palette {
+                    palette.apply(command);
+                }
+            },
+            Action::CommandPaletteSelect(forward) => {
+                if let Some(palette) = &mut self.state.command_palette {
+                    palette.select(forward);
+                }
+            },
+            Action::RunPaletteEntry => {
+                if let Some(action) = self.state.run_palette_entry() {
+                    let _ = self.action_tx.send(action);
+                }
+            },
+            Action::CloseCommandPalette => {
+                self.state.close_command_palette();
+            },
+            Action::OpenCreateRoom => self.state.open_create_room(),
+            Action::CreateRoomInput(command) => {
+                if let Some(form) = &mut self.state.create_room {
+                    form.apply(command);
+                }
+            },
+            Action::CreateRoomFocus(forward) => {
+                if let Some(form) = &mut self.state.create_room {
+                    form.focus_next(forward);
+                }
+            },
+            Action::SubmitCreateRoom => {
+                if let Some((room, password)) = self.state.create_room.as_mut().and_then(|form| form.submit()) {
+                    let _ = self.action_tx.send(Action::CreateRoom(room, password));
+                }
+            },
+            Action::CancelCreateRoom => {
+                self.state.close_create_room();
+            },
+            // Shown in the pane already; a form waiting on that line gets it back
+            Action::ServerWarning(warning) => {
+                if let Some(form) = &mut self.state.create_room
+                    && form.pending {
+                    form.refused(warning);
+                }
+            },
+            Action::CreateRoomFailed(reason) => match &mut self.state.create_room {
+                Some(form) => form.refused(reason),
+                // A `/create` typed in the message box
+                None => {
+                    let room = self.state.get_current_room().to_string();
+                    self.state.add_message(ChatMessage::system(format!("✗ {}", reason), room));
+                }
+            },
+            Action::TopicChanged(room, topic) if self.state.get_current_room() == room => {
+                self.state.room_topic = topic;
+            },
+            Action::PasswordRequired { room, wrong } => {
+                self.state.open_password_prompt(room, wrong);
+            },
+            Action::UpdatePasswordInput(command) => {
+                if let Some(prompt) = &mut self.state.password_prompt {
+                    prompt.input.apply(command);
+                }
+            },
+            Action::ToggleRememberPassword => {
+                if let Some(prompt) = &mut self.state.password_prompt {
+                    prompt.remember = !prompt.remember;
+                }
+            },
+            Action::SubmitPassword => {
+                self.stop_typing().await;
+                if let Some((room, password)) = self.state.submit_password()
+                    && let Some(client) = &self.state.client {
+                    let _ = client.send_message(&format!("/join {} {}", room, password)).await;
+                }
+            },
+            Action::CancelPassword => {
+                if let Some(room) = self.state.cancel_password_prompt() {
+                    self.join_room(&room).await;
+                }
+            },
             Action::JoinRoom(room) => {
-                self.state.change_room(room);
+                self.state.joined_room = Some(room.clone());
+                // Joined with /join: save how far we got in the old room
+                if self.state.get_current_room() != room {
+                    self.report_read(true).await;
+                }
+                self.state.close_dm();
+                self.state.change_room(room.clone());
                 self.state.messages.clear();
-                self.state.scroll_offset = 0;
+                self.state.scroll_to_bottom();
+                if let Some(commands) = self.state.finish_create_room(&room)
+                    && let Some(client) = &self.state.client {
+                    for command in commands {
+                        let _ = client.send_message(&command).await;
+                    }
+                }
+            },
+            Action::MessageReceived(message) => self.state.add_message(*message),
+            Action::RoomHistory { room, messages, read_marker } => {
+                self.state.load_history(&room, messages, read_marker);
             },
             _ => {}
         }
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_live_messages_survive_the_history_replay() {
+        let mut app = App::new();
+        app.state.username = "alice".to_string();
+        let wire = "ROOM_JOINED|dev\n\
+            HISTORY|dev\n\
+            CHAT|1|||2026-01-02 09:00:00|bob|#FFFFFF|before\n\
+            HISTORY_END|dev|1\n\
+            CHAT|2|||2026-01-02 09:01:00|bob|#FFFFFF|live\n";
+        TuiClient::receive_messages_with_reader(wire.as_bytes(), "alice".to_string(), app.action_tx()).await;
+
+        let action = app.next_action().await.unwrap();
+        app.update(action).await;
+        assert_eq!(app.state.get_current_room(), "dev");
+        let contents: Vec<&str> = app.state.messages.iter().map(|msg| msg.content.as_str()).collect();
+        assert_eq!(contents, vec!["before", "live"]);
+    }
+}
@@ -2,14 +2,31 @@ use std::sync::Arc;
 use tokio::{
     net::TcpStream,
     sync::{Mutex, mpsc},
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter}
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter}
 };
+use chat_core::client::presence::Presence;
 use chat_core::message::chat_message::ChatMessage;
-use crate::state::action::Action::{JoinRoom, UpdateRoomList};
+use chat_core::message::protocol::{escape_line, unescape_line};
+use crate::state::action::Action::{self, JoinRoom, UpdateRoomList};
+
+/// Room history being received, loaded in one go once complete
+struct HistoryReplay {
+    room: String,
+    messages: Vec<ChatMessage>,
+    /// Edits and reactions of the replayed messages, applied after them
+    updates_tx: mpsc::UnboundedSender<Action>,
+    updates_rx: mpsc::UnboundedReceiver<Action>,
+}
+
+impl HistoryReplay {
+    fn new(room: String) -> Self {
+        let (updates_tx, updates_rx) = mpsc::unbounded_channel();
+        Self { room, messages: Vec::new(), updates_tx, updates_rx }
+    }
+}
 
 pub struct TuiClient {
     writer: Arc<Mutex<BufWriter<tokio::net::tcp::OwnedWriteHalf>>>,
-    pub message_rx: mpsc::UnboundedReceiver<ChatMessage>,
     pub action_tx: Option<mpsc::UnboundedSender<crate::state::action::Action>>,
 }
 
@@ -23,7 +40,6 @@ impl TuiClient {
         let (read_half, write_half) = stream.into_split();
 
         let writer = Arc::new(Mutex::new(BufWriter::new(write_half)));
-        let (message_tx, message_rx) = mpsc::unbounded_channel();
 
         let mut reader = BufReader::new(read_half);
         
@@ -56,6 +72,10 @@ impl TuiClient {
 
         // Check if the name was accepted
         for line in &validation_lines {
+            if let Some(reason) = line.split("Invalid name: ").nth(1) {
+                let reason = reason.trim().trim_end_matches("Try again.").trim();
+                return Err(reason.to_string().into());
+            }
             if line.contains("The name is taken") {
                 return Err("Username already taken. Please choose another name.".into());
             }
@@ -68,23 +88,24 @@ impl TuiClient {
         let username_clone = username.clone();
         let action_tx_clone = action_tx.clone();
         tokio::spawn(async move {
-            Self::receive_messages_with_reader(reader, message_tx, username_clone, action_tx_clone).await;
+            Self::receive_messages_with_reader(reader, username_clone, action_tx_clone).await;
         });
 
         Ok(Self {
             writer,
-            message_rx,
             action_tx: Some(action_tx),
         })
     }
 
-    async fn receive_messages_with_reader(
-        mut reader: BufReader<tokio::net::tcp::OwnedReadHalf>,
-        tx: mpsc::UnboundedSender<ChatMessage>,
+    /// Turns what the server sends into actions, all on the one channel so
+    /// they are handled in the order they arrived
+    pub(crate) async fn receive_messages_with_reader(
+        mut reader: impl AsyncBufRead + Unpin,
         current_room: String,
         action_tx: mpsc::UnboundedSender<crate::state::action::Action>,
     ) {
         let mut line = String::new();
+        let mut replay: Option<HistoryReplay> = None;
 
         loop {
             line.clear();
@@ -102,14 +123,39 @@ impl TuiClient {
                         || clean_line.contains("Enter your nickname")
                         || clean_line.contains("Welcome")
                         || clean_line.contains("Type /help")
-                        || clean_line.contains("Joined room:")
-                        || clean_line.contains("Available commands") {
+                        || clean_line.contains("Joined room:") {
+                        continue;
+                    }
+
+                    // HISTORY|room ... HISTORY_END|room|read marker
+                    if let Some(room) = clean_line.strip_prefix("HISTORY|") {
+                        replay = Some(HistoryReplay::new(room.to_string()));
+                        continue;
+                    }
+                    if let Some(rest) = clean_line.strip_prefix("HISTORY_END|") {
+                        if let Some(mut history) = replay.take() {
+                            let read_marker = rest.split_once('|').and_then(|(_, id)| id.parse().ok());
+                            let _ = action_tx.send(Action::RoomHistory {
+                                room: history.room,
+                                messages: history.messages,
+                                read_marker,
+                            });
+                            while let Ok(update) = history.updates_rx.try_recv() {
+                                let _ = action_tx.send(update);
+                            }
+                        }
+                        continue;
+                    }
+                    if let Some(history) = replay.as_mut() {
+                        if let Ok(message) = Self::parse_structured_message(&clean_line, &current_room, &history.updates_tx) {
+                            history.messages.push(message);
+                        }
                         continue;
                     }
 
                     // Parse structured messages from server
                     if let Ok(message) = Self::parse_structured_message(&clean_line, &current_room, &action_tx) {
-                        let _ = tx.send(message);
+                        let _ = action_tx.send(Action::MessageReceived(Box::new(message)));
                     }
                 }
                 Err(e) => {
@@ -144,9 +190,10 @@ impl TuiClient {
         result
     }
 
+    /// Sends one line; the line breaks of a multi-line message are escaped
     pub async fn send_message(&self, message: &str) -> Result<(), Box<dyn std::error::Error>> {
         let mut w = self.writer.lock().await;
-        w.write_all(format!("{}\n", message).as_bytes()).await?;
+        w.write_all(format!("{}\n", escape_line(message)).as_bytes()).await?;
         w.flush().await?;
         Ok(())
     }
@@ -192,13 +239,38 @@ impl TuiClient {
         }
 
 
-        // USER_LIST|user1,user2,user3
+        // TOPIC|room|topic, the topic empty when cleared
+        if let Some(rest) = line.strip_prefix("TOPIC|") {
+            let (room, topic) = rest.split_once('|').unwrap_or((rest, ""));
+            let topic = Some(topic.trim().to_string()).filter(|topic| !topic.is_empty());
+            let _ = action_tx.send(crate::state::action::Action::TopicChanged(room.to_string(), topic));
+            return Err("Topic changed".into())
+        }
+
+        // CREATE_FAILED|reason
+        if let Some(reason) = line.strip_prefix("CREATE_FAILED|") {
+            let _ = action_tx.send(crate::state::action::Action::CreateRoomFailed(reason.to_string()));
+            return Err("Room not created".into())
+        }
+
+        // PASSWORD_REQUIRED|room|missing or wrong
+        if let Some(rest) = line.strip_prefix("PASSWORD_REQUIRED|") {
+            let (room, reason) = rest.rsplit_once('|').unwrap_or((rest, "missing"));
+            let _ = action_tx.send(crate::state::action::Action::PasswordRequired {
+                room: room.to_string(),
+                wrong: reason == "wrong",
+            });
+            return Err("Password required".into())
+        }
+
+        // USER_LIST|name:presence:status,name:presence:status
         if line.starts_with("USER_LIST|") {
             let users_str = line.strip_prefix("USER_LIST|").unwrap_or("");
-            let users: Vec<String> = users_str
+            let users: Vec<(String, Presence)> = users_str
                 .split(',')
-                .map(|s| s.trim().to_string())
+                .map(|s| s.trim())
                 .filter(|s| !s.is_empty())
+                .map(Presence::decode_entry)
                 .collect();
             
             // Send action to update user list
@@ -208,54 +280,158 @@ impl TuiClient {
             return Err("User list update".into());
         }
 
+        // NICK|old|new
+        if line.starts_with("NICK|") {
+            let parts: Vec<&str> = line.splitn(3, '|').collect();
+            if parts.len() == 3 {
+                let old_name = parts[1].to_string();
+                let new_name = parts[2].trim().to_string();
+                let _ = action_tx.send(crate::state::action::Action::NickChanged(old_name.clone(), new_name.clone()));
+
+                return Ok(Self::system_message(
+                    format!("{} is now known as {}", old_name, new_name),
+                    room,
+                    "#808080",
+                ));
+            }
+        }
+
         // SYSTEM|content
         if line.starts_with("SYSTEM|") {
-            let content = line.strip_prefix("SYSTEM|").unwrap_or(line).trim().to_string();
-            return Ok(ChatMessage {
-                content,
-                sender_addr: "0.0.0.0:0".parse()?,
-                sender_name: "System".to_string(),
-                room: room.to_string(),
-                message_type: chat_core::message::chat_message::MessageType::System,
-                target: None,
-                color: "#808080".to_string(),
-                timestamp: chrono::Utc::now(),
-            });
+            let content = unescape_line(line.strip_prefix("SYSTEM|").unwrap_or(line).trim());
+            return Ok(Self::system_message(content, room, "#808080"));
         }
 
-        // CHAT|timestamp|sender|color|content
-        if line.starts_with("CHAT|") {
+        // WARNING|content
+        if line.starts_with("WARNING|") {
+            let content = line.strip_prefix("WARNING|").unwrap_or(line).trim();
+            let _ = action_tx.send(crate::state::action::Action::ServerWarning(content.to_string()));
+            return Ok(Self::system_message(format!("⚠ {}", content), room, "#FFA500"));
+        }
+
+        // WHISPER|timestamp|from|to|color|content
+        if line.starts_with("WHISPER|") {
+            let parts: Vec<&str> = line.splitn(6, '|').collect();
+            if parts.len() == 6 {
+                let mut message = ChatMessage::whisper(
+                    unescape_line(parts[5]),
+                    "0.0.0.0:0".parse()?,
+                    parts[2].to_string(),
+                    None,
+                    parts[3].to_string(),
+                );
+                message.color = parts[4].to_string();
+                message.timestamp = Self::parse_timestamp(parts[1]);
+                return Ok(message);
+            }
+        }
+
+        // EDIT|id|content
+        if line.starts_with("EDIT|") {
+            let parts: Vec<&str> = line.splitn(3, '|').collect();
+            if parts.len() == 3 && let Ok(id) = parts[1].parse() {
+                let _ = action_tx.send(crate::state::action::Action::MessageEdited(id, unescape_line(parts[2])));
+                return Err("Message edited".into());
+            }
+        }
+
+        // TYPING|name|start or stop
+        if let Some(rest) = line.strip_prefix("TYPING|") {
+            if let Some((name, state)) = rest.split_once('|') {
+                let _ = action_tx.send(crate::state::action::Action::TypingChanged(name.to_string(), state == "start"));
+            }
+            return Err("Typing".into());
+        }
+
+        // REACTION|id|emoji|user|+ or -
+        if line.starts_with("REACTION|") {
             let parts: Vec<&str> = line.splitn(5, '|').collect();
-            if parts.len() == 5 {
-                let timestamp_str = parts[1];
-                let sender = parts[2].to_string();
-                let color = parts[3].to_string();
-                let content = parts[4].to_string();
-
-                let timestamp = chrono::DateTime::parse_from_str(
-                    timestamp_str, 
-                    "%Y-%m-%d %H:%M:%S"
-                )
-                .map(|dt| dt.with_timezone(&chrono::Utc))
-                .unwrap_or_else(|_| chrono::Utc::now());
-
-                return Ok(ChatMessage {
-                    content,
-                    sender_addr: "0.0.0.0:0".parse()?,
-                    sender_name: sender,
-                    room: room.to_string(),
-                    message_type: chat_core::message::chat_message::MessageType::Chat,
-                    target: None,
-                    color,
-                    timestamp,
+            if parts.len() == 5 && let Ok(id) = parts[1].parse() {
+                let _ = action_tx.send(crate::state::action::Action::ReactionChanged {
+                    id,
+                    emoji: parts[2].to_string(),
+                    user: parts[3].to_string(),
+                    added: parts[4] == "+",
                 });
             }
+            return Err("Reaction".into());
+        }
+
+        // DELETE|id
+        if let Some(id) = line.strip_prefix("DELETE|") {
+            if let Ok(id) = id.trim().parse() {
+                let _ = action_tx.send(crate::state::action::Action::MessageDeleted(id));
+            }
+            return Err("Message deleted".into());
+        }
+
+        // MENTION|room|id|timestamp|sender|color|content
+        if line.starts_with("MENTION|") {
+            let parts: Vec<&str> = line.splitn(7, '|').collect();
+            if parts.len() == 7 {
+                let mut message = ChatMessage::new(
+                    unescape_line(parts[6]),
+                    "0.0.0.0:0".parse()?,
+                    parts[4].to_string(),
+                    parts[1].to_string(),
+                );
+                message.message_type = chat_core::message::chat_message::MessageType::Mention;
+                message.id = parts[2].parse().ok();
+                message.timestamp = Self::parse_timestamp(parts[3]);
+                message.color = parts[5].to_string();
+                return Ok(message);
+            }
+        }
+
+        // CHAT|id|reply_to|mentions|timestamp|sender|color|content, ACTION for /me
+        if line.starts_with("CHAT|") || line.starts_with("ACTION|") {
+            let parts: Vec<&str> = line.splitn(8, '|').collect();
+            if parts.len() == 8 {
+                let id = parts[1].parse().ok();
+                let reply_to = parts[2].parse().ok();
+                let mentions = parts[3]
+                    .split(',')
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string())
+                    .collect();
+                let timestamp_str = parts[4];
+                let sender = parts[5].to_string();
+                let color = parts[6].to_string();
+                let content = unescape_line(parts[7]);
+
+                let timestamp = Self::parse_timestamp(timestamp_str);
+
+                let mut message = ChatMessage::new(content, "0.0.0.0:0".parse()?, sender, room.to_string());
+                message.id = id;
+                message.reply_to = reply_to;
+                message.mentions = mentions;
+                message.color = color;
+                message.timestamp = timestamp;
+                if parts[0] == "ACTION" {
+                    message.message_type = chat_core::message::chat_message::MessageType::Action;
+                }
+                return Ok(message);
+            }
         }
 
         Err("Unparseable line".into())
     }
 
 
+    fn system_message(content: String, room: &str, color: &str) -> ChatMessage {
+        let mut message = ChatMessage::system(content, room.to_string());
+        message.sender_name = "System".to_string();
+        message.color = color.to_string();
+        message
+    }
+
+    /// Server timestamps are UTC without an offset
+    fn parse_timestamp(timestamp: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")
+            .map(|dt| dt.and_utc())
+            .unwrap_or_else(|_| chrono::Utc::now())
+    }
+
     pub async fn change_room(&self, room: &str) -> Result<(), Box<dyn std::error::Error>> {
         self.send_message(&format!("/join {}", room)).await
     }
@@ -1,31 +1,49 @@
-use std::time::Duration;
-use crossterm::event::{self, Event as CrosstermEvent, KeyEvent, KeyEventKind};
+use crossterm::event::{Event as CrosstermEvent, EventStream, KeyEvent, KeyEventKind, MouseEvent, MouseEventKind};
+use tokio_stream::StreamExt;
 
 #[derive(Clone, Debug)]
 pub enum Event {
     Key(KeyEvent),
-    Tick,
-    Render,
+    Mouse(MouseEvent),
+    FocusGained,
+    FocusLost,
+    /// The terminal's new width and height
+    Resize(u16, u16),
 }
 
 pub struct EventHandler {
-    tick_rate: Duration,
+    events: EventStream,
+}
+
+impl Default for EventHandler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EventHandler {
-    pub fn new(tick_rate: Duration) -> Self {
-        Self { tick_rate }
+    pub fn new() -> Self {
+        Self { events: EventStream::new() }
     }
 
-    /// Return only events of key pressed 
-    pub fn next(&self) -> std::io::Result<Event> {
-        if event::poll(self.tick_rate)? {
-            if let CrosstermEvent::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    return Ok(Event::Key(key))
-                }
-            }
+    /// Waits for the next key press, click, wheel turn, resize or terminal
+    /// focus change; `None` once the terminal closes. Safe to cancel, as in
+    /// `tokio::select!`: no event is lost.
+    pub async fn next(&mut self) -> Option<std::io::Result<Event>> {
+        while let Some(event) = self.events.next().await {
+            let event = match event {
+                Ok(CrosstermEvent::Key(key)) if key.kind == KeyEventKind::Press => Event::Key(key),
+                // Movement and drags aren't used
+                Ok(CrosstermEvent::Mouse(mouse)) if matches!(mouse.kind,
+                    MouseEventKind::Down(_) | MouseEventKind::ScrollUp | MouseEventKind::ScrollDown) => Event::Mouse(mouse),
+                Ok(CrosstermEvent::FocusGained) => Event::FocusGained,
+                Ok(CrosstermEvent::FocusLost) => Event::FocusLost,
+                Ok(CrosstermEvent::Resize(width, height)) => Event::Resize(width, height),
+                Ok(_) => continue,
+                Err(err) => return Some(Err(err)),
+            };
+            return Some(Ok(event));
         }
-        Ok(Event::Tick)
+        None
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,10 @@
-use crate::state::{action::Action, state::{AppPage, InputMode, FocusedField}};
-use crossterm::event::{KeyEvent, KeyCode, KeyModifiers};
+use crate::state::{action::Action, line_editor::EditCommand, state::{AppPage, AppState, InputMode, FocusedField}};
+use crate::view::pages::chat_page::components::RoomList;
+use crossterm::event::{KeyEvent, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Position, Rect};
+
+/// Reactions on keys 1-6 while selecting messages
+pub const QUICK_REACTIONS: [&str; 6] = ["👍", "❤️", "😂", "🎉", "😮", "😢"];
 
 pub struct InputHandler;
 
@@ -13,6 +18,9 @@ impl InputHandler {
         if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
             return Some(Action::Quit);
         }
+        if key.code == KeyCode::F(1) {
+            return Some(Action::ToggleHelp);
+        }
         match input_mode {
             InputMode::Normal => Self::handle_normal_mode(key, current_page, focused_field),
             InputMode::Editing => Self::handle_editing_mode(key, current_page, focused_field),
@@ -25,6 +33,24 @@ impl InputHandler {
         current_page: &AppPage,
         focused_field: &FocusedField,
     ) -> Option<Action> {
+        if *focused_field == FocusedField::Help {
+            return Self::handle_help(key);
+        }
+        if *current_page == AppPage::Chat && *focused_field == FocusedField::MessageList {
+            match key.code {
+                KeyCode::Up => return Some(Action::SelectPreviousMessage),
+                KeyCode::Down => return Some(Action::SelectNextMessage),
+                KeyCode::Char('r') => return Some(Action::ReplyToSelected),
+                KeyCode::Char('t') => return Some(Action::ToggleThread),
+                KeyCode::Char(c @ '1'..='6') => {
+                    let idx = c as usize - '1' as usize;
+                    return Some(Action::ReactToSelected(QUICK_REACTIONS[idx].to_string()));
+                }
+                KeyCode::Esc => return Some(Action::FocusNext),
+                _ => {}
+            }
+        }
+
         match key.code {
             KeyCode::Char('q') => Some(Action::Quit),
             KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::Quit),
@@ -43,8 +69,18 @@ impl InputHandler {
             KeyCode::Char('i') => Some(Action::ToggleInputMode),
             KeyCode::Up => Some(Action::ScrollUp),
             KeyCode::Down => Some(Action::ScrollDown),
+            KeyCode::PageUp => Some(Action::PageUp),
+            KeyCode::PageDown => Some(Action::PageDown),
+            KeyCode::Home => Some(Action::ScrollToTop),
+            KeyCode::End => Some(Action::ScrollToBottom),
+            KeyCode::Char('p') if *current_page == AppPage::Chat
+                && key.modifiers.contains(KeyModifiers::CONTROL) => Some(Action::OpenCommandPalette),
             KeyCode::Char('n') => Some(Action::NextRoom),
             KeyCode::Char('p') => Some(Action::PreviousRoom),
+            KeyCode::Char('x') => Some(Action::CloseDirectMessage),
+            KeyCode::Char('m') => Some(Action::ToggleMentions),
+            KeyCode::Char('c') if *current_page == AppPage::Chat => Some(Action::OpenCreateRoom),
+            KeyCode::Char('?') => Some(Action::ToggleHelp),
             _ => None,
         }
     }
@@ -54,47 +90,220 @@ impl InputHandler {
         current_page: &AppPage,
         focused_field: &FocusedField,
     ) -> Option<Action> {
+        if *focused_field == FocusedField::PasswordPrompt {
+            return Self::handle_password_prompt(key);
+        }
+        if *focused_field == FocusedField::CreateRoomDialog {
+            return Self::handle_create_room(key);
+        }
+        if *focused_field == FocusedField::CommandPalette {
+            return Self::handle_command_palette(key);
+        }
         match key.code {
             KeyCode::Esc => Some(Action::ToggleInputMode),
+            // Shift+Enter needs a terminal that reports it, Alt+Enter works everywhere
+            KeyCode::Enter if *current_page == AppPage::Chat
+                && key.modifiers.intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) => {
+                Some(Action::UpdateMessageInput(EditCommand::Insert('\n')))
+            }
             KeyCode::Enter => match current_page {
                 AppPage::Connection => Some(Action::ToggleInputMode),
                 AppPage::Chat => Some(Action::SendMessage),
             },
-            KeyCode::Char(c) => {
-                if key.modifiers.contains(KeyModifiers::CONTROL) {
-                    return None;
-                }
+            // The message list pages while typing too
+            KeyCode::PageUp if *current_page == AppPage::Chat => Some(Action::PageUp),
+            KeyCode::PageDown if *current_page == AppPage::Chat => Some(Action::PageDown),
+            KeyCode::Up if *current_page == AppPage::Chat => Some(Action::HistoryPrevious),
+            KeyCode::Down if *current_page == AppPage::Chat => Some(Action::HistoryNext),
+            KeyCode::Char('r') if *current_page == AppPage::Chat
+                && key.modifiers == KeyModifiers::CONTROL => Some(Action::SearchHistory),
+            KeyCode::Char('p') if *current_page == AppPage::Chat
+                && key.modifiers == KeyModifiers::CONTROL => Some(Action::OpenCommandPalette),
+            KeyCode::Tab if *current_page == AppPage::Chat => Some(Action::Complete(true)),
+            KeyCode::BackTab if *current_page == AppPage::Chat => Some(Action::Complete(false)),
+            _ => {
+                let command = Self::edit_command(key)?;
                 match (current_page, focused_field) {
                     (AppPage::Connection, FocusedField::ServerAddress) => {
-                        Some(Action::UpdateServerAddress(c.to_string()))
+                        Some(Action::UpdateServerAddress(command))
                     },
                     (AppPage::Connection, FocusedField::Username) => {
-                        Some(Action::UpdateUsername(c.to_string()))
+                        Some(Action::UpdateUsername(command))
                     },
                     (AppPage::Chat, FocusedField::MessageInput) => {
-                        Some(Action::UpdateMessageInput(c.to_string()))
+                        Some(Action::UpdateMessageInput(command))
                     },
                     _ => None
                 }
             },
-            KeyCode::Backspace => {
-                match (current_page, focused_field) {
-                    (AppPage::Connection, FocusedField::ServerAddress) => {
-                        Some(Action::UpdateServerAddress("\x08".to_string()))
-                    }
-                    (AppPage::Connection, FocusedField::Username) => {
-                        Some(Action::UpdateUsername("\x08".to_string()))
-                    }
-                    (AppPage::Chat, FocusedField::MessageInput) => {
-                        Some(Action::UpdateMessageInput("\x08".to_string()))
-                    }
-                    _ => None,
+        }
+    }
+
+    /// The password dialog takes every key until it closes
+    fn handle_password_prompt(key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc => Some(Action::CancelPassword),
+            KeyCode::Enter => Some(Action::SubmitPassword),
+            KeyCode::Tab | KeyCode::BackTab => Some(Action::ToggleRememberPassword),
+            _ => Self::edit_command(key).map(Action::UpdatePasswordInput),
+        }
+    }
+
+    fn handle_create_room(key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc => Some(Action::CancelCreateRoom),
+            KeyCode::Enter => Some(Action::SubmitCreateRoom),
+            KeyCode::Tab | KeyCode::Down => Some(Action::CreateRoomFocus(true)),
+            KeyCode::BackTab | KeyCode::Up => Some(Action::CreateRoomFocus(false)),
+            _ => Self::edit_command(key).map(Action::CreateRoomInput),
+        }
+    }
+
+    fn handle_command_palette(key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc => Some(Action::CloseCommandPalette),
+            KeyCode::Enter => Some(Action::RunPaletteEntry),
+            KeyCode::Tab | KeyCode::Down => Some(Action::CommandPaletteSelect(true)),
+            KeyCode::BackTab | KeyCode::Up => Some(Action::CommandPaletteSelect(false)),
+            _ => Self::edit_command(key).map(Action::CommandPaletteInput),
+        }
+    }
+
+    /// The help overlay takes every key until it closes
+    fn handle_help(key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('?') | KeyCode::Char('q') => Some(Action::ToggleHelp),
+            KeyCode::Down => Some(Action::ScrollHelp(true)),
+            KeyCode::Up => Some(Action::ScrollHelp(false)),
+            _ => None,
+        }
+    }
+
+    /// Wheel over the messages scrolls them; clicks pick a room or DM in the
+    /// sidebar, open a DM with a user (right click mentions them instead)
+    /// or start editing in the message box
+    pub fn handle_mouse(mouse: MouseEvent, state: &AppState) -> Option<Action> {
+        let dialog_open = state.password_prompt.is_some() || state.create_room.is_some()
+            || state.command_palette.is_some() || state.help.is_some();
+        if state.current_page != AppPage::Chat || dialog_open {
+            return None;
+        }
+        let layout = state.chat_layout.get();
+        let position = Position::new(mouse.column, mouse.row);
+
+        match mouse.kind {
+            MouseEventKind::ScrollUp if layout.messages.contains(position) => Some(Action::ScrollUp),
+            MouseEventKind::ScrollDown if layout.messages.contains(position) => Some(Action::ScrollDown),
+            MouseEventKind::Down(button) => {
+                if layout.input.contains(position) {
+                    return Some(Action::StartEditing);
                 }
-            },
-            KeyCode::Left | KeyCode::Right | KeyCode::Home | KeyCode::End => {
-                None   // Ignore navigation keys
-            },
+                if let Some(row) = Self::list_row(layout.rooms, position) {
+                    let entry = RoomList::entry_at(state.available_rooms.len(), state.conversations.len(), row)?;
+                    return Some(Action::SelectSidebarEntry(entry));
+                }
+                let row = Self::list_row(layout.users, position)?;
+                let user = state.listed_users().get(row).map(|user| user.to_string())?;
+                if user == state.username {
+                    return None;
+                }
+                match button {
+                    MouseButton::Right => Some(Action::InsertMention(user)),
+                    _ => Some(Action::OpenDirectMessage(user)),
+                }
+            }
             _ => None,
         }
     }
-}
\ No newline at end of file
+
+    /// Row of a bordered list under `position`, from 0
+    fn list_row(area: Rect, position: Position) -> Option<usize> {
+        let inner = Rect::new(area.x + 1, area.y + 1, area.width.saturating_sub(2), area.height.saturating_sub(2));
+        inner.contains(position).then(|| (position.y - inner.y) as usize)
+    }
+
+    /// Line editing keys, the same in every text field
+    fn edit_command(key: KeyEvent) -> Option<EditCommand> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let command = match key.code {
+            // AltGr arrives as Ctrl+Alt
+            KeyCode::Char(c) if ctrl && alt => EditCommand::Insert(c),
+            KeyCode::Char('a') if ctrl => EditCommand::Home,
+            KeyCode::Char('e') if ctrl => EditCommand::End,
+            KeyCode::Char('w') if ctrl => EditCommand::DeleteWordBackward,
+            KeyCode::Char('k') if ctrl => EditCommand::KillToEnd,
+            KeyCode::Char('u') if ctrl => EditCommand::KillToStart,
+            KeyCode::Char('y') if ctrl => EditCommand::Yank,
+            KeyCode::Char('b') if alt => EditCommand::WordLeft,
+            KeyCode::Char('f') if alt => EditCommand::WordRight,
+            KeyCode::Char('d') if alt => EditCommand::DeleteWordForward,
+            KeyCode::Char(_) if ctrl || alt => return None,
+            KeyCode::Char(c) => EditCommand::Insert(c),
+            KeyCode::Backspace if ctrl || alt => EditCommand::DeleteWordBackward,
+            KeyCode::Backspace => EditCommand::Backspace,
+            KeyCode::Delete => EditCommand::Delete,
+            KeyCode::Left if ctrl || alt => EditCommand::WordLeft,
+            KeyCode::Right if ctrl || alt => EditCommand::WordRight,
+            KeyCode::Left => EditCommand::Left,
+            KeyCode::Right => EditCommand::Right,
+            KeyCode::Home => EditCommand::Home,
+            KeyCode::End => EditCommand::End,
+            _ => return None,
+        };
+        Some(command)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::state::{ChatLayout, DirectConversation};
+    use crossterm::event::KeyModifiers;
+
+    fn click(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent { kind, column, row, modifiers: KeyModifiers::NONE }
+    }
+
+    fn chat_state() -> AppState {
+        let state = AppState {
+            current_page: AppPage::Chat,
+            username: "alice".to_string(),
+            available_rooms: vec!["general".to_string(), "rust🔒".to_string()],
+            users_in_room: vec!["bob".to_string(), "alice".to_string()],
+            conversations: vec![DirectConversation::new("carol".to_string())],
+            ..AppState::default()
+        };
+        state.chat_layout.set(ChatLayout {
+            rooms: Rect::new(0, 3, 20, 10),
+            users: Rect::new(0, 13, 20, 6),
+            messages: Rect::new(20, 3, 60, 12),
+            input: Rect::new(20, 15, 60, 3),
+        });
+        state
+    }
+
+    #[test]
+    fn test_clicks_hit_the_pane_under_them() {
+        let state = chat_state();
+        let left = MouseEventKind::Down(MouseButton::Left);
+
+        assert_eq!(InputHandler::handle_mouse(click(left, 5, 5), &state), Some(Action::SelectSidebarEntry(1)));
+        // The "Direct" heading, then the DM under it
+        assert_eq!(InputHandler::handle_mouse(click(left, 5, 6), &state), None);
+        assert_eq!(InputHandler::handle_mouse(click(left, 5, 7), &state), Some(Action::SelectSidebarEntry(2)));
+        // Borders aren't entries
+        assert_eq!(InputHandler::handle_mouse(click(left, 5, 3), &state), None);
+
+        // We are listed first, and clicking ourselves does nothing
+        assert_eq!(InputHandler::handle_mouse(click(left, 5, 14), &state), None);
+        assert_eq!(InputHandler::handle_mouse(click(left, 5, 15), &state), Some(Action::OpenDirectMessage("bob".to_string())));
+        let right = MouseEventKind::Down(MouseButton::Right);
+        assert_eq!(InputHandler::handle_mouse(click(right, 5, 15), &state), Some(Action::InsertMention("bob".to_string())));
+
+        assert_eq!(InputHandler::handle_mouse(click(left, 30, 16), &state), Some(Action::StartEditing));
+        assert_eq!(InputHandler::handle_mouse(click(MouseEventKind::ScrollUp, 30, 5), &state), Some(Action::ScrollUp));
+        assert_eq!(InputHandler::handle_mouse(click(MouseEventKind::ScrollDown, 5, 5), &state), None);
+    }
+}
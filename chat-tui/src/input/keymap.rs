@@ -0,0 +1,206 @@
+use crate::state::action::Action;
+use crate::state::state::{AppPage, FocusedField, InputMode};
+
+/// A key and what it does, as the help overlay lists it
+#[derive(Debug, PartialEq)]
+pub struct KeyBinding {
+    pub keys: &'static str,
+    pub description: &'static str,
+    /// Offered in the command palette when set
+    pub action: Option<Action>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct KeySection {
+    pub title: &'static str,
+    pub bindings: &'static [KeyBinding],
+}
+
+const fn key(keys: &'static str, description: &'static str) -> KeyBinding {
+    KeyBinding { keys, description, action: None }
+}
+
+const fn run(keys: &'static str, description: &'static str, action: Action) -> KeyBinding {
+    KeyBinding { keys, description, action: Some(action) }
+}
+
+pub const GLOBAL: KeySection = KeySection {
+    title: "Anywhere",
+    bindings: &[
+        run("F1", "Show or hide this help", Action::ToggleHelp),
+        run("Ctrl+C", "Quit", Action::Quit),
+    ],
+};
+
+pub const CONNECTION: KeySection = KeySection {
+    title: "Connection",
+    bindings: &[
+        key("Tab / Shift+Tab", "Move between the fields"),
+        key("i / Enter", "Edit the field, or connect on the button"),
+        key("?", "Show this help"),
+        key("q", "Quit"),
+    ],
+};
+
+pub const CONNECTION_EDITING: KeySection = KeySection {
+    title: "Editing a field",
+    bindings: &[
+        key("Enter / Esc", "Stop editing"),
+    ],
+};
+
+pub const LINE_EDITING: KeySection = KeySection {
+    title: "Text fields",
+    bindings: &[
+        key("← / →", "Move the cursor"),
+        key("Ctrl+← / Ctrl+→, Alt+B / Alt+F", "Move by word"),
+        key("Home / End, Ctrl+A / Ctrl+E", "Start or end of the line"),
+        key("Backspace / Delete", "Delete before or under the cursor"),
+        key("Ctrl+W, Alt+Backspace", "Delete the word before the cursor"),
+        key("Alt+D", "Delete the word after the cursor"),
+        key("Ctrl+K / Ctrl+U", "Cut to the end or the start"),
+        key("Ctrl+Y", "Paste what was cut"),
+    ],
+};
+
+pub const CHAT: KeySection = KeySection {
+    title: "Chat",
+    bindings: &[
+        run("i", "Write a message", Action::ToggleInputMode),
+        key("Tab", "Select messages"),
+        key("↑ / ↓, PgUp / PgDn", "Scroll the messages"),
+        run("Home", "Jump to the oldest message", Action::ScrollToTop),
+        run("End", "Jump to the newest message", Action::ScrollToBottom),
+        run("n", "Next room", Action::NextRoom),
+        run("p", "Previous room", Action::PreviousRoom),
+        run("c", "Create a room", Action::OpenCreateRoom),
+        run("m", "Show or hide mentions", Action::ToggleMentions),
+        run("x", "Close the direct message", Action::CloseDirectMessage),
+        key("Ctrl+P", "Command palette"),
+        run("?", "Show this help", Action::ToggleHelp),
+        run("q", "Quit", Action::Quit),
+    ],
+};
+
+pub const MESSAGE_LIST: KeySection = KeySection {
+    title: "Selecting messages",
+    bindings: &[
+        key("↑ / ↓", "Select a message"),
+        key("r", "Reply to it"),
+        key("t", "Open or close its thread"),
+        key("1-6", "React with 👍 ❤️ 😂 🎉 😮 😢"),
+        key("Tab / Esc", "Back to the message box"),
+    ],
+};
+
+pub const CHAT_EDITING: KeySection = KeySection {
+    title: "Writing a message",
+    bindings: &[
+        key("Enter", "Send"),
+        key("Alt+Enter / Shift+Enter", "New line"),
+        key("↑ / ↓", "Previous or next sent line"),
+        key("Ctrl+R", "Search sent lines"),
+        key("Tab / Shift+Tab", "Complete a command, nick or room"),
+        key("PgUp / PgDn", "Scroll the messages"),
+        key("Ctrl+P", "Command palette"),
+        key("Esc", "Stop editing"),
+    ],
+};
+
+pub const MOUSE: KeySection = KeySection {
+    title: "Mouse",
+    bindings: &[
+        key("Wheel", "Scroll the messages"),
+        key("Click a room or DM", "Switch to it"),
+        key("Click a user", "Open a direct message"),
+        key("Right click a user", "Mention them"),
+        key("Click the message box", "Write a message"),
+    ],
+};
+
+pub const PASSWORD_PROMPT: KeySection = KeySection {
+    title: "Room password",
+    bindings: &[
+        key("Enter", "Join"),
+        key("Tab", "Remember the password for this session"),
+        key("Esc", "Cancel"),
+    ],
+};
+
+pub const CREATE_ROOM: KeySection = KeySection {
+    title: "New room",
+    bindings: &[
+        key("Tab / ↓", "Next field"),
+        key("Shift+Tab / ↑", "Previous field"),
+        key("← / →", "Pick the slow mode"),
+        key("Enter", "Create"),
+        key("Esc", "Cancel"),
+    ],
+};
+
+pub const COMMAND_PALETTE: KeySection = KeySection {
+    title: "Command palette",
+    bindings: &[
+        key("Type", "Filter commands"),
+        key("↑ / ↓, Tab / Shift+Tab", "Choose"),
+        key("Enter", "Run it"),
+        key("Esc", "Close"),
+    ],
+};
+
+/// Keys that work on `page` in `input_mode` with `focused_field`, most
+/// specific first
+pub fn sections(page: &AppPage, input_mode: &InputMode, focused_field: &FocusedField) -> Vec<&'static KeySection> {
+    match (page, input_mode, focused_field) {
+        (_, _, FocusedField::PasswordPrompt) => vec![&PASSWORD_PROMPT, &LINE_EDITING, &GLOBAL],
+        (_, _, FocusedField::CreateRoomDialog) => vec![&CREATE_ROOM, &LINE_EDITING, &GLOBAL],
+        (_, _, FocusedField::CommandPalette) => vec![&COMMAND_PALETTE, &LINE_EDITING, &GLOBAL],
+        (AppPage::Connection, InputMode::Normal, _) => vec![&CONNECTION, &GLOBAL],
+        (AppPage::Connection, InputMode::Editing, _) => vec![&CONNECTION_EDITING, &LINE_EDITING, &GLOBAL],
+        (AppPage::Chat, InputMode::Normal, FocusedField::MessageList) => vec![&MESSAGE_LIST, &CHAT, &MOUSE, &GLOBAL],
+        (AppPage::Chat, InputMode::Normal, _) => vec![&CHAT, &MOUSE, &GLOBAL],
+        (AppPage::Chat, InputMode::Editing, _) => vec![&CHAT_EDITING, &LINE_EDITING, &MOUSE, &GLOBAL],
+    }
+}
+
+/// Bindings the command palette can run, each once
+pub fn palette_bindings() -> impl Iterator<Item = &'static KeyBinding> {
+    CHAT.bindings.iter().filter(|binding| binding.action.is_some())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::input_handler::InputHandler;
+    use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+    #[test]
+    fn test_sections_follow_mode_and_focus() {
+        let chat = sections(&AppPage::Chat, &InputMode::Normal, &FocusedField::MessageList);
+        assert_eq!(chat.first(), Some(&&MESSAGE_LIST));
+        let editing = sections(&AppPage::Chat, &InputMode::Editing, &FocusedField::MessageInput);
+        assert_eq!(editing.first(), Some(&&CHAT_EDITING));
+        let dialog = sections(&AppPage::Chat, &InputMode::Editing, &FocusedField::CreateRoomDialog);
+        assert_eq!(dialog.first(), Some(&&CREATE_ROOM));
+        assert!(!sections(&AppPage::Connection, &InputMode::Normal, &FocusedField::Username).contains(&&MOUSE));
+    }
+
+    #[test]
+    fn test_single_key_bindings_do_what_they_say() {
+        // The palette runs the action listed here; the key must do the same
+        for binding in palette_bindings() {
+            let Some(first) = binding.keys.split(" / ").next().filter(|keys| keys.chars().count() == 1) else {
+                continue;
+            };
+            let code = KeyCode::Char(first.chars().next().unwrap());
+            let action = InputHandler::handle_key(
+                KeyEvent::new(code, KeyModifiers::NONE),
+                &AppPage::Chat,
+                &InputMode::Normal,
+                &FocusedField::MessageInput,
+            );
+            assert_eq!(action.as_ref(), binding.action.as_ref(), "key {}", first);
+        }
+    }
+}
@@ -1 +1,2 @@
-pub mod input_handler;
\ No newline at end of file
+pub mod input_handler;
+pub mod keymap;
\ No newline at end of file
@@ -1,38 +1,63 @@
 use std::io;
 use std::time::Duration;
 
+use tokio::time::MissedTickBehavior;
+
 use crossterm::{
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{
+        DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture,
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen, SetTitle,
+    },
 };
 use ratatui::{
     backend::{CrosstermBackend, Backend},
+    layout::Rect,
     Terminal,
 };
 
 use chat_tui::{
     app::App,
     state::action::Action,
+    state::input_history::InputHistory,
     view::view::View,
     event::event_handler::{Event, EventHandler},
     input::input_handler::InputHandler
 };
 
+const APP_TITLE: &str = "Rustatui Chat";
+/// Path of a file to keep sent lines in between sessions; history isn't saved when unset
+const HISTORY_ENV: &str = "RUSTATUI_HISTORY";
+/// How often to catch up on time-driven state: throttled read reports and stale typing notices
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_secs(1);
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture, EnableFocusChange, SetTitle(APP_TITLE))?;
+    // Lets terminals that can tell Shift+Enter from Enter report it
+    let keyboard_enhanced = supports_keyboard_enhancement().unwrap_or(false);
+    if keyboard_enhanced {
+        execute!(stdout, PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES))?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
     // create App
     let mut app = App::new();
+    if let Ok(path) = std::env::var(HISTORY_ENV) {
+        app.state.input_history = InputHistory::persistent(path.into());
+    }
 
     // Create event handler
-    let event_handler = EventHandler::new(Duration::from_millis(100));
+    let event_handler = EventHandler::new();
 
     // Get action sender
     let action_tx = app.action_tx();
@@ -41,11 +66,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let result = run_app(&mut terminal, &mut app, event_handler, action_tx).await;
 
     // Restores terminal
+    if keyboard_enhanced {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        DisableMouseCapture
+        DisableMouseCapture,
+        DisableFocusChange
     )?;
     terminal.show_cursor()?;
 
@@ -59,43 +88,77 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 async fn run_app<B: Backend>(
     terminal: &mut Terminal<B>,
     app: &mut App,
-    event_handler: EventHandler,
+    mut event_handler: EventHandler,
     action_tx: tokio::sync::mpsc::UnboundedSender<Action>,
 ) -> io::Result<()>
     where io::Error: From<<B as Backend>::Error>
 {
+    let mut housekeeping = tokio::time::interval(HOUSEKEEPING_INTERVAL);
+    housekeeping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    // Draw only when something changed since the last frame
+    let mut dirty = true;
+
     loop {
-        // Process messages and actions
-        app.tick().await;
+        if dirty {
+            // Ring the bell and flag the window title when mentioned out of sight
+            if app.state.take_mention_alert() {
+                let title = format!("({}) {}", app.state.unseen_mentions.max(1), APP_TITLE);
+                execute!(io::stdout(), Print("\x07"), SetTitle(title))?;
+            }
 
-        // Render UI
-        terminal.draw(|frame| {
-            View::render(&app.state, frame);
-        })?;
+            terminal.draw(|frame| {
+                View::render(&app.state, frame);
+            })?;
+            dirty = false;
+        }
 
         // quit
         if app.state.should_quit {
             break;
         }
 
-        // Capture events with timeout
-        match event_handler.next()? {
-            Event::Key(key) => {
-                // Translate keyboard actions
-                if let Some(action) = InputHandler::handle_key(
-                    key,
-                    &app.state.current_page,
-                    &app.state.input_mode,
-                    &app.state.focused_field,
-                ) {
-                    // Send action to process
-                    let _ = action_tx.send(action);
+        tokio::select! {
+            event = event_handler.next() => {
+                let Some(event) = event else { break };
+                match event? {
+                    Event::Key(key) => {
+                        // Translate keyboard actions; they are handled on the next turn
+                        if let Some(action) = InputHandler::handle_key(
+                            key,
+                            &app.state.current_page,
+                            &app.state.input_mode,
+                            &app.state.focused_field,
+                        ) {
+                            let _ = action_tx.send(action);
+                        }
+                    }
+                    Event::Mouse(mouse) => {
+                        if let Some(action) = InputHandler::handle_mouse(mouse, &app.state) {
+                            let _ = action_tx.send(action);
+                        }
+                    }
+                    Event::FocusGained => {
+                        execute!(io::stdout(), SetTitle(APP_TITLE))?;
+                        let _ = action_tx.send(Action::TerminalFocusChanged(true));
+                    }
+                    Event::FocusLost => {
+                        let _ = action_tx.send(Action::TerminalFocusChanged(false));
+                    }
+                    Event::Resize(width, height) => {
+                        terminal.resize(Rect::new(0, 0, width, height))?;
+                        dirty = true;
+                    }
                 }
             }
-            Event::Tick => {}
-            Event::Render => {}
+            Some(action) = app.next_action() => {
+                app.update(action).await;
+                dirty = true;
+            }
+            _ = housekeeping.tick() => {
+                dirty |= app.housekeep().await;
+            }
         }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
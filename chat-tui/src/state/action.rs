@@ -1,5 +1,8 @@
 use std::fmt;
 use std::fmt::Formatter;
+use chat_core::client::presence::Presence;
+use chat_core::message::chat_message::ChatMessage;
+use crate::state::line_editor::EditCommand;
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Action {
@@ -9,16 +12,36 @@ pub enum Action {
     SwitchToChatPage,
 
     // Connection
-    UpdateServerAddress(String),
-    UpdateUsername(String),
+    UpdateServerAddress(EditCommand),
+    UpdateUsername(EditCommand),
     Connect,
     Disconnect,
 
     //Chat actions
-    UpdateMessageInput(String),
+    UpdateMessageInput(EditCommand),
+    HistoryPrevious,
+    HistoryNext,
+    SearchHistory,
+    /// Tab / Shift+Tab in the message box
+    Complete(bool),
     SendMessage,
     ScrollUp,
     ScrollDown,
+    PageUp,
+    PageDown,
+    ScrollToTop,
+    ScrollToBottom,
+    SelectPreviousMessage,
+    SelectNextMessage,
+    ReplyToSelected,
+    ToggleThread,
+    ToggleMentions,
+    ReactToSelected(String),
+    /// A message from the server, queued with its other updates in the order they arrived
+    MessageReceived(Box<ChatMessage>),
+    ReactionChanged { id: u64, emoji: String, user: String, added: bool },
+    MessageEdited(u64, String),
+    MessageDeleted(u64),
 
     //Room actions
     ChangeRoom(String),
@@ -26,16 +49,54 @@ pub enum Action {
     PreviousRoom,
     UpdateRoomList(Vec<String>),
     JoinRoom(String),
+    RoomHistory { room: String, messages: Vec<ChatMessage>, read_marker: Option<u64> },
+    /// Sidebar entry clicked, counting the rooms then the DMs
+    SelectSidebarEntry(usize),
     JoinRoomWithPassword(String, String),
+    /// The server wants a password for the room; `wrong` when one was refused
+    PasswordRequired { room: String, wrong: bool },
+    UpdatePasswordInput(EditCommand),
+    ToggleRememberPassword,
+    SubmitPassword,
+    CancelPassword,
     CreateRoom(String, Option<String>),
+    OpenCreateRoom,
+    CreateRoomInput(EditCommand),
+    /// Tab / Shift+Tab between the fields of the create-room dialog
+    CreateRoomFocus(bool),
+    SubmitCreateRoom,
+    CancelCreateRoom,
+    CreateRoomFailed(String),
+    /// The server dropped the last line we sent, with why
+    ServerWarning(String),
+    /// Room and its topic, `None` when cleared
+    TopicChanged(String, Option<String>),
+    CloseDirectMessage,
 
     // User list actions
-    UpdateUserList(Vec<String>),
+    UpdateUserList(Vec<(String, Presence)>),
+    OpenDirectMessage(String),
+    InsertMention(String),
+    NickChanged(String, String),
+    TypingChanged(String, bool),
 
     // Ui Actions
     FocusNext,
     FocusPrevious,
     ToggleInputMode,
+    /// Click in the message box
+    StartEditing,
+    TerminalFocusChanged(bool),
+    /// F1 or ?: open or close the help overlay
+    ToggleHelp,
+    /// ↓ / ↑ in the help overlay
+    ScrollHelp(bool),
+    OpenCommandPalette,
+    CommandPaletteInput(EditCommand),
+    /// ↓ / ↑ in the command palette
+    CommandPaletteSelect(bool),
+    RunPaletteEntry,
+    CloseCommandPalette,
 
 
     // System actions
@@ -0,0 +1,179 @@
+use chat_core::message::command_registry::{self, CommandSpec};
+use crate::input::keymap::{self, KeyBinding};
+use crate::state::line_editor::{EditCommand, LineEditor};
+
+/// What an entry of the palette runs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaletteCommand {
+    /// Put in the message box for its arguments
+    Slash(&'static CommandSpec),
+    /// Done right away, like pressing its key
+    Ui(&'static KeyBinding),
+}
+
+impl PaletteCommand {
+    pub fn label(&self) -> String {
+        match self {
+            PaletteCommand::Slash(spec) => format!("/{}", spec.name),
+            PaletteCommand::Ui(binding) => binding.description.to_string(),
+        }
+    }
+
+    /// Arguments of a command, the key of an action
+    pub fn hint(&self) -> &'static str {
+        match self {
+            PaletteCommand::Slash(spec) => spec.args,
+            PaletteCommand::Ui(binding) => binding.keys,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            PaletteCommand::Slash(spec) => spec.description,
+            PaletteCommand::Ui(_) => "",
+        }
+    }
+}
+
+/// The Ctrl+P palette: slash commands and UI actions filtered by a fuzzy query
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandPalette {
+    pub query: LineEditor,
+    /// Matches of the query, best first
+    pub matches: Vec<PaletteCommand>,
+    /// Index into `matches`
+    pub selected: usize,
+}
+
+impl Default for CommandPalette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandPalette {
+    pub fn new() -> Self {
+        let mut palette = Self {
+            query: LineEditor::new(),
+            matches: Vec::new(),
+            selected: 0,
+        };
+        palette.filter();
+        palette
+    }
+
+    /// Every entry, in the order shown for an empty query
+    fn entries() -> impl Iterator<Item = PaletteCommand> {
+        command_registry::COMMANDS.iter().map(PaletteCommand::Slash)
+            .chain(keymap::palette_bindings().map(PaletteCommand::Ui))
+    }
+
+    pub fn apply(&mut self, command: EditCommand) {
+        self.query.apply(command);
+        self.filter();
+    }
+
+    /// Moves the selection, wrapping around
+    pub fn select(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        self.selected = if forward { (self.selected + 1) % len } else { (self.selected + len - 1) % len };
+    }
+
+    pub fn selected(&self) -> Option<PaletteCommand> {
+        self.matches.get(self.selected).copied()
+    }
+
+    fn filter(&mut self) {
+        let query = self.query.trim().trim_start_matches('/').to_lowercase();
+        let mut scored: Vec<(i32, PaletteCommand)> = Self::entries()
+            .filter_map(|entry| {
+                let label = entry.label();
+                let score = fuzzy_score(label.trim_start_matches('/'), &query)
+                    // A word of the description still finds it, below any name match
+                    .or_else(|| entry.description().to_lowercase().contains(&query).then_some(i32::MIN / 2))?;
+                Some((score, entry))
+            })
+            .collect();
+        // Stable, so ties keep the registry order
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        self.matches = scored.into_iter().map(|(_, entry)| entry).collect();
+        self.selected = 0;
+    }
+}
+
+/// How well `query` matches `candidate` as a subsequence, ignoring case;
+/// `None` when it doesn't. Runs of letters and word starts score higher.
+pub fn fuzzy_score(candidate: &str, query: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let found = next + candidate[next..].iter().position(|c| *c == wanted)?;
+        score += 1;
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 8;
+        }
+        match previous {
+            Some(previous) if found == previous + 1 => score += 5,
+            Some(previous) => score -= (found - previous - 1).min(5) as i32,
+            None => score -= found.min(5) as i32,
+        }
+        previous = Some(found);
+        next = found + 1;
+    }
+    Some(score)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::action::Action;
+
+    fn labels(palette: &CommandPalette) -> Vec<String> {
+        palette.matches.iter().map(PaletteCommand::label).collect()
+    }
+
+    fn typed(query: &str) -> CommandPalette {
+        let mut palette = CommandPalette::new();
+        for c in query.chars() {
+            palette.apply(EditCommand::Insert(c));
+        }
+        palette
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert_eq!(fuzzy_score("join", "xj"), None);
+        // Prefixes beat scattered letters
+        assert!(fuzzy_score("slowmode", "sl") > fuzzy_score("whisper", "sp"));
+        assert!(fuzzy_score("Next room", "nr") > fuzzy_score("Change your nickname", "nr"));
+    }
+
+    #[test]
+    fn test_query_filters_commands_and_actions() {
+        let palette = CommandPalette::new();
+        assert_eq!(palette.matches.len(), command_registry::COMMANDS.len() + keymap::palette_bindings().count());
+        assert_eq!(palette.selected(), Some(PaletteCommand::Slash(&command_registry::COMMANDS[0])));
+
+        let palette = typed("/jo");
+        assert_eq!(labels(&palette)[0], "/join");
+
+        let palette = typed("room");
+        assert!(labels(&palette).starts_with(&["/rooms".to_string()]));
+        assert!(labels(&palette).contains(&"Create a room".to_string()));
+
+        let mut palette = typed("nextro");
+        assert!(matches!(palette.selected(), Some(PaletteCommand::Ui(binding)) if binding.action == Some(Action::NextRoom)));
+        palette.select(false);
+        assert_eq!(palette.selected, palette.matches.len() - 1);
+
+        // Descriptions are searched too
+        let palette = typed("private");
+        assert_eq!(labels(&palette), vec!["/whisper"]);
+    }
+}
@@ -0,0 +1,154 @@
+use chat_core::message::command_registry::COMMANDS;
+
+/// What the word being completed names
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Target {
+    Command,
+    Nick,
+    Room,
+}
+
+/// A Tab completion in progress: `candidates[index]` stands in the
+/// message box from `start` up to the cursor
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub candidates: Vec<String>,
+    pub index: usize,
+    /// Byte offset where the completed word starts
+    pub start: usize,
+    /// The word as typed, given back when the completion is cancelled
+    pub typed: String,
+}
+
+impl Completion {
+    /// Candidates for the word before `cursor`, `None` when nothing matches
+    pub fn new(text: &str, cursor: usize, users: &[String], rooms: &[String]) -> Option<Self> {
+        let before = &text[..cursor];
+        let start = before.rfind(char::is_whitespace)
+            .map_or(0, |idx| idx + before[idx..].chars().next().map_or(1, char::len_utf8));
+        let word = &before[start..];
+        let target = Self::target(&before[..start], word)?;
+
+        let candidates: Vec<String> = match target {
+            Target::Command => COMMANDS.iter()
+                .flat_map(|spec| spec.names())
+                .filter(|command| starts_with_ignore_case(command, &word[1..]))
+                .map(|command| format!("/{} ", command))
+                .collect(),
+            Target::Room => rooms.iter()
+                .map(|room| room.trim_end_matches("🔒"))
+                .filter(|room| starts_with_ignore_case(room, word))
+                .map(|room| format!("{} ", room))
+                .collect(),
+            Target::Nick => {
+                let (prefix, name) = match word.strip_prefix('@') {
+                    Some(name) => ("@", name),
+                    None => ("", word),
+                };
+                // Addressing someone at the start of a line
+                let suffix = if start == 0 && prefix.is_empty() { ": " } else { " " };
+                users.iter()
+                    .filter(|user| starts_with_ignore_case(user, name))
+                    .map(|user| format!("{}{}{}", prefix, user, suffix))
+                    .collect()
+            }
+        };
+
+        if candidates.is_empty() {
+            return None;
+        }
+        Some(Self {
+            candidates,
+            index: 0,
+            start,
+            typed: word.to_string(),
+        })
+    }
+
+    pub fn current(&self) -> &str {
+        &self.candidates[self.index]
+    }
+
+    pub fn step(&mut self, forward: bool) {
+        let len = self.candidates.len();
+        self.index = if forward {
+            (self.index + 1) % len
+        } else {
+            (self.index + len - 1) % len
+        };
+    }
+
+    /// Decides what to complete from the text before the word
+    fn target(before_word: &str, word: &str) -> Option<Target> {
+        let mut tokens = before_word.split_whitespace();
+        let Some(first) = tokens.next() else {
+            return match word {
+                w if w.starts_with('/') => Some(Target::Command),
+                "" => None,
+                _ => Some(Target::Nick),
+            };
+        };
+
+        // Argument position after a command
+        let argument = tokens.count();
+        let target = match (first.strip_prefix('/'), argument) {
+            (Some("join" | "info"), 0) => Target::Room,
+            (Some("invite"), 1) => Target::Room,
+            (Some("w" | "whisper" | "whois" | "invite"), 0) => Target::Nick,
+            _ if word.is_empty() => return None,
+            _ => Target::Nick,
+        };
+        Some(target)
+    }
+}
+
+fn starts_with_ignore_case(candidate: &str, prefix: &str) -> bool {
+    candidate.to_lowercase().starts_with(&prefix.to_lowercase())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn complete(text: &str) -> Option<Vec<String>> {
+        let users = names(&["bob", "Bernard", "carol"]);
+        let rooms = names(&["general", "dev", "design🔒"]);
+        Completion::new(text, text.len(), &users, &rooms).map(|c| c.candidates)
+    }
+
+    #[test]
+    fn test_commands_complete_at_line_start() {
+        assert_eq!(complete("/whi"), Some(names(&["/whisper "])));
+        assert_eq!(complete("/w"), Some(names(&["/whois ", "/w ", "/whisper "])));
+        assert_eq!(complete("/nope"), None);
+    }
+
+    #[test]
+    fn test_nicks_and_rooms_by_position() {
+        assert_eq!(complete("b"), Some(names(&["bob: ", "Bernard: "])));
+        assert_eq!(complete("hi @ca"), Some(names(&["@carol "])));
+        assert_eq!(complete("/w car"), Some(names(&["carol "])));
+        assert_eq!(complete("/join de"), Some(names(&["dev ", "design "])));
+        assert_eq!(complete("/invite bob "), Some(names(&["general ", "dev ", "design "])));
+        // Nothing typed outside an argument: Tab does nothing
+        assert_eq!(complete("hello "), None);
+    }
+
+    #[test]
+    fn test_step_cycles_both_ways() {
+        let users = names(&["bob", "Bernard"]);
+        let mut completion = Completion::new("b", 1, &users, &[]).unwrap();
+        assert_eq!(completion.current(), "bob: ");
+        completion.step(true);
+        assert_eq!(completion.current(), "Bernard: ");
+        completion.step(true);
+        assert_eq!(completion.current(), "bob: ");
+        completion.step(false);
+        assert_eq!(completion.current(), "Bernard: ");
+    }
+}
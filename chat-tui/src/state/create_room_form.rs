@@ -0,0 +1,192 @@
+use chat_core::server::room_policy::RoomPolicy;
+use crate::state::line_editor::{EditCommand, LineEditor};
+
+/// Slow mode choices offered by the form, in seconds; `None` leaves it off
+pub const SLOW_MODES: [Option<u64>; 4] = [None, Some(10), Some(30), Some(60)];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CreateRoomField {
+    Name,
+    Password,
+    Mode,
+    Topic,
+}
+
+impl CreateRoomField {
+    const ORDER: [CreateRoomField; 4] = [Self::Name, Self::Password, Self::Mode, Self::Topic];
+
+    fn step(self, forward: bool) -> Self {
+        let idx = Self::ORDER.iter().position(|field| *field == self).unwrap_or(0);
+        let len = Self::ORDER.len();
+        Self::ORDER[if forward { (idx + 1) % len } else { (idx + len - 1) % len }]
+    }
+}
+
+/// The create-room dialog. The mode and topic are applied with `/slowmode`
+/// and `/topic` once the server confirms the room.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateRoomForm {
+    pub name: LineEditor,
+    pub password: LineEditor,
+    /// Index into `SLOW_MODES`
+    pub mode: usize,
+    pub topic: LineEditor,
+    pub focus: CreateRoomField,
+    /// Why the last submit was refused, here or by the server
+    pub error: Option<String>,
+    /// Sent, waiting for the server's answer
+    pub pending: bool,
+}
+
+impl Default for CreateRoomForm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CreateRoomForm {
+    pub fn new() -> Self {
+        Self {
+            name: LineEditor::new(),
+            password: LineEditor::new(),
+            mode: 0,
+            topic: LineEditor::new(),
+            focus: CreateRoomField::Name,
+            error: None,
+            pending: false,
+        }
+    }
+
+    pub fn focus_next(&mut self, forward: bool) {
+        self.focus = self.focus.step(forward);
+    }
+
+    /// Edits the focused field; on the mode, ←/→ pick the slow mode
+    pub fn apply(&mut self, command: EditCommand) {
+        let editor = match self.focus {
+            CreateRoomField::Name => &mut self.name,
+            CreateRoomField::Password => &mut self.password,
+            CreateRoomField::Topic => &mut self.topic,
+            CreateRoomField::Mode => {
+                let len = SLOW_MODES.len();
+                match command {
+                    EditCommand::Left => self.mode = (self.mode + len - 1) % len,
+                    EditCommand::Right | EditCommand::Insert(' ') => self.mode = (self.mode + 1) % len,
+                    _ => {}
+                }
+                return;
+            }
+        };
+        editor.apply(command);
+        self.error = None;
+    }
+
+    pub fn slow_mode(&self) -> Option<u64> {
+        SLOW_MODES[self.mode]
+    }
+
+    /// Checks the fields against the server's rules and gives the room and
+    /// password to create, or puts the problem on the form
+    pub fn submit(&mut self) -> Option<(String, Option<String>)> {
+        if self.pending {
+            return None;
+        }
+        match self.validate() {
+            Ok(request) => {
+                self.error = None;
+                self.pending = true;
+                Some(request)
+            }
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<(String, Option<String>), String> {
+        let name = self.name.trim();
+        RoomPolicy::validate_name(name).map_err(|e| e.to_string())?;
+        // `/create` takes the password as one word
+        let password = match self.password.text() {
+            "" => None,
+            password if password.contains(char::is_whitespace) => {
+                return Err("The password can't contain spaces".to_string());
+            }
+            password => Some(password.to_string()),
+        };
+        RoomPolicy::validate_topic(self.topic.trim())?;
+        Ok((name.to_string(), password))
+    }
+
+    /// The server refused the room
+    pub fn refused(&mut self, reason: String) {
+        self.pending = false;
+        self.error = Some(reason);
+    }
+
+    /// Commands that apply the rest of the form to the room just created
+    pub fn setup_commands(&self) -> Vec<String> {
+        let mut commands = Vec::new();
+        if let Some(seconds) = self.slow_mode() {
+            commands.push(format!("/slowmode {}", seconds));
+        }
+        let topic = self.topic.trim();
+        if !topic.is_empty() {
+            commands.push(format!("/topic {}", topic));
+        }
+        commands
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(text: &str) -> LineEditor {
+        LineEditor::from(text)
+    }
+
+    #[test]
+    fn test_fields_are_checked_before_sending() {
+        let mut form = CreateRoomForm::new();
+        form.name = typed("two words");
+        assert_eq!(form.submit(), None);
+        assert!(form.error.as_deref().is_some_and(|error| error.contains("' '")));
+
+        form.name = typed("rustaceans");
+        form.password = typed("open sesame");
+        assert_eq!(form.submit(), None);
+        assert_eq!(form.error.as_deref(), Some("The password can't contain spaces"));
+
+        form.password = typed("sesame");
+        assert_eq!(form.submit(), Some(("rustaceans".to_string(), Some("sesame".to_string()))));
+        assert!(form.pending && form.error.is_none());
+        // Once sent, Enter waits for the answer
+        assert_eq!(form.submit(), None);
+
+        form.refused("Room 'rustaceans' already exists".to_string());
+        assert!(!form.pending);
+        assert_eq!(form.error.as_deref(), Some("Room 'rustaceans' already exists"));
+    }
+
+    #[test]
+    fn test_mode_and_topic_become_commands() {
+        let mut form = CreateRoomForm::new();
+        assert!(form.setup_commands().is_empty());
+
+        form.focus_next(true);
+        form.focus_next(true);
+        assert_eq!(form.focus, CreateRoomField::Mode);
+        form.apply(EditCommand::Left);
+        assert_eq!(form.slow_mode(), Some(60));
+
+        form.focus_next(true);
+        form.apply(EditCommand::Insert('h'));
+        form.apply(EditCommand::Insert('i'));
+        assert_eq!(form.setup_commands(), vec!["/slowmode 60", "/topic hi"]);
+        form.focus_next(true);
+        assert_eq!(form.focus, CreateRoomField::Name);
+    }
+}
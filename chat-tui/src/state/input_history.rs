@@ -0,0 +1,223 @@
+use std::fs;
+use std::path::PathBuf;
+use chat_core::message::command_registry;
+use chat_core::message::protocol::{escape_line, unescape_line};
+
+/// Lines kept, oldest dropped first
+pub const MAX_HISTORY: usize = 500;
+
+/// Lines sent from the message box, oldest first, without duplicates
+#[derive(Debug, Default)]
+pub struct InputHistory {
+    entries: Vec<String>,
+    /// Entry shown by Up/Down, `None` while editing a new line
+    position: Option<usize>,
+    /// The unsent line, given back when moving past the newest entry
+    draft: String,
+    /// Saved after every change when set
+    path: Option<PathBuf>,
+}
+
+impl InputHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// History kept in `path` across sessions, one escaped entry per line.
+    /// A missing or unreadable file starts empty.
+    pub fn persistent(path: PathBuf) -> Self {
+        let entries = fs::read_to_string(&path)
+            .map(|content| content.lines().map(unescape_line).collect())
+            .unwrap_or_default();
+        Self {
+            entries,
+            path: Some(path),
+            ..Self::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Records a sent line; sending it again makes it the newest entry.
+    /// Room passwords are left out, see `without_password`.
+    pub fn push(&mut self, line: &str) {
+        self.reset();
+        if line.trim().is_empty() {
+            return;
+        }
+        let line = without_password(line);
+        self.entries.retain(|entry| *entry != line);
+        self.entries.push(line);
+        if self.entries.len() > MAX_HISTORY {
+            self.entries.remove(0);
+        }
+        self.save();
+    }
+
+    /// One entry further back; `current` is kept as the draft when leaving it
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let index = match self.position {
+            Some(0) => 0,
+            Some(position) => position - 1,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+        };
+        self.position = Some(index);
+        self.get(index)
+    }
+
+    /// One entry forward, or the draft again after the newest entry.
+    /// `None` when not browsing.
+    pub fn newer(&mut self) -> Option<String> {
+        let position = self.position?;
+        if position + 1 < self.entries.len() {
+            self.position = Some(position + 1);
+            return self.get(position + 1).map(str::to_string);
+        }
+        self.position = None;
+        Some(std::mem::take(&mut self.draft))
+    }
+
+    /// Stops browsing
+    pub fn reset(&mut self) {
+        self.position = None;
+        self.draft.clear();
+    }
+
+    /// Newest entry before `before` that contains `query`
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        if query.is_empty() {
+            return None;
+        }
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.contains(query))
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            let mut content = self.entries.iter()
+                .map(|entry| escape_line(entry))
+                .collect::<Vec<_>>()
+                .join("\n");
+            content.push('\n');
+            // History is a convenience: failing to save shouldn't disturb the chat
+            let _ = fs::write(path, content);
+        }
+    }
+}
+
+/// State of a Ctrl+R search
+#[derive(Debug, Default, PartialEq)]
+pub struct HistorySearch {
+    pub query: String,
+    /// Index of the entry currently matched
+    pub found: Option<usize>,
+    /// What the message box held when the search started
+    pub original: String,
+}
+
+
+/// `/join <room> <password>` and `/create <room> <password>` without the
+/// password, so it never reaches the history file
+fn without_password(line: &str) -> String {
+    let mut words = line.split_whitespace();
+    let command = words.next().and_then(|word| word.strip_prefix('/')).and_then(command_registry::find);
+    match (command, words.next(), words.next()) {
+        (Some(spec), Some(room), Some(_)) if matches!(spec.name, "join" | "create") => {
+            format!("/{} {}", spec.name, room)
+        }
+        _ => line.to_string(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(lines: &[&str]) -> InputHistory {
+        let mut history = InputHistory::new();
+        for line in lines {
+            history.push(line);
+        }
+        history
+    }
+
+    #[test]
+    fn test_duplicates_become_newest() {
+        let history = history(&["one", "two", "one", "  "]);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(0), Some("two"));
+        assert_eq!(history.get(1), Some("one"));
+    }
+
+    #[test]
+    fn test_browsing_keeps_the_draft() {
+        let mut history = history(&["one", "two"]);
+        assert_eq!(history.older("half typed"), Some("two"));
+        assert_eq!(history.older("two"), Some("one"));
+        assert_eq!(history.older("one"), Some("one"));
+
+        assert_eq!(history.newer().as_deref(), Some("two"));
+        assert_eq!(history.newer().as_deref(), Some("half typed"));
+        assert_eq!(history.newer(), None);
+    }
+
+    #[test]
+    fn test_search_goes_back_through_matches() {
+        let history = history(&["/join dev", "hello", "/join ops"]);
+        let newest = history.search("join", history.len());
+        assert_eq!(newest, Some(2));
+        assert_eq!(history.search("join", 2), Some(0));
+        assert_eq!(history.search("join", 0), None);
+        assert_eq!(history.search("", history.len()), None);
+    }
+
+    #[test]
+    fn test_persistent_history_survives_restart() {
+        let path = std::env::temp_dir().join(format!("rustatui-history-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut history = InputHistory::persistent(path.clone());
+        history.push("first");
+        history.push("second\nline");
+
+        let reloaded = InputHistory::persistent(path.clone());
+        assert_eq!(reloaded.get(0), Some("first"));
+        assert_eq!(reloaded.get(1), Some("second\nline"));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_room_passwords_are_not_kept() {
+        let path = std::env::temp_dir().join(format!("rustatui-history-secret-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut history = InputHistory::persistent(path.clone());
+        history.push("/join vault s3cret");
+        history.push("/create den  hunter2");
+        history.push("/join   vault");
+        history.push("my password is not a command");
+
+        assert_eq!(history.get(0), Some("/join vault"));
+        assert_eq!(history.get(1), Some("/create den"));
+        // Without a password the line is kept as typed
+        assert_eq!(history.get(2), Some("/join   vault"));
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(!saved.contains("s3cret") && !saved.contains("hunter2"));
+        let _ = fs::remove_file(&path);
+    }
+}
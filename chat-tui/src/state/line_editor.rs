@@ -0,0 +1,442 @@
+use std::ops::Deref;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// One editing step, produced by `InputHandler` from a key press
+#[derive(Clone, Debug, PartialEq)]
+pub enum EditCommand {
+    Insert(char),
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    WordLeft,
+    WordRight,
+    /// Start of the current line
+    Home,
+    /// End of the current line
+    End,
+    /// Ctrl+W: back to the previous whitespace
+    DeleteWordBackward,
+    /// Alt+D: to the end of the current word
+    DeleteWordForward,
+    /// Ctrl+K
+    KillToEnd,
+    /// Ctrl+U
+    KillToStart,
+    /// Ctrl+Y: inserts the last killed text
+    Yank,
+}
+
+/// A text field with a cursor. The cursor is a byte offset that always
+/// sits on a grapheme boundary. The text may hold line breaks, which only
+/// the message box inserts (Shift+Enter or Alt+Enter).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LineEditor {
+    text: String,
+    cursor: usize,
+    killed: String,
+}
+
+impl From<&str> for LineEditor {
+    fn from(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            cursor: text.len(),
+            killed: String::new(),
+        }
+    }
+}
+
+impl Deref for LineEditor {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replaces the text and puts the cursor at the end
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    /// Replaces the text from `start` up to the cursor, leaving the cursor after it
+    pub fn replace_before_cursor(&mut self, start: usize, replacement: &str) {
+        self.text.replace_range(start..self.cursor, replacement);
+        self.cursor = start + replacement.len();
+    }
+
+    pub fn apply(&mut self, command: EditCommand) {
+        match command {
+            EditCommand::Insert(c) => {
+                self.text.insert(self.cursor, c);
+                self.cursor += c.len_utf8();
+                // A combining mark joins the grapheme before it
+                self.cursor = self.boundary_at_or_after(self.cursor);
+            }
+            EditCommand::Backspace => {
+                let start = self.previous_boundary(self.cursor);
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            EditCommand::Delete => {
+                let end = self.next_boundary(self.cursor);
+                self.text.replace_range(self.cursor..end, "");
+            }
+            EditCommand::Left => self.cursor = self.previous_boundary(self.cursor),
+            EditCommand::Right => self.cursor = self.next_boundary(self.cursor),
+            EditCommand::WordLeft => self.cursor = self.word_start(self.cursor, is_word),
+            EditCommand::WordRight => self.cursor = self.word_end(self.cursor),
+            EditCommand::Home => self.cursor = self.line_start(self.cursor),
+            EditCommand::End => self.cursor = self.line_end(self.cursor),
+            EditCommand::DeleteWordBackward => {
+                let start = self.word_start(self.cursor, |g| !g.trim().is_empty());
+                self.kill(start, self.cursor);
+            }
+            EditCommand::DeleteWordForward => {
+                let end = self.word_end(self.cursor);
+                self.kill(self.cursor, end);
+            }
+            EditCommand::KillToEnd => self.kill(self.cursor, self.line_end(self.cursor)),
+            EditCommand::KillToStart => self.kill(self.line_start(self.cursor), self.cursor),
+            EditCommand::Yank => {
+                self.text.insert_str(self.cursor, &self.killed);
+                self.cursor += self.killed.len();
+            }
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.text.split('\n').count()
+    }
+
+    /// Line of the cursor, from 0
+    pub fn cursor_row(&self) -> usize {
+        self.text[..self.cursor].matches('\n').count()
+    }
+
+    /// Columns taken by the text before the cursor on its line, wide characters counting double
+    pub fn cursor_column(&self) -> usize {
+        self.text[self.line_start(self.cursor)..self.cursor].width()
+    }
+
+    /// Moves to the line above, keeping the column where it can.
+    /// Returns false on the first line.
+    pub fn line_up(&mut self) -> bool {
+        let start = self.line_start(self.cursor);
+        if start == 0 {
+            return false;
+        }
+        let column = self.cursor_column();
+        self.cursor = self.offset_at_column(self.line_start(start - 1), column);
+        true
+    }
+
+    /// Moves to the line below, keeping the column where it can.
+    /// Returns false on the last line.
+    pub fn line_down(&mut self) -> bool {
+        let end = self.line_end(self.cursor);
+        if end == self.text.len() {
+            return false;
+        }
+        let column = self.cursor_column();
+        self.cursor = self.offset_at_column(end + 1, column);
+        true
+    }
+
+    /// A copy showing `mask` for every character, the cursor in the same
+    /// place, for drawing passwords
+    pub fn masked(&self, mask: char) -> LineEditor {
+        let hidden = |text: &str| text.graphemes(true).map(|_| mask).collect::<String>();
+        Self {
+            text: hidden(&self.text),
+            cursor: hidden(&self.text[..self.cursor]).len(),
+            killed: String::new(),
+        }
+    }
+
+    /// The part of the cursor's line to draw in a field `width` columns wide,
+    /// scrolled so the cursor stays visible, and the cursor column within it
+    pub fn visible(&self, width: usize) -> (&str, usize) {
+        let line = &self.text[self.line_start(self.cursor)..self.line_end(self.cursor)];
+        let column = self.cursor_column();
+        let (visible, skipped) = skip_columns(line, overflow(column, width));
+        (visible, column - skipped)
+    }
+
+    /// The lines to draw in a box `width` by `height`, scrolled down to the
+    /// cursor's line and sideways like `visible`, with the cursor row and
+    /// column within them
+    pub fn visible_lines(&self, width: usize, height: usize) -> (Vec<&str>, usize, usize) {
+        let height = height.max(1);
+        let row = self.cursor_row();
+        let first = (row + 1).saturating_sub(height);
+        let (cursor_line, column) = self.visible(width);
+        let skip = overflow(self.cursor_column(), width);
+
+        let lines = self.text.split('\n')
+            .enumerate()
+            .skip(first)
+            .take(height)
+            .map(|(idx, line)| if idx == row { cursor_line } else { skip_columns(line, skip).0 })
+            .collect();
+        (lines, row - first, column)
+    }
+
+    fn kill(&mut self, start: usize, end: usize) {
+        if start == end {
+            return;
+        }
+        self.killed = self.text[start..end].to_string();
+        self.text.replace_range(start..end, "");
+        self.cursor = start;
+    }
+
+    fn line_start(&self, from: usize) -> usize {
+        self.text[..from].rfind('\n').map_or(0, |pos| pos + 1)
+    }
+
+    fn line_end(&self, from: usize) -> usize {
+        self.text[from..].find('\n').map_or(self.text.len(), |pos| from + pos)
+    }
+
+    /// Offset in the line starting at `start` whose column is closest to `column` without passing it
+    fn offset_at_column(&self, start: usize, column: usize) -> usize {
+        let line = &self.text[start..self.line_end(start)];
+        let mut width = 0;
+        for (offset, grapheme) in line.grapheme_indices(true) {
+            width += grapheme.width();
+            if width > column {
+                return start + offset;
+            }
+        }
+        start + line.len()
+    }
+
+    fn previous_boundary(&self, from: usize) -> usize {
+        self.text[..from].grapheme_indices(true).next_back().map_or(0, |(offset, _)| offset)
+    }
+
+    fn next_boundary(&self, from: usize) -> usize {
+        self.text[from..].graphemes(true).next().map_or(from, |g| from + g.len())
+    }
+
+    fn boundary_at_or_after(&self, from: usize) -> usize {
+        self.text.grapheme_indices(true)
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(self.text.len()))
+            .find(|offset| *offset >= from)
+            .unwrap_or(self.text.len())
+    }
+
+    /// Start of the word before `from`: skips separators, then word graphemes
+    fn word_start(&self, from: usize, in_word: fn(&str) -> bool) -> usize {
+        let mut graphemes = self.text[..from].grapheme_indices(true).rev().peekable();
+        while graphemes.next_if(|(_, g)| !in_word(g)).is_some() {}
+        let mut start = graphemes.peek().map_or(0, |(offset, g)| offset + g.len());
+        for (offset, grapheme) in graphemes {
+            if !in_word(grapheme) {
+                break;
+            }
+            start = offset;
+        }
+        start
+    }
+
+    /// End of the word after `from`: skips separators, then word graphemes
+    fn word_end(&self, from: usize) -> usize {
+        let mut end = from;
+        let mut seen_word = false;
+        for grapheme in self.text[from..].graphemes(true) {
+            match (is_word(grapheme), seen_word) {
+                (true, _) => seen_word = true,
+                (false, true) => break,
+                (false, false) => {}
+            }
+            end += grapheme.len();
+        }
+        end
+    }
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().next().is_some_and(char::is_alphanumeric)
+}
+
+/// Columns to scroll past so `column` fits in a field `width` columns wide
+fn overflow(column: usize, width: usize) -> usize {
+    if width == 0 || column < width {
+        0
+    } else {
+        column + 1 - width
+    }
+}
+
+/// `line` without the whole graphemes covering its first `columns` columns,
+/// and how many columns that skipped
+fn skip_columns(line: &str, columns: usize) -> (&str, usize) {
+    let mut skipped = 0;
+    for (offset, grapheme) in line.grapheme_indices(true) {
+        if skipped >= columns {
+            return (&line[offset..], skipped);
+        }
+        skipped += grapheme.width();
+    }
+    ("", skipped)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EditCommand::*;
+
+    fn editor(text: &str, cursor: usize) -> LineEditor {
+        let mut editor = LineEditor::from(text);
+        editor.cursor = cursor;
+        editor
+    }
+
+    #[test]
+    fn test_insert_and_delete_at_cursor() {
+        let mut editor = LineEditor::from("hllo");
+        editor.apply(Home);
+        editor.apply(Right);
+        editor.apply(Insert('e'));
+        assert_eq!(editor.text(), "hello");
+        assert_eq!(editor.cursor(), 2);
+
+        editor.apply(Delete);
+        assert_eq!(editor.text(), "helo");
+        editor.apply(Backspace);
+        assert_eq!(editor.text(), "hlo");
+        editor.apply(End);
+        editor.apply(Right);
+        assert_eq!(editor.cursor(), 3);
+    }
+
+    #[test]
+    fn test_graphemes_move_as_one() {
+        // "e" + combining acute, then a family emoji made of several code points
+        let mut editor = LineEditor::from("e\u{301}👨‍👩‍👧");
+        editor.apply(Left);
+        assert_eq!(editor.cursor(), "e\u{301}".len());
+        editor.apply(Backspace);
+        assert_eq!(editor.text(), "👨‍👩‍👧");
+
+        // A combining mark typed after a letter stays with it
+        let mut editor = LineEditor::from("e");
+        editor.apply(Insert('\u{301}'));
+        editor.apply(Left);
+        assert_eq!(editor.cursor(), 0);
+    }
+
+    #[test]
+    fn test_word_movement() {
+        let mut editor = editor("say hello, world", 16);
+        editor.apply(WordLeft);
+        assert_eq!(editor.cursor(), 11);
+        editor.apply(WordLeft);
+        assert_eq!(editor.cursor(), 4);
+        editor.apply(WordRight);
+        assert_eq!(editor.cursor(), 9);
+        editor.apply(WordRight);
+        assert_eq!(editor.cursor(), 16);
+    }
+
+    #[test]
+    fn test_kill_and_yank() {
+        let mut editor = LineEditor::from("git commit -m msg");
+        editor.apply(DeleteWordBackward);
+        assert_eq!(editor.text(), "git commit -m ");
+        editor.apply(DeleteWordBackward);
+        assert_eq!(editor.text(), "git commit ");
+
+        editor.apply(Home);
+        editor.apply(DeleteWordForward);
+        assert_eq!(editor.text(), " commit ");
+        editor.apply(End);
+        editor.apply(Yank);
+        assert_eq!(editor.text(), " commit git");
+
+        editor.apply(WordLeft);
+        editor.apply(KillToStart);
+        assert_eq!(editor.text(), "git");
+        editor.apply(KillToEnd);
+        assert!(editor.is_empty());
+    }
+
+    #[test]
+    fn test_visible_window_follows_cursor() {
+        let editor = LineEditor::from("abcdef");
+        assert_eq!(editor.visible(10), ("abcdef", 6));
+        assert_eq!(editor.visible(4), ("def", 3));
+
+        // Wide characters take two columns
+        let editor = LineEditor::from("日本語");
+        assert_eq!(editor.cursor_column(), 6);
+        assert_eq!(editor.visible(4), ("語", 2));
+    }
+
+    #[test]
+    fn test_multi_line_movement() {
+        let mut editor = LineEditor::from("first line\nok\nthird");
+        assert_eq!(editor.line_count(), 3);
+        assert_eq!((editor.cursor_row(), editor.cursor_column()), (2, 5));
+
+        // The column is kept where the line is long enough
+        assert!(editor.line_up());
+        assert_eq!(editor.cursor(), "first line\nok".len());
+        assert!(editor.line_up());
+        assert_eq!(editor.cursor(), 2);
+        assert!(!editor.line_up());
+
+        editor.apply(End);
+        assert_eq!(editor.cursor(), "first line".len());
+        assert!(editor.line_down());
+        editor.apply(Home);
+        editor.apply(KillToEnd);
+        assert_eq!(editor.text(), "first line\n\nthird");
+        assert!(editor.line_down());
+        assert!(!editor.line_down());
+    }
+
+    #[test]
+    fn test_masked_keeps_the_cursor() {
+        let mut editor = LineEditor::from("pässw🔑rd");
+        editor.apply(EditCommand::Left);
+        editor.apply(EditCommand::Left);
+        let masked = editor.masked('•');
+        assert_eq!(masked.text(), "••••••••");
+        assert_eq!(masked.visible(20), ("••••••••", 6));
+    }
+
+    #[test]
+    fn test_visible_lines_follow_cursor() {
+        let editor = LineEditor::from("one\ntwo\nthree");
+        assert_eq!(editor.visible_lines(10, 5), (vec!["one", "two", "three"], 2, 5));
+        assert_eq!(editor.visible_lines(10, 2), (vec!["two", "three"], 1, 5));
+        // Every line scrolls sideways with the cursor's
+        assert_eq!(editor.visible_lines(4, 2), (vec!["o", "ree"], 1, 3));
+    }
+}
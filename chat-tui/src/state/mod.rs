@@ -1,4 +1,10 @@
 pub mod action;
+pub mod command_palette;
+pub mod completion;
+pub mod create_room_form;
+pub mod input_history;
+pub mod line_editor;
+pub mod password_prompt;
 pub mod state;
 
 use std::sync::Arc;
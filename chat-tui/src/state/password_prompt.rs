@@ -0,0 +1,24 @@
+use crate::state::line_editor::LineEditor;
+
+/// Dialog asking for the password of a locked room the server refused to
+/// let us into; submitting it sends the join again
+#[derive(Debug, Clone, PartialEq)]
+pub struct PasswordPrompt {
+    pub room: String,
+    pub input: LineEditor,
+    /// The last password tried was wrong, rather than missing
+    pub wrong: bool,
+    /// Keep the password for the rest of the session
+    pub remember: bool,
+}
+
+impl PasswordPrompt {
+    pub fn new(room: String, wrong: bool, remember: bool) -> Self {
+        Self {
+            room,
+            input: LineEditor::new(),
+            wrong,
+            remember,
+        }
+    }
+}
@@ -1,5 +1,18 @@
-use chat_core::message::chat_message::ChatMessage;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use ratatui::layout::Rect;
+use chat_core::client::presence::Presence;
+use chat_core::message::chat_message::{ChatMessage, MessageType};
+use chat_core::message::command_registry;
 use crate::client::tui_client::TuiClient;
+use crate::state::action::Action;
+use crate::state::command_palette::{CommandPalette, PaletteCommand};
+use crate::state::completion::Completion;
+use crate::state::create_room_form::CreateRoomForm;
+use crate::state::input_history::{HistorySearch, InputHistory};
+use crate::state::line_editor::{EditCommand, LineEditor};
+use crate::state::password_prompt::PasswordPrompt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppPage {
@@ -28,24 +41,143 @@ pub enum FocusedField {
     ConnectButton,
     MessageInput,
     MessageList,
+    /// The password dialog of a locked room
+    PasswordPrompt,
+    CreateRoomDialog,
+    CommandPalette,
+    /// The help overlay, over whatever had focus before
+    Help,
 }
 
+/// The message list as it was last drawn, for scrolling by pages and
+/// stopping at the top. Written by the view on every frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MessageViewport {
+    /// Rows scrolled up from the bottom once clamped and kept on the selection
+    pub offset: usize,
+    /// Largest offset: every wrapped row but one screen
+    pub max_offset: usize,
+    /// Rows that fit on screen
+    pub height: usize,
+}
+
+/// Where the chat page's panes were last drawn, for telling what a mouse
+/// click or wheel turn landed on. Written by the view on every frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ChatLayout {
+    pub rooms: Rect,
+    pub users: Rect,
+    /// The room, DM or mentions pane, the thread pane left out
+    pub messages: Rect,
+    pub input: Rect,
+}
+
+/// The help overlay, keeping the mode and focus it was opened over: the
+/// keys it lists are theirs, and closing it gives them back
+#[derive(Debug, Clone, PartialEq)]
+pub struct HelpContext {
+    pub input_mode: InputMode,
+    pub focused_field: FocusedField,
+    /// Rows scrolled down
+    pub scroll: u16,
+}
+
+/// Private conversation with one peer, shown in the sidebar under the rooms
+#[derive(Debug, Clone)]
+pub struct DirectConversation {
+    pub peer: String,
+    pub messages: Vec<ChatMessage>,
+    pub unread: usize,
+}
+
+impl DirectConversation {
+    pub fn new(peer: String) -> Self {
+        Self {
+            peer,
+            messages: Vec::new(),
+            unread: 0,
+        }
+    }
+}
+
+/// While typing, "start" is sent again this often
+pub const TYPING_REFRESH: Duration = Duration::from_secs(3);
+/// A typing notice with no refresh or stop expires after this long
+pub const TYPING_TIMEOUT: Duration = Duration::from_secs(6);
+/// Read progress is reported to the server at most this often
+pub const READ_REPORT_INTERVAL: Duration = Duration::from_secs(2);
+/// Mentions kept for the mentions view, oldest dropped first
+pub const MAX_MENTIONS: usize = 200;
+
 pub struct AppState {
     pub current_page: AppPage,
     pub should_quit: bool,
 
-    pub server_address: String,
+    pub server_address: LineEditor,
+    /// Name typed on the connection page; `username` is the one the server knows us by
+    pub username_input: LineEditor,
     pub username: String,
     pub connection_status: ConnectionStatus,
 
     pub messages: Vec<ChatMessage>,
-    pub message_input: String,
+    pub message_input: LineEditor,
+    pub input_history: InputHistory,
+    /// Ctrl+R search in progress
+    pub history_search: Option<HistorySearch>,
+    /// Tab completion in progress, its candidates shown in a popup
+    pub completion: Option<Completion>,
     pub input_mode: InputMode,
+    /// Wrapped rows scrolled up from the newest message
     pub scroll_offset: usize,
+    /// Scroll so the first unread message ends at the bottom on the next draw
+    pub scroll_to_unread: bool,
+    pub message_viewport: Cell<MessageViewport>,
+    pub chat_layout: Cell<ChatLayout>,
+    /// Index into `visible_messages()` while the message list has focus
+    pub selected_message: Option<usize>,
+    /// Message the next line answers
+    pub reply_to: Option<u64>,
+    /// Root of the thread shown in the side pane
+    pub thread_root: Option<u64>,
+    /// Last message read before entering the room; the "new messages" divider goes after it
+    pub read_marker: Option<u64>,
+    /// Last message id reported as read in this room, and when
+    pub read_reported: Option<u64>,
+    pub read_reported_at: Option<Instant>,
 
     pub available_rooms: Vec<String>,
     pub current_room: Option<String>,
+    pub room_topic: Option<String>,
+    /// Room the server last confirmed we joined; the current room runs
+    /// ahead of it while a join is on its way
+    pub joined_room: Option<String>,
+    /// Asking for the password of a locked room
+    pub password_prompt: Option<PasswordPrompt>,
+    /// Passwords of locked rooms kept for the session
+    pub room_passwords: HashMap<String, String>,
+    pub create_room: Option<CreateRoomForm>,
+    pub command_palette: Option<CommandPalette>,
+    pub help: Option<HelpContext>,
     pub users_in_room: Vec<String>,
+    /// Presence of everyone in the room, ourselves included
+    pub presence: HashMap<String, Presence>,
+    /// Who is typing in the room and when we last heard about it
+    pub typing_users: HashMap<String, Instant>,
+    /// When we last told the room we are typing
+    pub typing_sent_at: Option<Instant>,
+
+    pub conversations: Vec<DirectConversation>,
+    /// Peer of the DM pane being shown instead of the current room
+    pub active_dm: Option<String>,
+
+    /// Messages that mentioned us, from every room
+    pub mentions: Vec<ChatMessage>,
+    pub show_mentions: bool,
+    /// Mentions that arrived while the terminal was in the background
+    pub unseen_mentions: usize,
+    /// Bell and title update waiting to be written to the terminal
+    pub mention_alert: bool,
+    pub terminal_focused: bool,
 
     pub focused_field: FocusedField,
 
@@ -57,18 +189,48 @@ impl Default for AppState {
         Self {
             current_page: AppPage::Connection,
             should_quit: false,
-            server_address: String::from("127.0.0.1:4556"),
+            server_address: LineEditor::from("127.0.0.1:4556"),
+            username_input: LineEditor::new(),
             username: String::new(),
             connection_status: ConnectionStatus::Disconnected,
             messages: Vec::new(),
-            message_input: String::new(),
+            message_input: LineEditor::new(),
+            input_history: InputHistory::new(),
+            history_search: None,
+            completion: None,
             input_mode: InputMode::Normal,
             scroll_offset: 0,
+            scroll_to_unread: false,
+            message_viewport: Cell::new(MessageViewport::default()),
+            chat_layout: Cell::new(ChatLayout::default()),
+            selected_message: None,
+            reply_to: None,
+            thread_root: None,
+            read_marker: None,
+            read_reported: None,
+            read_reported_at: None,
             available_rooms: vec![
                 "general".to_string(),
             ],
             current_room: Some("general".to_string()),
+            room_topic: None,
+            joined_room: None,
+            password_prompt: None,
+            room_passwords: HashMap::new(),
+            create_room: None,
+            command_palette: None,
+            help: None,
             users_in_room: vec![],
+            presence: HashMap::new(),
+            typing_users: HashMap::new(),
+            typing_sent_at: None,
+            conversations: Vec::new(),
+            active_dm: None,
+            mentions: Vec::new(),
+            show_mentions: false,
+            unseen_mentions: 0,
+            mention_alert: false,
+            terminal_focused: true,
             focused_field: FocusedField::ServerAddress,
             client: None,
         }
@@ -90,65 +252,1297 @@ impl AppState {
     }
 
     pub fn add_message(&mut self, msg: ChatMessage) {
-        self.messages.push(msg);
+        match msg.message_type {
+            MessageType::Whisper => self.add_direct_message(msg),
+            // Mentioned from another room: we never see the room itself
+            MessageType::Mention => self.record_mention(msg, true),
+            // Command replies go to whatever the user is looking at
+            MessageType::System if self.active_dm.is_some() => {
+                if let Some(conversation) = self.active_conversation_mut() {
+                    conversation.messages.push(msg);
+                }
+            }
+            _ => {
+                // A message ends its sender's typing notice
+                self.typing_users.remove(&msg.sender_name);
+                if msg.mentions_user(&self.username) && msg.sender_name != self.username {
+                    let mut mention = msg.clone();
+                    mention.message_type = MessageType::Mention;
+                    mention.room = self.get_current_room().to_string();
+                    let room_hidden = self.active_dm.is_some() || self.show_mentions;
+                    self.record_mention(mention, room_hidden);
+                }
+                self.messages.push(msg);
+            }
+        }
+        self.scroll_to_bottom();
+    }
+
+    /// Keeps a mention for the mentions view and raises an alert
+    /// when the user can't see it arrive
+    fn record_mention(&mut self, msg: ChatMessage, room_hidden: bool) {
+        if self.mentions.len() >= MAX_MENTIONS {
+            self.mentions.remove(0);
+        }
+        self.mentions.push(msg);
+        if room_hidden || !self.terminal_focused {
+            self.unseen_mentions += 1;
+            self.mention_alert = true;
+        }
+    }
+
+    /// Returns true once per pending alert
+    pub fn take_mention_alert(&mut self) -> bool {
+        std::mem::take(&mut self.mention_alert)
+    }
+
+    pub fn set_terminal_focus(&mut self, focused: bool) {
+        self.terminal_focused = focused;
+        if focused {
+            self.unseen_mentions = 0;
+        }
+    }
+
+    pub fn set_typing(&mut self, name: String, typing: bool, now: Instant) {
+        if typing {
+            self.typing_users.insert(name, now);
+        } else {
+            self.typing_users.remove(&name);
+        }
+    }
+
+    /// Names with a fresh typing notice, sorted
+    pub fn typing_names(&self, now: Instant) -> Vec<&str> {
+        let mut names: Vec<&str> = self.typing_users.iter()
+            .filter(|(_, since)| now.duration_since(**since) < TYPING_TIMEOUT)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Forgets typing notices older than the timeout; true when any went
+    pub fn expire_typing(&mut self, now: Instant) -> bool {
+        let before = self.typing_users.len();
+        self.typing_users.retain(|_, since| now.duration_since(*since) < TYPING_TIMEOUT);
+        self.typing_users.len() != before
+    }
+
+    /// Decides which typing frame, if any, the current input calls for:
+    /// `Some(true)` to (re)announce typing, `Some(false)` to stop
+    pub fn typing_update(&mut self, now: Instant) -> Option<bool> {
+        // Whispers and commands aren't announced to the room
+        let composing = self.active_dm.is_none()
+            && !self.message_input.is_empty()
+            && !self.message_input.starts_with('/');
+        match (composing, self.typing_sent_at) {
+            (true, Some(sent)) if now.duration_since(sent) < TYPING_REFRESH => None,
+            (true, _) => {
+                self.typing_sent_at = Some(now);
+                Some(true)
+            }
+            (false, Some(_)) => {
+                self.typing_sent_at = None;
+                Some(false)
+            }
+            (false, None) => None,
+        }
+    }
+
+    /// Forgets our typing notice; true when the room still shows it and a
+    /// stop frame is due
+    pub fn end_typing(&mut self) -> bool {
+        self.typing_sent_at.take().is_some()
+    }
+
+    /// Replaces the room messages with the history sent on entry and
+    /// scrolls so the first unread message sits at the bottom of the view
+    pub fn load_history(&mut self, room: &str, messages: Vec<ChatMessage>, read_marker: Option<u64>) {
+        if self.get_current_room() != room {
+            return;
+        }
+        // Whatever arrived since we joined stays after the history, unless the history has it too
+        let newest = messages.iter().filter_map(|msg| msg.id).max();
+        let live = std::mem::replace(&mut self.messages, messages);
+        self.messages.extend(live.into_iter()
+            .filter(|msg| msg.id.is_none_or(|id| newest.is_none_or(|newest| id > newest))));
+        self.read_reported = read_marker;
+        self.read_marker = read_marker;
+        let unread = match self.first_unread() {
+            Some(index) => self.messages.len() - index,
+            None => 0,
+        };
+        if unread == 0 {
+            // Nothing new: no divider
+            self.read_marker = None;
+        }
         self.scroll_offset = 0;
+        // Rows are only known once drawn; with one unread message the bottom is right already
+        self.scroll_to_unread = unread > 1;
+    }
+
+    /// Index of the first room message after the read marker
+    pub fn first_unread(&self) -> Option<usize> {
+        let marker = self.read_marker?;
+        self.messages.iter().position(|msg| msg.id.is_some_and(|id| id > marker))
+    }
+
+    /// The room and message id to report as read, when the latest room
+    /// message is on screen and wasn't reported yet. `force` skips the throttle.
+    pub fn read_update(&mut self, now: Instant, force: bool) -> Option<(String, u64)> {
+        let watching = self.active_dm.is_none()
+            && !self.show_mentions
+            && self.terminal_focused
+            && self.scroll_offset == 0
+            && !self.scroll_to_unread;
+        if !watching {
+            return None;
+        }
+        if !force && self.read_reported_at.is_some_and(|at| now.duration_since(at) < READ_REPORT_INTERVAL) {
+            return None;
+        }
+
+        let last = self.messages.iter().rev().find_map(|msg| msg.id)?;
+        if self.read_reported.is_some_and(|reported| reported >= last) {
+            return None;
+        }
+        self.read_reported = Some(last);
+        self.read_reported_at = Some(now);
+        Some((self.get_current_room().to_string(), last))
+    }
+
+    pub fn toggle_mentions(&mut self) {
+        let show = !self.show_mentions;
+        self.reset_view();
+        self.show_mentions = show;
+        if show {
+            self.unseen_mentions = 0;
+        }
+    }
+
+    fn add_direct_message(&mut self, msg: ChatMessage) {
+        let outgoing = msg.sender_name == self.username;
+        let peer = if outgoing {
+            msg.target_name.clone().unwrap_or_default()
+        } else {
+            msg.sender_name.clone()
+        };
+        let is_active = self.active_dm.as_deref() == Some(peer.as_str());
+
+        let conversation = self.conversation_mut(&peer);
+        conversation.messages.push(msg);
+        if !outgoing && !is_active {
+            conversation.unread += 1;
+        }
+    }
+
+    fn conversation_mut(&mut self, peer: &str) -> &mut DirectConversation {
+        let idx = match self.conversations.iter().position(|c| c.peer == peer) {
+            Some(idx) => idx,
+            None => {
+                self.conversations.push(DirectConversation::new(peer.to_string()));
+                self.conversations.len() - 1
+            }
+        };
+        &mut self.conversations[idx]
+    }
+
+    fn active_conversation_mut(&mut self) -> Option<&mut DirectConversation> {
+        let peer = self.active_dm.as_deref()?;
+        self.conversations.iter_mut().find(|c| c.peer == peer)
+    }
+
+    /// Messages of the pane being shown: mentions, the active DM or the current room
+    pub fn visible_messages(&self) -> &[ChatMessage] {
+        if self.show_mentions {
+            return &self.mentions;
+        }
+        match &self.active_dm {
+            Some(peer) => self.conversations.iter()
+                .find(|c| &c.peer == peer)
+                .map(|c| c.messages.as_slice())
+                .unwrap_or(&[]),
+            None => &self.messages,
+        }
+    }
+
+    pub fn open_dm(&mut self, peer: &str) {
+        self.conversation_mut(peer).unread = 0;
+        self.active_dm = Some(peer.to_string());
+        self.reset_view();
+    }
+
+    pub fn close_dm(&mut self) {
+        self.active_dm = None;
+        self.reset_view();
+    }
+
+    /// Drops scroll, selection and thread state tied to the previous pane
+    fn reset_view(&mut self) {
+        self.scroll_to_bottom();
+        self.selected_message = None;
+        self.reply_to = None;
+        self.thread_root = None;
+        self.show_mentions = false;
+    }
+
+    /// Keeps DMs attached to a peer that changed nick
+    pub fn rename_peer(&mut self, old_name: &str, new_name: &str) {
+        for conversation in self.conversations.iter_mut() {
+            if conversation.peer == old_name {
+                conversation.peer = new_name.to_string();
+            }
+        }
+        if self.active_dm.as_deref() == Some(old_name) {
+            self.active_dm = Some(new_name.to_string());
+        }
+    }
+
+    /// The user list as shown: ourselves first, then the rest of the room
+    pub fn listed_users(&self) -> Vec<&str> {
+        let mut users = vec![self.username.as_str()];
+        users.extend(self.users_in_room.iter()
+            .map(String::as_str)
+            .filter(|user| *user != self.username));
+        users
+    }
+
+    /// Puts "@nick " in the message box at the cursor
+    pub fn insert_mention(&mut self, nick: &str) {
+        let before = &self.message_input[..self.message_input.cursor()];
+        let spacer = if before.is_empty() || before.ends_with(char::is_whitespace) { "" } else { " " };
+        for c in format!("{}@{} ", spacer, nick).chars() {
+            self.message_input.apply(EditCommand::Insert(c));
+        }
+    }
+
+    /// What gets sent for the input line: plain text in a DM pane becomes a whisper,
+    /// and a pending reply wraps it in /reply. `/me` is refused in a DM pane,
+    /// where it would be posted to the room instead.
+    pub fn outgoing_line(&self) -> Result<String, String> {
+        if self.message_input.starts_with('/') {
+            let command = self.message_input.trim()[1..].split_whitespace().next().unwrap_or_default();
+            if self.active_dm.is_some() && command_registry::find(command).is_some_and(|spec| spec.name == "me") {
+                return Err("✗ /me can't be used in a direct message".to_string());
+            }
+            return Ok(self.message_input.to_string());
+        }
+        Ok(match (&self.active_dm, self.reply_to) {
+            (Some(peer), _) => format!("/w {} {}", peer, self.message_input.text()),
+            (None, Some(parent)) => format!("/reply {} {}", parent, self.message_input.text()),
+            (None, None) => self.message_input.to_string(),
+        })
+    }
+
+    pub fn find_message(&self, id: u64) -> Option<&ChatMessage> {
+        self.messages.iter().find(|msg| msg.id == Some(id))
+    }
+
+    pub fn selected(&self) -> Option<&ChatMessage> {
+        self.visible_messages().get(self.selected_message?)
+    }
+
+    /// Moves the selection towards older messages, starting from the newest one
+    pub fn select_previous(&mut self) {
+        let len = self.visible_messages().len();
+        if len == 0 {
+            return;
+        }
+        self.selected_message = Some(match self.selected_message {
+            Some(idx) => idx.saturating_sub(1),
+            None => len - 1,
+        });
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.visible_messages().len();
+        if len == 0 {
+            return;
+        }
+        self.selected_message = Some(match self.selected_message {
+            Some(idx) => (idx + 1).min(len - 1),
+            None => len - 1,
+        });
+    }
+
+    /// Id of the selected message when it can be replied or reacted to from
+    /// here. The server looks ids up in the current room, so a mention from
+    /// another room can't.
+    pub fn selected_id(&self) -> Option<u64> {
+        let msg = self.selected()?;
+        if self.show_mentions && msg.room != self.get_current_room() {
+            return None;
+        }
+        msg.id
+    }
+
+    /// Arms a reply to the selected message, if it can be referenced
+    pub fn start_reply(&mut self) -> bool {
+        match self.selected_id() {
+            Some(id) => {
+                self.reply_to = Some(id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Opens the thread of the selected message, or closes the open one
+    pub fn toggle_thread(&mut self) {
+        if self.thread_root.is_some() {
+            self.thread_root = None;
+            return;
+        }
+        if let Some(id) = self.selected().and_then(|msg| msg.id) {
+            self.thread_root = self.thread_roots().get(&id).copied();
+        }
+    }
+
+    /// The first message of the thread each message belongs to, following reply
+    /// links through the messages we have. One pass, each chain walked once.
+    fn thread_roots(&self) -> HashMap<u64, u64> {
+        let parents: HashMap<u64, Option<u64>> = self.messages.iter()
+            .filter_map(|msg| Some((msg.id?, msg.reply_to)))
+            .collect();
+        let mut roots: HashMap<u64, u64> = HashMap::with_capacity(parents.len());
+        // In arrival order, so a cycle always gets the same root
+        for id in self.messages.iter().filter_map(|msg| msg.id) {
+            let mut chain = Vec::new();
+            let mut current = id;
+            let root = loop {
+                if let Some(&root) = roots.get(&current) {
+                    break root;
+                }
+                match parents.get(&current).copied().flatten() {
+                    // Bounded in case of a reply cycle
+                    Some(parent) if parents.contains_key(&parent) && chain.len() < parents.len() => {
+                        chain.push(current);
+                        current = parent;
+                    }
+                    _ => break current,
+                }
+            };
+            roots.insert(current, root);
+            for link in chain {
+                roots.insert(link, root);
+            }
+        }
+        roots
+    }
+
+    /// Root message and every reply that leads back to it, in arrival order
+    pub fn thread_messages(&self) -> Vec<ChatMessage> {
+        let Some(root) = self.thread_root else {
+            return Vec::new();
+        };
+        let roots = self.thread_roots();
+        self.messages.iter()
+            .filter(|msg| msg.id.and_then(|id| roots.get(&id)) == Some(&root))
+            .cloned()
+            .collect()
+    }
+
+    fn message_mut(&mut self, id: u64) -> Option<&mut ChatMessage> {
+        self.messages.iter_mut().find(|msg| msg.id == Some(id))
+    }
+
+    pub fn edit_message(&mut self, id: u64, content: String) {
+        if let Some(msg) = self.message_mut(id) {
+            msg.content = content;
+            msg.edited = true;
+        }
+    }
+
+    /// Applies a reaction delta; deltas that are already applied are ignored
+    pub fn apply_reaction(&mut self, id: u64, emoji: &str, user: &str, added: bool) {
+        let Some(msg) = self.message_mut(id) else {
+            return;
+        };
+        let has_reacted = msg.reactions.iter()
+            .any(|r| r.emoji == emoji && r.users.iter().any(|u| u == user));
+        if has_reacted != added {
+            msg.toggle_reaction(emoji, user);
+        }
+    }
+
+    /// Keeps the message as a tombstone so the conversation still reads in order
+    pub fn delete_message(&mut self, id: u64) {
+        if let Some(msg) = self.message_mut(id) {
+            msg.content.clear();
+            msg.reactions.clear();
+            msg.deleted = true;
+        }
     }
 
     pub fn clear_input(&mut self) {
         self.message_input.clear()
     }
 
-    pub fn change_room(&mut self, room: String) {
-        self.current_room = Some(room);
-        self.scroll_offset = 0;
+    /// Up in the message box: goes up a line of a multi-line message,
+    /// or brings back the previous line sent from the first one
+    pub fn history_previous(&mut self) {
+        if self.history_search.is_some() {
+            return self.accept_history_search();
+        }
+        if self.message_input.line_up() {
+            return;
+        }
+        if let Some(entry) = self.input_history.older(&self.message_input) {
+            let entry = entry.to_string();
+            self.message_input.set_text(&entry);
+        }
     }
 
-    pub fn next_room(&mut self) {
-        if self.available_rooms.is_empty() {
+    pub fn history_next(&mut self) {
+        if self.history_search.is_some() {
+            return self.accept_history_search();
+        }
+        if self.message_input.line_down() {
             return;
         }
+        if let Some(entry) = self.input_history.newer() {
+            self.message_input.set_text(&entry);
+        }
+    }
 
-        let current = self.current_room.as_deref().unwrap_or("general");
-
-        if let Some(idx) = self.available_rooms.iter().position(|r| {
-            r.trim_end_matches("🔒") == current
-        }) {
-            let next_idx = (idx + 1) % self.available_rooms.len();
-            let next_room = self.available_rooms[next_idx].trim_end_matches("🔒").to_string();
-            self.current_room = Some(next_room);
+    /// Ctrl+R starts a search, and again goes to the next older match
+    pub fn search_history(&mut self) {
+        match &mut self.history_search {
+            None => {
+                self.history_search = Some(HistorySearch {
+                    original: self.message_input.to_string(),
+                    ..HistorySearch::default()
+                });
+            }
+            Some(search) => {
+                let before = search.found.unwrap_or(self.input_history.len());
+                if let Some(found) = self.input_history.search(&search.query, before) {
+                    search.found = Some(found);
+                }
+            }
         }
     }
 
-    pub fn previous_room(&mut self) {
-        if self.available_rooms.is_empty() {
+    /// Typing while searching edits the query; any other edit takes the match and applies
+    pub fn edit_history_search(&mut self, command: EditCommand) {
+        let Some(search) = &mut self.history_search else {
             return;
+        };
+        match command {
+            EditCommand::Insert(c) => search.query.push(c),
+            EditCommand::Backspace => {
+                search.query.pop();
+            }
+            other => {
+                self.accept_history_search();
+                self.message_input.apply(other);
+                return;
+            }
+        }
+        search.found = self.input_history.search(&search.query, self.input_history.len());
+    }
+
+    /// Puts the matched line in the message box
+    pub fn accept_history_search(&mut self) {
+        if let Some(search) = self.history_search.take()
+            && let Some(entry) = search.found.and_then(|index| self.input_history.get(index)) {
+            let entry = entry.to_string();
+            self.message_input.set_text(&entry);
         }
+    }
 
-        let current = self.current_room.as_deref().unwrap_or("general");
+    /// Esc while searching gives back what was typed before
+    pub fn cancel_history_search(&mut self) {
+        if let Some(search) = self.history_search.take() {
+            self.message_input.set_text(&search.original);
+        }
+    }
 
-        if let Some(idx) = self.available_rooms.iter().position(|r| {
-            r.trim_end_matches("🔒") == current
-        }) {
-            let prev_idx = if idx == 0 {
-                self.available_rooms.len() - 1
-            } else {
-                idx - 1
+    pub fn history_match(&self) -> Option<&str> {
+        let search = self.history_search.as_ref()?;
+        search.found.and_then(|index| self.input_history.get(index))
+    }
+
+    /// Tab completes the word before the cursor; pressed again, it cycles
+    /// through the candidates (`forward` false goes back)
+    pub fn complete(&mut self, forward: bool) {
+        if let Some(completion) = &mut self.completion {
+            completion.step(forward);
+        } else {
+            let Some(completion) = Completion::new(
+                &self.message_input,
+                self.message_input.cursor(),
+                &self.users_in_room,
+                &self.available_rooms,
+            ) else {
+                return;
             };
-            let prev_room = self.available_rooms[prev_idx].trim_end_matches("🔒").to_string();
-            self.current_room = Some(prev_room);
+            self.completion = Some(completion);
+        }
+
+        let Some(completion) = &self.completion else {
+            return;
+        };
+        self.message_input.replace_before_cursor(completion.start, completion.current());
+        // A single match is simply taken
+        if completion.candidates.len() == 1 {
+            self.completion = None;
         }
     }
 
-    pub fn scroll_up(&mut self) {
-        self.scroll_offset = self.scroll_offset.saturating_add(1)
+    /// Esc while completing gives back the word as typed
+    pub fn cancel_completion(&mut self) {
+        if let Some(completion) = self.completion.take() {
+            self.message_input.replace_before_cursor(completion.start, &completion.typed);
+        }
+    }
+
+    pub fn change_room(&mut self, room: String) {
+        if self.current_room.as_deref() != Some(room.as_str()) {
+            self.room_topic = None;
+        }
+        self.current_room = Some(room);
+        self.typing_users.clear();
+        self.typing_sent_at = None;
+        self.read_marker = None;
+        self.read_reported = None;
+        self.read_reported_at = None;
+        self.reset_view();
+    }
+
+    /// Moves to the next sidebar entry (rooms, then DMs).
+    /// Returns the room to join when the move lands on a different room.
+    pub fn next_room(&mut self) -> Option<String> {
+        self.step_sidebar(true)
+    }
+
+    pub fn previous_room(&mut self) -> Option<String> {
+        self.step_sidebar(false)
+    }
+
+    fn step_sidebar(&mut self, forward: bool) -> Option<String> {
+        let total = self.available_rooms.len() + self.conversations.len();
+        if total == 0 {
+            return None;
+        }
+
+        let current = match &self.active_dm {
+            Some(peer) => self.conversations.iter()
+                .position(|c| &c.peer == peer)
+                .map(|idx| self.available_rooms.len() + idx),
+            None => {
+                let room = self.current_room.as_deref().unwrap_or("general");
+                self.available_rooms.iter().position(|r| r.trim_end_matches("🔒") == room)
+            }
+        }?;
 
+        let next = if forward {
+            (current + 1) % total
+        } else if current == 0 {
+            total - 1
+        } else {
+            current - 1
+        };
+        self.select_sidebar(next)
     }
+
+    /// Shows sidebar entry `index`, counting the rooms then the DMs.
+    /// Returns the room to join when it is a different room.
+    pub fn select_sidebar(&mut self, index: usize) -> Option<String> {
+        if index >= self.available_rooms.len() {
+            let peer = self.conversations.get(index - self.available_rooms.len())?.peer.clone();
+            self.open_dm(&peer);
+            return None;
+        }
+
+        self.close_dm();
+        let room = self.available_rooms[index].trim_end_matches("🔒").to_string();
+        if self.current_room.as_deref() == Some(room.as_str()) {
+            return None;
+        }
+        // The room's history arrives with the join
+        self.messages.clear();
+        self.change_room(room.clone());
+        Some(room)
+    }
+
+    /// The `/join` line for `room`, with its password when one is kept
+    pub fn join_command(&self, room: &str) -> String {
+        match self.room_passwords.get(room) {
+            Some(password) => format!("/join {} {}", room, password),
+            None => format!("/join {}", room),
+        }
+    }
+
+    /// Asks for the password of a room the server refused. A kept password
+    /// that turned out wrong is forgotten.
+    pub fn open_password_prompt(&mut self, room: String, wrong: bool) {
+        let remember = wrong && self.room_passwords.remove(&room).is_some();
+        self.password_prompt = Some(PasswordPrompt::new(room, wrong, remember));
+        self.completion = None;
+        self.history_search = None;
+        self.input_mode = InputMode::Editing;
+        self.focused_field = FocusedField::PasswordPrompt;
+    }
+
+    /// Closes the dialog and gives the room and password to join with,
+    /// keeping the password when asked. Nothing while the field is empty.
+    pub fn submit_password(&mut self) -> Option<(String, String)> {
+        if self.password_prompt.as_ref()?.input.is_empty() {
+            return None;
+        }
+        let prompt = self.close_password_prompt()?;
+        let password = prompt.input.text().to_string();
+        if prompt.remember {
+            self.room_passwords.insert(prompt.room.clone(), password.clone());
+        }
+        Some((prompt.room, password))
+    }
+
+    /// Gives up on the locked room. Returns the room the server still has
+    /// us in when the view had already moved on from it.
+    pub fn cancel_password_prompt(&mut self) -> Option<String> {
+        self.close_password_prompt()?;
+        let joined = self.joined_room.clone().unwrap_or_else(|| "general".to_string());
+        (self.current_room.as_deref() != Some(joined.as_str())).then_some(joined)
+    }
+
+    pub fn open_create_room(&mut self) {
+        self.create_room = Some(CreateRoomForm::new());
+        self.completion = None;
+        self.history_search = None;
+        self.input_mode = InputMode::Editing;
+        self.focused_field = FocusedField::CreateRoomDialog;
+    }
+
+    pub fn close_create_room(&mut self) -> Option<CreateRoomForm> {
+        let form = self.create_room.take()?;
+        self.input_mode = InputMode::Normal;
+        self.focused_field = FocusedField::MessageInput;
+        Some(form)
+    }
+
+    /// Closes the dialog once the server put us in the room it asked for,
+    /// giving the commands that apply its mode and topic
+    pub fn finish_create_room(&mut self, room: &str) -> Option<Vec<String>> {
+        let form = self.create_room.as_ref()?;
+        if !form.pending || form.name.trim() != room {
+            return None;
+        }
+        self.close_create_room().map(|form| form.setup_commands())
+    }
+
+    /// Opens the help overlay, or closes it and gives back the mode and focus
+    pub fn toggle_help(&mut self) {
+        match self.help.take() {
+            Some(help) => {
+                self.input_mode = help.input_mode;
+                self.focused_field = help.focused_field;
+            }
+            None => {
+                self.help = Some(HelpContext {
+                    input_mode: std::mem::replace(&mut self.input_mode, InputMode::Normal),
+                    focused_field: std::mem::replace(&mut self.focused_field, FocusedField::Help),
+                    scroll: 0,
+                });
+            }
+        }
+    }
+
+    pub fn scroll_help(&mut self, down: bool) {
+        if let Some(help) = &mut self.help {
+            help.scroll = if down { help.scroll.saturating_add(1) } else { help.scroll.saturating_sub(1) };
+        }
+    }
+
+    pub fn open_command_palette(&mut self) {
+        self.command_palette = Some(CommandPalette::new());
+        self.completion = None;
+        self.history_search = None;
+        self.input_mode = InputMode::Editing;
+        self.focused_field = FocusedField::CommandPalette;
+    }
+
+    pub fn close_command_palette(&mut self) -> Option<CommandPalette> {
+        let palette = self.command_palette.take()?;
+        self.input_mode = InputMode::Normal;
+        self.focused_field = FocusedField::MessageInput;
+        Some(palette)
+    }
+
+    /// Closes the palette on its selected entry. A slash command is put in
+    /// the message box for its arguments; an action is given back to run.
+    pub fn run_palette_entry(&mut self) -> Option<Action> {
+        let entry = self.command_palette.as_ref()?.selected()?;
+        self.close_command_palette();
+        match entry {
+            PaletteCommand::Slash(spec) => {
+                self.message_input.set_text(&format!("/{} ", spec.name));
+                self.selected_message = None;
+                self.input_mode = InputMode::Editing;
+                None
+            }
+            PaletteCommand::Ui(binding) => binding.action.clone(),
+        }
+    }
+
+    fn close_password_prompt(&mut self) -> Option<PasswordPrompt> {
+        let prompt = self.password_prompt.take()?;
+        self.input_mode = InputMode::Normal;
+        self.focused_field = FocusedField::MessageInput;
+        Some(prompt)
+    }
+
+    /// Moves the message list `rows` wrapped rows up, or down when negative,
+    /// from where it was last drawn and no further than the oldest message
+    fn scroll_by(&mut self, rows: isize) {
+        let viewport = self.message_viewport.get();
+        let current = if self.scroll_to_unread {
+            viewport.offset
+        } else {
+            self.scroll_offset.min(viewport.max_offset)
+        };
+        self.scroll_to_unread = false;
+        self.scroll_offset = current.saturating_add_signed(rows).min(viewport.max_offset);
+    }
+
+    pub fn scroll_up(&mut self) {
+        self.scroll_by(1)
+    }
+
     pub fn scroll_down(&mut self) {
-        self.scroll_offset = self.scroll_offset.saturating_sub(1)
+        self.scroll_by(-1)
+    }
+
+    /// One screen less a row, so the last row seen stays in view
+    fn page(&self) -> isize {
+        self.message_viewport.get().height.saturating_sub(1).max(1) as isize
+    }
+
+    pub fn page_up(&mut self) {
+        self.scroll_by(self.page())
+    }
+
+    pub fn page_down(&mut self) {
+        self.scroll_by(-self.page())
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll_to_unread = false;
+        self.scroll_offset = self.message_viewport.get().max_offset;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll_to_unread = false;
+        self.scroll_offset = 0;
     }
 
 
     pub fn get_current_room(&self) -> &str {
         self.current_room.as_deref().unwrap_or("general")
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn whisper(from: &str, to: &str, content: &str) -> ChatMessage {
+        ChatMessage::whisper(
+            content.to_string(),
+            "0.0.0.0:0".parse().unwrap(),
+            from.to_string(),
+            None,
+            to.to_string(),
+        )
+    }
+
+    fn state() -> AppState {
+        AppState {
+            username: "alice".to_string(),
+            ..AppState::default()
+        }
+    }
+
+    #[test]
+    fn test_password_prompt_remembers_for_the_session() {
+        let mut state = state();
+        state.available_rooms = vec!["general".to_string(), "vault🔒".to_string()];
+        state.joined_room = Some("general".to_string());
+        assert_eq!(state.next_room().as_deref(), Some("vault"));
+        assert_eq!(state.join_command("vault"), "/join vault");
+
+        state.open_password_prompt("vault".to_string(), false);
+        assert_eq!(state.focused_field, FocusedField::PasswordPrompt);
+        assert_eq!(state.submit_password(), None);
+
+        let prompt = state.password_prompt.as_mut().unwrap();
+        prompt.input.set_text("s3cret");
+        prompt.remember = true;
+        assert_eq!(state.submit_password(), Some(("vault".to_string(), "s3cret".to_string())));
+        assert!(state.password_prompt.is_none());
+        assert_eq!(state.input_mode, InputMode::Normal);
+        assert_eq!(state.join_command("vault"), "/join vault s3cret");
+
+        // A kept password that stopped working is dropped, and asked for again
+        state.open_password_prompt("vault".to_string(), true);
+        assert_eq!(state.join_command("vault"), "/join vault");
+        assert!(state.password_prompt.as_ref().is_some_and(|prompt| prompt.wrong && prompt.remember));
+
+        // Giving up goes back to the room the server has us in
+        assert_eq!(state.cancel_password_prompt().as_deref(), Some("general"));
+        assert_eq!(state.cancel_password_prompt(), None);
+    }
+
+    #[test]
+    fn test_create_room_dialog_closes_on_the_new_room() {
+        let mut state = state();
+        state.open_create_room();
+        assert_eq!(state.focused_field, FocusedField::CreateRoomDialog);
+
+        let form = state.create_room.as_mut().unwrap();
+        form.name.set_text("rustaceans");
+        form.topic.set_text("All things Rust");
+        assert!(form.submit().is_some());
+
+        // Some other join doesn't answer the form
+        assert_eq!(state.finish_create_room("general"), None);
+        assert!(state.create_room.is_some());
+        assert_eq!(state.finish_create_room("rustaceans"), Some(vec!["/topic All things Rust".to_string()]));
+        assert!(state.create_room.is_none());
+        assert_eq!(state.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn test_help_gives_back_the_focus_it_took() {
+        let mut state = AppState::new();
+        state.input_mode = InputMode::Editing;
+        state.focused_field = FocusedField::Username;
+        state.toggle_help();
+        assert_eq!((&state.input_mode, &state.focused_field), (&InputMode::Normal, &FocusedField::Help));
+        state.toggle_help();
+        assert!(state.help.is_none());
+        assert_eq!((&state.input_mode, &state.focused_field), (&InputMode::Editing, &FocusedField::Username));
+    }
+
+    #[test]
+    fn test_palette_runs_actions_and_prefills_commands() {
+        let mut state = AppState::new();
+        state.open_command_palette();
+        for c in "invite".chars() {
+            state.command_palette.as_mut().unwrap().apply(EditCommand::Insert(c));
+        }
+        assert_eq!(state.run_palette_entry(), None);
+        assert_eq!(state.message_input.text(), "/invite ");
+        assert_eq!((&state.input_mode, &state.focused_field), (&InputMode::Editing, &FocusedField::MessageInput));
+
+        state.open_command_palette();
+        for c in "mentions".chars() {
+            state.command_palette.as_mut().unwrap().apply(EditCommand::Insert(c));
+        }
+        assert_eq!(state.run_palette_entry(), Some(Action::ToggleMentions));
+        assert!(state.command_palette.is_none());
+        assert_eq!(state.input_mode, InputMode::Normal);
+    }
+
+    #[test]
+    fn test_whispers_are_grouped_by_peer() {
+        let mut state = state();
+        state.add_message(whisper("bob", "alice", "hi"));
+        state.add_message(whisper("alice", "bob", "hey"));
+        state.add_message(whisper("carol", "alice", "yo"));
+
+        assert!(state.messages.is_empty());
+        assert_eq!(state.conversations.len(), 2);
+        assert_eq!(state.conversations[0].peer, "bob");
+        assert_eq!(state.conversations[0].messages.len(), 2);
+        // Our own echo doesn't count as unread
+        assert_eq!(state.conversations[0].unread, 1);
+
+        state.open_dm("bob");
+        assert_eq!(state.conversations[0].unread, 0);
+        state.add_message(whisper("bob", "alice", "still here?"));
+        assert_eq!(state.conversations[0].unread, 0);
+        assert_eq!(state.visible_messages().len(), 3);
+    }
+
+    #[test]
+    fn test_sidebar_cycles_rooms_then_dms() {
+        let mut state = state();
+        state.available_rooms = vec!["general".to_string(), "rust🔒".to_string()];
+        state.add_message(whisper("bob", "alice", "hi"));
+
+        assert_eq!(state.next_room(), Some("rust".to_string()));
+        assert_eq!(state.next_room(), None);
+        assert_eq!(state.active_dm.as_deref(), Some("bob"));
+        assert_eq!(state.outgoing_line().unwrap(), "/w bob ");
+
+        // An emote would reach the room, not bob
+        state.message_input.set_text("/me waves");
+        assert!(state.outgoing_line().is_err());
+        state.message_input.clear();
+
+        // Back to the room we are already in: no rejoin
+        assert_eq!(state.previous_room(), None);
+        assert_eq!(state.active_dm, None);
+        assert_eq!(state.current_room.as_deref(), Some("rust"));
+    }
+
+    #[test]
+    fn test_mention_inserted_at_cursor() {
+        let mut state = state();
+        state.insert_mention("bob");
+        assert_eq!(state.message_input.text(), "@bob ");
+
+        state.message_input.set_text("thanks");
+        state.insert_mention("bea");
+        assert_eq!(state.message_input.text(), "thanks @bea ");
+    }
+
+    #[test]
+    fn test_edit_and_delete_apply_in_place() {
+        let mut state = state();
+        for (id, text) in [(1, "first"), (2, "second")] {
+            let mut msg = ChatMessage::new(
+                text.to_string(),
+                "0.0.0.0:0".parse().unwrap(),
+                "bob".to_string(),
+                "general".to_string(),
+            );
+            msg.id = Some(id);
+            state.add_message(msg);
+        }
+
+        state.edit_message(1, "first!".to_string());
+        state.delete_message(2);
+        state.edit_message(99, "unknown".to_string());
+
+        assert_eq!(state.messages[0].content, "first!");
+        assert!(state.messages[0].edited);
+        assert!(state.messages[1].deleted);
+        assert_eq!(state.messages.len(), 2);
+    }
+
+    fn chat(id: u64, reply_to: Option<u64>) -> ChatMessage {
+        let mut msg = ChatMessage::new(
+            format!("message {}", id),
+            "0.0.0.0:0".parse().unwrap(),
+            "bob".to_string(),
+            "general".to_string(),
+        );
+        msg.id = Some(id);
+        msg.reply_to = reply_to;
+        msg
+    }
+
+    #[test]
+    fn test_reply_to_selected_message() {
+        let mut state = state();
+        state.add_message(chat(1, None));
+        state.add_message(chat(2, None));
+
+        state.select_previous();
+        state.select_previous();
+        assert_eq!(state.selected().and_then(|m| m.id), Some(1));
+        assert!(state.start_reply());
+
+        state.message_input.set_text("agreed");
+        assert_eq!(state.outgoing_line().unwrap(), "/reply 1 agreed");
+    }
+
+    #[test]
+    fn test_thread_collects_nested_replies() {
+        let mut state = state();
+        state.add_message(chat(1, None));
+        state.add_message(chat(2, None));
+        state.add_message(chat(3, Some(1)));
+        state.add_message(chat(4, Some(3)));
+        state.add_message(chat(5, Some(2)));
+
+        // Selecting a nested reply opens the whole thread
+        state.selected_message = Some(3);
+        state.toggle_thread();
+        assert_eq!(state.thread_root, Some(1));
+
+        let ids: Vec<u64> = state.thread_messages().iter().filter_map(|m| m.id).collect();
+        assert_eq!(ids, vec![1, 3, 4]);
+
+        state.toggle_thread();
+        assert_eq!(state.thread_root, None);
+
+        // A reply cycle still ends
+        state.add_message(chat(6, Some(7)));
+        state.add_message(chat(7, Some(6)));
+        state.selected_message = Some(5);
+        state.toggle_thread();
+        assert!(state.thread_root.is_some());
+        assert_eq!(state.thread_messages().len(), 2);
+    }
+
+    #[test]
+    fn test_reaction_deltas() {
+        let mut state = state();
+        state.add_message(chat(1, None));
+
+        state.apply_reaction(1, "👍", "bob", true);
+        state.apply_reaction(1, "👍", "bob", true);
+        state.apply_reaction(1, "👍", "carol", true);
+        assert_eq!(state.messages[0].reactions[0].users, vec!["bob", "carol"]);
+
+        state.apply_reaction(1, "👍", "bob", false);
+        state.apply_reaction(1, "👍", "carol", false);
+        assert!(state.messages[0].reactions.is_empty());
+    }
+
+    #[test]
+    fn test_mentions_are_collected() {
+        let mut state = state();
+        let mut mention = chat(1, None);
+        mention.mentions = vec!["alice".to_string()];
+        state.add_message(mention);
+        state.add_message(chat(2, None));
+
+        assert_eq!(state.messages.len(), 2);
+        assert_eq!(state.mentions.len(), 1);
+        // Seen as it arrived, nothing to ring about
+        assert!(!state.take_mention_alert());
+
+        let mut elsewhere = chat(3, None);
+        elsewhere.message_type = MessageType::Mention;
+        elsewhere.room = "rust".to_string();
+        state.add_message(elsewhere);
+        assert_eq!(state.messages.len(), 2);
+        assert!(state.take_mention_alert());
+        assert!(!state.take_mention_alert());
+
+        state.toggle_mentions();
+        assert_eq!(state.visible_messages().len(), 2);
+        assert_eq!(state.unseen_mentions, 0);
+
+        // Only the one from this room can be replied to
+        state.selected_message = Some(1);
+        assert_eq!(state.selected_id(), None);
+        assert!(!state.start_reply());
+        state.selected_message = Some(0);
+        assert_eq!(state.selected_id(), Some(1));
+    }
+
+    #[test]
+    fn test_mentions_are_capped() {
+        let mut state = state();
+        for id in 0..MAX_MENTIONS as u64 + 5 {
+            let mut mention = chat(id, None);
+            mention.message_type = MessageType::Mention;
+            state.add_message(mention);
+        }
+        assert_eq!(state.mentions.len(), MAX_MENTIONS);
+        assert_eq!(state.mentions[0].id, Some(5));
+    }
+
+    #[test]
+    fn test_mention_while_unfocused_alerts() {
+        let mut state = state();
+        state.set_terminal_focus(false);
+        let mut mention = chat(1, None);
+        mention.mentions = vec!["alice".to_string()];
+        state.add_message(mention);
+
+        assert!(state.take_mention_alert());
+        assert_eq!(state.unseen_mentions, 1);
+        state.set_terminal_focus(true);
+        assert_eq!(state.unseen_mentions, 0);
+    }
+
+    #[test]
+    fn test_history_divider_and_read_reports() {
+        let mut state = state();
+        let start = Instant::now();
+        state.load_history("general", (1..=5).map(|id| chat(id, None)).collect(), Some(3));
+
+        assert_eq!(state.first_unread(), Some(3));
+        assert!(state.scroll_to_unread);
+        // Scrolled up: the newest message isn't on screen yet
+        assert_eq!(state.read_update(start, false), None);
+
+        state.scroll_to_bottom();
+        assert_eq!(state.read_update(start, false), Some(("general".to_string(), 5)));
+        assert_eq!(state.read_update(start, false), None);
+
+        state.add_message(chat(6, None));
+        assert_eq!(state.read_update(start + Duration::from_secs(1), false), None);
+        assert_eq!(state.read_update(start + Duration::from_secs(1), true), Some(("general".to_string(), 6)));
+        // The divider stays where it was on entry
+        assert_eq!(state.first_unread(), Some(3));
+    }
+
+    #[test]
+    fn test_history_keeps_what_arrived_since_joining() {
+        let mut state = state();
+        state.add_message(chat(3, None));
+        state.add_message(chat(4, None));
+        state.load_history("general", (1..=3).map(|id| chat(id, None)).collect(), Some(3));
+
+        let ids: Vec<_> = state.messages.iter().map(|msg| msg.id).collect();
+        assert_eq!(ids, vec![Some(1), Some(2), Some(3), Some(4)]);
+        assert_eq!(state.first_unread(), Some(3));
+    }
+
+    #[test]
+    fn test_no_divider_when_caught_up() {
+        let mut state = state();
+        state.load_history("general", (1..=3).map(|id| chat(id, None)).collect(), Some(3));
+        assert_eq!(state.first_unread(), None);
+        assert!(!state.scroll_to_unread);
+        assert_eq!(state.read_update(Instant::now(), true), None);
+
+        // History of a room we already left is ignored
+        state.load_history("dev", vec![chat(9, None)], None);
+        assert_eq!(state.messages.len(), 3);
+    }
+
+    #[test]
+    fn test_scrolling_stops_at_the_oldest_row() {
+        let mut state = state();
+        state.message_viewport.set(MessageViewport { offset: 0, max_offset: 25, height: 10 });
+
+        state.scroll_up();
+        state.page_up();
+        assert_eq!(state.scroll_offset, 10);
+        state.page_up();
+        state.page_up();
+        assert_eq!(state.scroll_offset, 25);
+        state.page_down();
+        assert_eq!(state.scroll_offset, 16);
+        state.scroll_to_bottom();
+        state.scroll_down();
+        assert_eq!(state.scroll_offset, 0);
+        state.scroll_to_top();
+        assert_eq!(state.scroll_offset, 25);
+
+        // From the unread position, scrolling starts where the view was drawn
+        state.scroll_to_unread = true;
+        state.message_viewport.set(MessageViewport { offset: 7, max_offset: 25, height: 10 });
+        state.scroll_down();
+        assert_eq!(state.scroll_offset, 6);
+        assert!(!state.scroll_to_unread);
+    }
+
+    #[test]
+    fn test_history_search_takes_or_restores() {
+        let mut state = state();
+        state.input_history.push("/join dev");
+        state.input_history.push("hello");
+        state.message_input.set_text("draft");
+
+        state.search_history();
+        state.edit_history_search(EditCommand::Insert('j'));
+        assert_eq!(state.history_match(), Some("/join dev"));
+        state.cancel_history_search();
+        assert_eq!(state.message_input.text(), "draft");
+
+        state.search_history();
+        state.edit_history_search(EditCommand::Insert('l'));
+        assert_eq!(state.history_match(), Some("hello"));
+        // Moving the cursor takes the match and keeps editing it
+        state.edit_history_search(EditCommand::Home);
+        assert!(state.history_search.is_none());
+        assert_eq!(state.message_input.text(), "hello");
+        assert_eq!(state.message_input.cursor(), 0);
+    }
+
+    #[test]
+    fn test_up_moves_through_lines_before_history() {
+        let mut state = state();
+        state.input_history.push("sent");
+        state.message_input.set_text("line one\nline two");
+
+        state.history_previous();
+        assert_eq!(state.message_input.text(), "line one\nline two");
+        assert_eq!(state.message_input.cursor_row(), 0);
+        state.history_previous();
+        assert_eq!(state.message_input.text(), "sent");
+        state.history_next();
+        assert_eq!(state.message_input.text(), "line one\nline two");
+    }
+
+    #[test]
+    fn test_typing_frames_are_throttled() {
+        let mut state = state();
+        let start = Instant::now();
+
+        state.message_input.set_text("h");
+        assert_eq!(state.typing_update(start), Some(true));
+        state.message_input.set_text("he");
+        assert_eq!(state.typing_update(start + Duration::from_secs(1)), None);
+        assert_eq!(state.typing_update(start + TYPING_REFRESH), Some(true));
+
+        state.message_input.clear();
+        assert_eq!(state.typing_update(start + TYPING_REFRESH), Some(false));
+        assert_eq!(state.typing_update(start + TYPING_REFRESH), None);
+
+        // Commands are not announced
+        state.message_input.set_text("/nick");
+        assert_eq!(state.typing_update(start), None);
+
+        // Leaving the room with a draft stops the notice once
+        state.message_input.set_text("draft");
+        assert_eq!(state.typing_update(start), Some(true));
+        assert!(state.end_typing());
+        assert!(!state.end_typing());
+    }
+
+    #[test]
+    fn test_typing_notices_expire() {
+        let mut state = state();
+        let start = Instant::now();
+        state.set_typing("bob".to_string(), true, start);
+        state.set_typing("carol".to_string(), true, start + Duration::from_secs(4));
+
+        assert_eq!(state.typing_names(start + Duration::from_secs(5)), vec!["bob", "carol"]);
+        assert_eq!(state.typing_names(start + TYPING_TIMEOUT), vec!["carol"]);
+        assert!(state.expire_typing(start + TYPING_TIMEOUT));
+        assert!(!state.expire_typing(start + TYPING_TIMEOUT));
+        assert_eq!(state.typing_users.len(), 1);
+
+        state.add_message(ChatMessage::new(
+            "done".to_string(),
+            "0.0.0.0:0".parse().unwrap(),
+            "carol".to_string(),
+            "general".to_string(),
+        ));
+        assert!(state.typing_names(start + TYPING_TIMEOUT).is_empty());
+    }
+
+    #[test]
+    fn test_dm_follows_nick_change() {
+        let mut state = state();
+        state.add_message(whisper("bob", "alice", "hi"));
+        state.open_dm("bob");
+        state.rename_peer("bob", "robert");
+
+        assert_eq!(state.active_dm.as_deref(), Some("robert"));
+        state.message_input.set_text("/nick al");
+        assert_eq!(state.outgoing_line().unwrap(), "/nick al");
+    }
+
+    #[test]
+    fn test_completion_cycles_and_cancels() {
+        let mut state = state();
+        state.users_in_room = vec!["bob".to_string(), "bea".to_string()];
+        state.message_input.set_text("thanks b");
+
+        state.complete(true);
+        assert_eq!(state.message_input.text(), "thanks bob ");
+        state.complete(true);
+        assert_eq!(state.message_input.text(), "thanks bea ");
+        state.cancel_completion();
+        assert_eq!(state.message_input.text(), "thanks b");
+
+        // A single candidate is taken without a popup
+        state.message_input.set_text("/joi");
+        state.complete(true);
+        assert_eq!(state.message_input.text(), "/join ");
+        assert!(state.completion.is_none());
+    }
+}
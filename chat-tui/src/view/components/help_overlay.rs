@@ -0,0 +1,127 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+use unicode_width::UnicodeWidthStr;
+use crate::input::keymap::KeySection;
+
+const WIDTH: u16 = 76;
+
+/// Every key that works where the overlay was opened, by section
+pub struct HelpOverlay<'a> {
+    sections: &'a [&'static KeySection],
+    scroll: u16,
+}
+
+impl<'a> HelpOverlay<'a> {
+    pub fn new(sections: &'a [&'static KeySection]) -> Self {
+        Self { sections, scroll: 0 }
+    }
+
+    /// Rows scrolled down, kept so the last row stays on screen
+    pub fn scroll(mut self, scroll: u16) -> Self {
+        self.scroll = scroll;
+        self
+    }
+
+    /// Where to draw the overlay: centered in `bounds`, as tall as its keys
+    pub fn area(&self, bounds: Rect) -> Rect {
+        let width = WIDTH.min(bounds.width);
+        // Borders and the footer
+        let height = (self.lines().len() as u16 + 3).min(bounds.height);
+        Rect::new(
+            bounds.x + (bounds.width - width) / 2,
+            bounds.y + (bounds.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    fn lines(&self) -> Vec<Line<'static>> {
+        let keys_width = self.sections.iter()
+            .flat_map(|section| section.bindings)
+            .map(|binding| binding.keys.width())
+            .max()
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        for section in self.sections {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(Line::styled(section.title, Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)));
+            for binding in section.bindings {
+                let padding = " ".repeat(keys_width - binding.keys.width());
+                lines.push(Line::from(vec![
+                    Span::styled(format!("  {}{}", binding.keys, padding), Style::default().fg(Color::Cyan)),
+                    Span::raw(format!("  {}", binding.description)),
+                ]));
+            }
+        }
+        lines
+    }
+}
+
+impl<'a> Widget for HelpOverlay<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(" Help ");
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),     // Keys
+                Constraint::Length(1),  // Footer
+            ]).split(inner);
+
+        let lines = self.lines();
+        let max_scroll = (lines.len() as u16).saturating_sub(chunks[0].height);
+        Paragraph::new(lines)
+            .scroll((self.scroll.min(max_scroll), 0))
+            .render(chunks[0], buf);
+        let footer = if max_scroll > 0 { "↑/↓ scroll · Esc close" } else { "Esc close" };
+        Paragraph::new(footer)
+            .style(Style::default().fg(Color::DarkGray))
+            .render(chunks[1], buf);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::keymap::{CREATE_ROOM, GLOBAL};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    #[test]
+    fn test_keys_line_up_and_scroll() {
+        let sections = [&CREATE_ROOM, &GLOBAL];
+        let overlay = HelpOverlay::new(&sections);
+        let area = overlay.area(Rect::new(0, 0, 80, 40));
+        // Two titles, a blank line and seven keys inside borders and the footer
+        assert_eq!(area, Rect::new(2, 13, 76, 13));
+
+        let mut terminal = Terminal::new(TestBackend::new(42, 6)).unwrap();
+        terminal.draw(|frame| frame.render_widget(HelpOverlay::new(&sections).scroll(99), frame.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: Vec<String> = buffer.content()
+            .chunks(42)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect();
+        // Scrolled as far as it goes: the last keys fill the view
+        assert_eq!(screen[1], "│Anywhere                                │");
+        assert_eq!(screen[2], "│  F1             Show or hide this help │");
+        assert_eq!(screen[3], "│  Ctrl+C         Quit                   │");
+        assert_eq!(screen[4], "│↑/↓ scroll · Esc close                  │");
+    }
+}
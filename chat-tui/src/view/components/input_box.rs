@@ -1,21 +1,37 @@
 use ratatui::{
     buffer::Buffer,
-    layout::Rect,
+    layout::{Position, Rect},
     style::{Color, Modifier, Style},
-    text::{Line, Span},
+    text::Line,
     widgets::{Block, Borders, Paragraph, Widget},
 };
+use crate::state::line_editor::LineEditor;
+
+/// Draws the editor's text inside `inner`, scrolled to keep the cursor in view,
+/// with the cursor cell highlighted when `editing`
+pub fn render_editor(editor: &LineEditor, inner: Rect, editing: bool, buf: &mut Buffer) {
+    let (lines, row, column) = editor.visible_lines(inner.width as usize, inner.height as usize);
+    let text: Vec<Line> = lines.into_iter().map(Line::from).collect();
+    Paragraph::new(text).render(inner, buf);
+
+    if editing && inner.height > 0 {
+        let position = Position::new(inner.x + column as u16, inner.y + row as u16);
+        if inner.contains(position) {
+            buf[position].set_style(Style::default().add_modifier(Modifier::REVERSED));
+        }
+    }
+}
 
 pub struct InputBox<'a> {
     title: &'a str,
-    content: &'a str,
+    content: &'a LineEditor,
     is_focused: bool,
     is_editing: bool,
 }
 
 
 impl<'a> InputBox<'a> {
-    pub fn new(title: &'a str, content: &'a str) -> Self {
+    pub fn new(title: &'a str, content: &'a LineEditor) -> Self {
         Self {
             title,
             content,
@@ -56,17 +72,8 @@ impl<'a> Widget for InputBox<'a> {
             .border_style(Style::default().fg(border_color).add_modifier(border_styles))
             .title(self.title);
 
-        let text = if self.is_editing {
-            Line::from(vec![
-                Span::raw(self.content),
-                Span::styled("█", Style::default().fg(Color::Green))
-            ])
-        } else {
-            Line::from(self.content)
-        };
-
-        let paragraph = Paragraph::new(text).block(block);
-
-        paragraph.render(area, buf)
+        let inner = block.inner(area);
+        block.render(area, buf);
+        render_editor(self.content, inner, self.is_editing, buf);
     }
 }
\ No newline at end of file
@@ -1 +1,3 @@
-pub mod input_box;
\ No newline at end of file
+pub mod help_overlay;
+pub mod input_box;
+pub mod wrap;
@@ -0,0 +1,148 @@
+use ratatui::{
+    style::Style,
+    text::{Line, Span},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Columns between tab stops
+const TAB_WIDTH: usize = 4;
+const TAB_SPACES: &str = "    ";
+
+/// Lays `body` out after `prefix` in rows at most `width` columns wide,
+/// breaking between words where it can and at every line break of the body.
+/// Rows after the first start under the body (a hanging indent), unless the
+/// prefix takes more than half the row. Tabs become spaces up to the next
+/// tab stop, counted from where the body starts.
+pub fn wrap(prefix: &[Span], body: &[Span], width: usize) -> Vec<Line<'static>> {
+    let width = width.max(1);
+    let prefix_width: usize = prefix.iter().map(Span::width).sum();
+    let indent = if prefix_width * 2 <= width { prefix_width } else { 0 };
+
+    let cells = prefix.iter().map(|span| (span, false))
+        .chain(body.iter().map(|span| (span, true)))
+        .flat_map(|(span, in_body)| {
+            span.content.graphemes(true).map(move |g| (g, span.style, in_body))
+        });
+
+    let mut rows = Vec::new();
+    let mut row: Vec<(&str, Style)> = Vec::new();
+    let mut row_start = 0;
+    let mut column: usize = 0;
+    // Where the row can be broken: just after the last space of the body
+    let mut break_at = None;
+
+    for (grapheme, style, in_body) in cells {
+        let grapheme = if grapheme == "\t" {
+            let body_start = if rows.is_empty() { prefix_width } else { row_start };
+            &TAB_SPACES[..TAB_WIDTH - column.saturating_sub(body_start) % TAB_WIDTH]
+        } else {
+            grapheme
+        };
+        let grapheme_width = grapheme.width();
+        let is_space = grapheme.trim().is_empty();
+        let line_break = grapheme == "\n";
+
+        if line_break || (column + grapheme_width > width && column > row_start) {
+            // The unfinished word moves down when it fits on the next row
+            let carried = match break_at {
+                Some(at) if !is_space && !line_break && indent + cells_width(&row[at..]) < width => row.split_off(at),
+                _ => Vec::new(),
+            };
+            while row.len() > row_start && row.last().is_some_and(|(g, _)| g.trim().is_empty()) {
+                row.pop();
+            }
+            rows.push(std::mem::take(&mut row));
+            row.extend(std::iter::repeat_n((" ", Style::default()), indent));
+            row_start = indent;
+            column = indent + cells_width(&carried);
+            row.extend(carried);
+            break_at = None;
+            if is_space {
+                continue;
+            }
+        }
+
+        row.push((grapheme, style));
+        column += grapheme_width;
+        if in_body && is_space {
+            break_at = Some(row.len());
+        }
+    }
+    rows.push(row);
+
+    rows.into_iter().map(to_line).collect()
+}
+
+fn cells_width(cells: &[(&str, Style)]) -> usize {
+    cells.iter().map(|(g, _)| g.width()).sum()
+}
+
+/// Joins neighbouring graphemes of the same style back into spans
+fn to_line(cells: Vec<(&str, Style)>) -> Line<'static> {
+    let mut spans: Vec<Span<'static>> = Vec::new();
+    for (grapheme, style) in cells {
+        match spans.last_mut() {
+            Some(last) if last.style == style => last.content.to_mut().push_str(grapheme),
+            _ => spans.push(Span::styled(grapheme.to_string(), style)),
+        }
+    }
+    Line::from(spans)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(prefix: &str, body: &str, width: usize) -> Vec<String> {
+        wrap(&[Span::raw(prefix)], &[Span::raw(body)], width)
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_breaks_between_words_with_hanging_indent() {
+        assert_eq!(rows("bob: ", "the quick brown fox jumps", 16), vec![
+            "bob: the quick",
+            "     brown fox",
+            "     jumps",
+        ]);
+        assert_eq!(rows("bob: ", "short", 16), vec!["bob: short"]);
+    }
+
+    #[test]
+    fn test_line_breaks_start_indented_rows() {
+        assert_eq!(rows("al: ", "fn main() {\n    run();\n}", 40), vec![
+            "al: fn main() {",
+            "        run();",
+            "    }",
+        ]);
+    }
+
+    #[test]
+    fn test_long_words_are_cut() {
+        assert_eq!(rows("> ", "abcdefghijkl", 6), vec!["> abcd", "  efgh", "  ijkl"]);
+        // Wide characters are never split across rows
+        assert_eq!(rows("", "日本語です", 5), vec!["日本", "語で", "す"]);
+    }
+
+    #[test]
+    fn test_tabs_align_to_stops() {
+        assert_eq!(rows("> ", "a\tbc\td\n\tx", 20), vec!["> a   bc  d", "      x"]);
+    }
+
+    #[test]
+    fn test_wide_prefix_drops_the_indent() {
+        assert_eq!(rows("a long prefix: ", "one two", 20), vec!["a long prefix: one", "two"]);
+    }
+
+    #[test]
+    fn test_styles_are_kept() {
+        let bold = Style::default().add_modifier(ratatui::style::Modifier::BOLD);
+        let lines = wrap(&[Span::styled("al: ", bold)], &[Span::raw("hi there")], 9);
+        assert_eq!(lines[0].spans, vec![Span::styled("al: ", bold), Span::raw("hi")]);
+        assert_eq!(lines[1].spans, vec![Span::raw("    there")]);
+    }
+}
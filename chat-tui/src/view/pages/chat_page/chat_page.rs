@@ -3,8 +3,10 @@ use ratatui::{
     buffer::Buffer,
     widgets::Widget,
 };
-use crate::state::state::AppState;
-use super::components::{Header, MessageInputBox, MessageList, RoomList, UserList, HelpBar};
+use std::time::Instant;
+use crate::state::state::{AppState, ChatLayout, FocusedField};
+use super::components::{Header, MessageInputBox, MessageList, RoomList, UserList, HelpBar, TypingIndicator, CompletionPopup, PasswordDialog, CreateRoomDialog, PaletteDialog};
+use unicode_width::UnicodeWidthStr;
 pub struct ChatPage<'a> {
     state: &'a AppState
 }
@@ -14,8 +16,16 @@ impl<'a> ChatPage<'a> {
         Self { state }
     }
 
+    /// Remembers where a pane is drawn for mouse hit-testing
+    fn record_layout(&self, update: impl FnOnce(&mut ChatLayout)) {
+        let mut layout = self.state.chat_layout.get();
+        update(&mut layout);
+        self.state.chat_layout.set(layout);
+    }
+
     fn render_helper(&self, area: Rect, buf: &mut Buffer) {
-        let help_bar = HelpBar::new(&self.state.input_mode);
+        let selecting = self.state.focused_field == FocusedField::MessageList;
+        let help_bar = HelpBar::new(&self.state.input_mode).selecting(selecting);
         help_bar.render(area, buf)
     }
 
@@ -26,25 +36,27 @@ impl<'a> ChatPage<'a> {
             &self.state.server_address,
             current_room,
             &self.state.connection_status
-        );
+        ).topic(self.state.room_topic.as_deref())
+            .direct(self.state.active_dm.as_deref())
+            .mentions(self.state.show_mentions);
 
         header.render(area, buf);
     }
 
     fn render_rooms(&self, area: Rect, buf: &mut Buffer) {
+        self.record_layout(|layout| layout.rooms = area);
         let current_room = self.state.current_room.as_deref();
-        let room_list = RoomList::new(&self.state.available_rooms, current_room).focused(false);
+        let room_list = RoomList::new(&self.state.available_rooms, current_room)
+            .direct_messages(&self.state.conversations, self.state.active_dm.as_deref())
+            .focused(false);
         room_list.render(area, buf);
     }
 
     fn render_users(&self, area: Rect, buf: &mut Buffer) {
-        let mut all_users = vec![self.state.username.clone()];
-        for user in &self.state.users_in_room {
-            if user != &self.state.username {
-                all_users.push(user.clone());
-            }
-        }
-        let user_list = UserList::new(&all_users, &self.state.username);
+        self.record_layout(|layout| layout.users = area);
+        let all_users: Vec<String> = self.state.listed_users().into_iter().map(str::to_string).collect();
+        let user_list = UserList::new(&all_users, &self.state.username)
+            .presence(&self.state.presence);
         user_list.render(area, buf);
     }
 
@@ -62,35 +74,95 @@ impl<'a> ChatPage<'a> {
 
     fn render_messages(&self, area: Rect, buf: &mut Buffer) {
         let message_list = MessageList::new(
-            &self.state.messages,
+            self.state.visible_messages(),
             &self.state.username,
             self.state.scroll_offset,
-        );
+        ).selected(self.state.selected_message)
+            // Scrolling needs the rows as drawn at this width
+            .report_viewport(&self.state.message_viewport);
+        // The divider belongs to the room view
+        let message_list = if self.state.active_dm.is_none() && !self.state.show_mentions {
+            message_list.divider(self.state.first_unread())
+                .scroll_to_divider(self.state.scroll_to_unread)
+        } else {
+            message_list
+        };
+        self.record_layout(|layout| layout.messages = area);
         message_list.render(area, buf)
     }
 
-    fn render_message_input(&self, area: Rect, buf: &mut Buffer) {
+    fn render_thread(&self, area: Rect, buf: &mut Buffer) {
+        let Some(root) = self.state.thread_root else {
+            return;
+        };
+        let thread = self.state.thread_messages();
+        let thread_list = MessageList::new(&thread, &self.state.username, 0)
+            .title(format!("Thread #{} [t to close]", root));
+        thread_list.render(area, buf)
+    }
+
+    fn message_input(&self) -> MessageInputBox<'a> {
         let can_send = self.state.can_send_message();
-        let message_input = MessageInputBox::new(
+        MessageInputBox::new(
             &self.state.message_input,
             &self.state.input_mode,
             can_send,
-        );
-        message_input.render(area, buf);
+        ).recipient(self.state.active_dm.as_deref())
+            .replying_to(self.state.reply_to)
+            .history_search(self.state.history_search.as_ref()
+                .map(|search| (search.query.as_str(), self.state.history_match())))
+    }
+
+    /// Candidates of a Tab completion, above the word being completed
+    fn render_completion(&self, input: Rect, bounds: Rect, buf: &mut Buffer) {
+        let Some(completion) = &self.state.completion else {
+            return;
+        };
+        let popup = CompletionPopup::new(&completion.candidates, completion.index);
+        let (_, cursor) = self.state.message_input.visible(input.width.saturating_sub(2) as usize);
+        // Column of the word start inside the bordered box
+        let word = self.state.message_input[completion.start..self.state.message_input.cursor()].width();
+        let column = 1 + cursor.saturating_sub(word) as u16;
+        let area = popup.area(input, column, bounds);
+        popup.render(area, buf);
     }
 
     pub fn render_chat_area(&self, area: Rect, buf: &mut Buffer) {
+        // Typing notices only concern the room view
+        let typing = if self.state.active_dm.is_none() {
+            self.state.typing_names(Instant::now())
+        } else {
+            Vec::new()
+        };
+        let typing_height = if typing.is_empty() { 0 } else { 1 };
+        let message_input = self.message_input();
+
         let chat_chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Min(0),
+                Constraint::Length(typing_height),  // Typing
+                Constraint::Length(message_input.height()),  // Input
                 Constraint::Length(3),
-                Constraint::Length(3),          // Input
             ]).split(area);
 
-        self.render_messages(chat_chunks[0], buf);
-        self.render_message_input(chat_chunks[1], buf);
-        self.render_helper(chat_chunks[2], buf);
+        if self.state.thread_root.is_some() {
+            let thread_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(60),
+                    Constraint::Percentage(40),     // Thread
+                ]).split(chat_chunks[0]);
+            self.render_messages(thread_chunks[0], buf);
+            self.render_thread(thread_chunks[1], buf);
+        } else {
+            self.render_messages(chat_chunks[0], buf);
+        }
+        TypingIndicator::new(&typing).render(chat_chunks[1], buf);
+        self.record_layout(|layout| layout.input = chat_chunks[2]);
+        message_input.render(chat_chunks[2], buf);
+        self.render_helper(chat_chunks[3], buf);
+        self.render_completion(chat_chunks[2], area, buf);
     }
 
 }
@@ -124,5 +196,15 @@ impl<'a> Widget for ChatPage<'a> {
 
         // Renderiza área do chat
         self.render_chat_area(content_chunks[1], buf);
+
+        if let Some(form) = &self.state.create_room {
+            CreateRoomDialog::new(form).render(CreateRoomDialog::area(area), buf);
+        }
+        if let Some(prompt) = &self.state.password_prompt {
+            PasswordDialog::new(prompt).render(PasswordDialog::area(area), buf);
+        }
+        if let Some(palette) = &self.state.command_palette {
+            PaletteDialog::new(palette).render(PaletteDialog::area(area), buf);
+        }
     }
 }
\ No newline at end of file
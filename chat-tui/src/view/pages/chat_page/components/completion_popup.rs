@@ -0,0 +1,59 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, Widget},
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Candidates shown at once; the list scrolls with the selection
+const MAX_ROWS: usize = 6;
+
+pub struct CompletionPopup<'a> {
+    candidates: &'a [String],
+    selected: usize,
+}
+
+impl<'a> CompletionPopup<'a> {
+    pub fn new(candidates: &'a [String], selected: usize) -> Self {
+        Self { candidates, selected }
+    }
+
+    /// Where to draw the popup: just above `input`, starting at `column`
+    /// inside it and kept within `bounds`
+    pub fn area(&self, input: Rect, column: u16, bounds: Rect) -> Rect {
+        let widest = self.candidates.iter().map(|c| c.trim_end().width()).max().unwrap_or(0);
+        let width = (widest as u16 + 2).min(bounds.width);
+        let height = (self.candidates.len().min(MAX_ROWS) as u16 + 2).min(input.y.saturating_sub(bounds.y));
+        let x = (input.x + column).min(bounds.right().saturating_sub(width));
+        Rect::new(x, input.y.saturating_sub(height), width, height)
+    }
+}
+
+impl<'a> Widget for CompletionPopup<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let rows = area.height.saturating_sub(2) as usize;
+        let first = (self.selected + 1).saturating_sub(rows);
+        let items: Vec<ListItem> = self.candidates.iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .map(|(idx, candidate)| {
+                let item = ListItem::new(candidate.trim_end());
+                if idx == self.selected {
+                    item.style(Style::default().fg(Color::Black).bg(Color::Cyan).add_modifier(Modifier::BOLD))
+                } else {
+                    item
+                }
+            })
+            .collect();
+
+        Clear.render(area, buf);
+        List::new(items)
+            .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Cyan)))
+            .render(area, buf);
+    }
+}
@@ -0,0 +1,141 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+use crate::state::create_room_form::{CreateRoomField, CreateRoomForm};
+use crate::view::components::input_box::InputBox;
+
+const WIDTH: u16 = 50;
+const HEIGHT: u16 = 14;
+
+/// Form for a new room: name, optional password, slow mode and topic,
+/// drawn over the chat page
+pub struct CreateRoomDialog<'a> {
+    form: &'a CreateRoomForm,
+}
+
+impl<'a> CreateRoomDialog<'a> {
+    pub fn new(form: &'a CreateRoomForm) -> Self {
+        Self { form }
+    }
+
+    /// Where to draw the dialog: centered in `bounds`
+    pub fn area(bounds: Rect) -> Rect {
+        let width = WIDTH.min(bounds.width);
+        let height = HEIGHT.min(bounds.height);
+        Rect::new(
+            bounds.x + (bounds.width - width) / 2,
+            bounds.y + (bounds.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    fn mode_line(&self) -> Line<'static> {
+        let mode = match self.form.slow_mode() {
+            Some(seconds) => format!("one message every {}s", seconds),
+            None => "off".to_string(),
+        };
+        let style = if self.form.focus == CreateRoomField::Mode {
+            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        Line::from(vec![
+            Span::raw(" Slow mode: "),
+            Span::styled(format!("◀ {} ▶", mode), style),
+        ])
+    }
+
+    fn status_line(&self) -> Line<'static> {
+        match (&self.form.error, self.form.pending) {
+            (Some(error), _) => Line::styled(format!(" {}", error), Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            (None, true) => Line::styled(" Creating…", Style::default().fg(Color::Yellow)),
+            (None, false) => Line::default(),
+        }
+    }
+}
+
+impl<'a> Widget for CreateRoomDialog<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(" New room ");
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),  // Name
+                Constraint::Length(3),  // Password
+                Constraint::Length(1),  // Mode
+                Constraint::Length(3),  // Topic
+                Constraint::Length(1),  // Error or progress
+                Constraint::Length(1),  // Keys
+            ]).split(inner);
+
+        let focus = self.form.focus;
+        InputBox::new("Name", &self.form.name)
+            .editing(focus == CreateRoomField::Name)
+            .render(chunks[0], buf);
+        let masked = self.form.password.masked('•');
+        InputBox::new("Password (optional)", &masked)
+            .editing(focus == CreateRoomField::Password)
+            .render(chunks[1], buf);
+        Paragraph::new(self.mode_line()).render(chunks[2], buf);
+        InputBox::new("Topic (optional)", &self.form.topic)
+            .editing(focus == CreateRoomField::Topic)
+            .render(chunks[3], buf);
+        Paragraph::new(self.status_line()).render(chunks[4], buf);
+        Paragraph::new(" Tab next · ←/→ mode · Enter create · Esc cancel")
+            .style(Style::default().fg(Color::DarkGray))
+            .render(chunks[5], buf);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::line_editor::LineEditor;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn draw(form: &CreateRoomForm) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+        terminal.draw(|frame| frame.render_widget(CreateRoomDialog::new(form), frame.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content()
+            .chunks(WIDTH as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_form_is_drawn_with_its_status() {
+        let mut form = CreateRoomForm::new();
+        form.name = LineEditor::from("den");
+        form.password = LineEditor::from("hunter2");
+        form.mode = 1;
+        assert!(form.submit().is_some());
+
+        let screen = draw(&form);
+        assert_eq!(screen[2], "││den                                           ││");
+        assert_eq!(screen[5], "││•••••••                                       ││");
+        assert_eq!(screen[7], "│ Slow mode: ◀ one message every 10s ▶           │");
+        assert_eq!(screen[11], "│ Creating…                                      │");
+        assert!(!screen.concat().contains("hunter2"));
+
+        // A refusal takes the place of the progress line
+        form.refused("You are sending messages too fast.".to_string());
+        assert_eq!(draw(&form)[11], "│ You are sending messages too fast.             │");
+    }
+}
@@ -11,6 +11,9 @@ pub struct Header<'a> {
     username: &'a str,
     server_address: &'a str,
     current_room: Option<&'a str>,
+    topic: Option<&'a str>,
+    direct_peer: Option<&'a str>,
+    showing_mentions: bool,
     connection_status: &'a ConnectionStatus
 }
 
@@ -25,9 +28,27 @@ impl<'a> Header<'a> {
             username,
             server_address,
             current_room,
+            topic: None,
+            direct_peer: None,
+            showing_mentions: false,
             connection_status,
         }
     }
+
+    pub fn mentions(mut self, showing: bool) -> Self {
+        self.showing_mentions = showing;
+        self
+    }
+
+    pub fn topic(mut self, topic: Option<&'a str>) -> Self {
+        self.topic = topic;
+        self
+    }
+
+    pub fn direct(mut self, peer: Option<&'a str>) -> Self {
+        self.direct_peer = peer;
+        self
+    }
 }
 
 impl<'a> Widget for Header<'a> {
@@ -41,8 +62,15 @@ impl<'a> Widget for Header<'a> {
             ConnectionStatus::Disconnected => ("○", Color::Red),
             ConnectionStatus::Error(_) => ("✖", Color::Red),
         };
-        let room_text = if let Some(room) = self.current_room {
-            format!(" | Room {}", room)
+        let room_text = if self.showing_mentions {
+            String::from(" | Mentions")
+        } else if let Some(peer) = self.direct_peer {
+            format!(" | DM with @{}", peer)
+        } else if let Some(room) = self.current_room {
+            match self.topic {
+                Some(topic) => format!(" | Room {} — {}", room, topic),
+                None => format!(" | Room {}", room),
+            }
         } else {
             String::from(" | No Room")
         };
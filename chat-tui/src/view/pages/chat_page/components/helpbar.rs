@@ -1,8 +1,6 @@
 use ratatui::{
     buffer::Buffer,
     layout::{Alignment, Rect},
-    style::{Color, Style},
-    text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Widget},
 };
 
@@ -10,11 +8,18 @@ use crate::state::state::InputMode;
 
 pub struct HelpBar<'a>{
     input_mode: &'a InputMode,
+    selecting: bool,
 }
 
 impl<'a> HelpBar<'a> {
     pub fn new(input_mode: &'a InputMode) -> Self {
-        Self { input_mode }
+        Self { input_mode, selecting: false }
+    }
+
+    /// The message list has focus
+    pub fn selecting(mut self, selecting: bool) -> Self {
+        self.selecting = selecting;
+        self
     }
 }
 
@@ -24,11 +29,14 @@ impl<'a> Widget for HelpBar<'a> {
         Self: Sized,
     {
         let help_message = match self.input_mode {
+            InputMode::Normal if self.selecting => {
+                "↑↓:select | r:reply | t:thread | 1-6:react | Tab/Esc:back | ?:help | q:quit"
+            }
             InputMode::Normal => {
-                "i:edit | n:next room | p:prev room | ↑↓:scroll | q:quit"
+                "i:edit | Tab:select | n:next | p:prev | c:new room | x:close DM | m:mentions | ↑↓/PgUp/PgDn:scroll | Ctrl+P:commands | ?:help | q:quit"
             }
             InputMode::Editing => {
-                "Esc:normal | Enter:send"
+                "Esc:normal | Enter:send | Alt+Enter:new line | Tab:complete | ↑↓:history | Ctrl+R:search | Ctrl+P:commands | F1:help"
             }
         };
 
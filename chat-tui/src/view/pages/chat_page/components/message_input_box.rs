@@ -2,20 +2,28 @@ use ratatui::{
     buffer::Buffer,
     layout::Rect,
     style::{Color, Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Widget},
+    widgets::{Block, Borders, Widget},
 };
+use crate::state::line_editor::LineEditor;
 use crate::state::state::InputMode;
+use crate::view::components::input_box::render_editor;
+
+/// Lines the box grows to before it scrolls
+const MAX_VISIBLE_LINES: usize = 6;
 
 pub struct MessageInputBox<'a> {
-    content: &'a str,
+    content: &'a LineEditor,
     input_mode: &'a InputMode,
     can_send: bool,
+    recipient: Option<&'a str>,
+    reply_to: Option<u64>,
+    /// Query and match of a Ctrl+R search
+    search: Option<(&'a str, Option<&'a str>)>,
 }
 
 impl<'a> MessageInputBox<'a> {
     pub fn new(
-        content: &'a str,
+        content: &'a LineEditor,
         input_mode: &'a InputMode,
         can_send: bool,
     ) -> Self {
@@ -23,8 +31,36 @@ impl<'a> MessageInputBox<'a> {
             content,
             input_mode,
             can_send,
+            recipient: None,
+            reply_to: None,
+            search: None,
         }
     }
+
+    /// Peer of the DM pane, plain text is whispered to them
+    pub fn recipient(mut self, recipient: Option<&'a str>) -> Self {
+        self.recipient = recipient;
+        self
+    }
+
+    pub fn history_search(mut self, search: Option<(&'a str, Option<&'a str>)>) -> Self {
+        self.search = search;
+        self
+    }
+
+    pub fn replying_to(mut self, reply_to: Option<u64>) -> Self {
+        self.reply_to = reply_to;
+        self
+    }
+
+    /// Rows needed, borders included, growing with the lines of the message
+    pub fn height(&self) -> u16 {
+        let lines = match self.search {
+            Some((_, found)) => found.unwrap_or_default().split('\n').count(),
+            None => self.content.line_count(),
+        };
+        lines.min(MAX_VISIBLE_LINES) as u16 + 2
+    }
 }
 
 impl<'a> Widget for MessageInputBox<'a> {
@@ -32,24 +68,26 @@ impl<'a> Widget for MessageInputBox<'a> {
         let is_editing = matches!(self.input_mode, InputMode::Editing);
 
         let (border_color, title) = if is_editing {
-            (Color::Green, "Message [ESC to exit, ENTER to send]")
+            (Color::Green, "Message [ESC to exit, ENTER to send, Alt+ENTER new line]")
         } else {
             (Color::Yellow, "Message [i to edit, q to quit]")
         };
 
-        let text = if is_editing {
-            Line::from(vec![
-                Span::raw(self.content),
-                Span::styled("█", Style::default().fg(Color::Green)),
-            ])
-        } else {
-            Line::from(self.content)
+        let title = match (self.search, self.recipient, self.reply_to) {
+            (Some((query, found)), _, _) => format!(
+                "History search: '{}'{} [Ctrl+R older, Enter take, ESC cancel]",
+                query,
+                if found.is_none() && !query.is_empty() { " (no match)" } else { "" }
+            ),
+            (None, Some(peer), _) => format!("@{} {}", peer, title),
+            (None, None, Some(parent)) => format!("Reply to #{} {}", parent, title),
+            (None, None, None) => title.to_string(),
         };
 
         let title_with_status = if self.can_send && is_editing {
             format!("{} ✓  ", title)
         } else {
-            title.to_string()
+            title
         };
 
         let block = Block::default()
@@ -61,8 +99,14 @@ impl<'a> Widget for MessageInputBox<'a> {
             )
             .title(title_with_status);
 
-        let paragraph = Paragraph::new(text).block(block);
-
-        paragraph.render(area, buf);
+        let inner = block.inner(area);
+        block.render(area, buf);
+        match self.search {
+            Some((_, found)) => {
+                let found = LineEditor::from(found.unwrap_or_default());
+                render_editor(&found, inner, is_editing, buf);
+            }
+            None => render_editor(self.content, inner, is_editing, buf),
+        }
     }
 }
\ No newline at end of file
@@ -1,16 +1,44 @@
+use std::cell::Cell;
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{List, Widget, ListItem},
+    widgets::{
+        Block, Borders, List, ListItem, Scrollbar, ScrollbarOrientation, ScrollbarState,
+        StatefulWidget, Widget,
+    },
 };
-use chat_core::message::chat_message::ChatMessage;
+use chat_core::message::chat_message::{ChatMessage, MessageType};
+use crate::state::state::MessageViewport;
+use crate::view::components::wrap::wrap;
+
+/// Characters of the parent shown above a reply
+const QUOTE_LENGTH: usize = 40;
 
 pub struct MessageList<'a> {
     messages: &'a [ChatMessage],
     current_username: &'a str,
+    /// Wrapped rows scrolled up from the bottom
     scroll_offset: usize,
+    selected: Option<usize>,
+    title: Option<String>,
+    divider: Option<usize>,
+    scroll_to_divider: bool,
+    /// Written with the viewport when drawn
+    viewport_out: Option<&'a Cell<MessageViewport>>,
+}
+
+/// Every message wrapped to the width of the list, and the part on screen
+struct Layout {
+    /// One entry per terminal row, with the background of its message
+    rows: Vec<(Line<'static>, Style)>,
+    /// Area the rows are drawn in, the scrollbar excluded
+    text: Rect,
+    scrollbar: bool,
+    /// First row on screen
+    top: usize,
+    viewport: MessageViewport,
 }
 
 impl<'a> MessageList<'a> {
@@ -22,33 +50,232 @@ impl<'a> MessageList<'a> {
         Self {
             messages,
             current_username,
-            scroll_offset
+            scroll_offset,
+            selected: None,
+            title: None,
+            divider: None,
+            scroll_to_divider: false,
+            viewport_out: None,
         }
     }
 
+    /// Where to leave the viewport when drawing, so the list is only laid
+    /// out once per frame
+    pub fn report_viewport(mut self, viewport: &'a Cell<MessageViewport>) -> Self {
+        self.viewport_out = Some(viewport);
+        self
+    }
+
+    pub fn selected(mut self, selected: Option<usize>) -> Self {
+        self.selected = selected;
+        self
+    }
+
+    /// Draws a "new messages" line above the message at this index
+    pub fn divider(mut self, divider: Option<usize>) -> Self {
+        self.divider = divider;
+        self
+    }
+
+    /// Ignores `scroll_offset` and scrolls so the message after the
+    /// divider ends at the bottom
+    pub fn scroll_to_divider(mut self, scroll_to_divider: bool) -> Self {
+        self.scroll_to_divider = scroll_to_divider;
+        self
+    }
+
+    /// Draws the list inside a titled border (used by the thread pane)
+    pub fn title(mut self, title: String) -> Self {
+        self.title = Some(title);
+        self
+    }
+
     pub fn hex_to_ratatui(hex: &str) -> Color {
         use chat_core::utils::color_manager::ColorGenerator;
         let (r, g, b) = ColorGenerator::hex_to_rgb(hex);
         Color::Rgb(r, g, b)
     }
 
-    pub fn format_message(&self, message: &'a ChatMessage) -> ListItem<'a> {
+    fn find(&self, id: u64) -> Option<&'a ChatMessage> {
+        self.messages.iter().find(|msg| msg.id == Some(id))
+    }
+
+    /// "↪ bob: first words of the parent…"
+    fn quote_lines(&self, parent_id: u64, width: usize) -> Vec<Line<'static>> {
+        let style = Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC);
+        let text = match self.find(parent_id) {
+            Some(parent) if parent.deleted => format!("↪ {}: message deleted", parent.sender_name),
+            Some(parent) => {
+                let mut snippet: String = parent.content.chars()
+                    .take(QUOTE_LENGTH)
+                    .map(|c| if c == '\n' { ' ' } else { c })
+                    .collect();
+                if parent.content.chars().count() > QUOTE_LENGTH {
+                    snippet.push('…');
+                }
+                format!("↪ {}: {}", parent.sender_name, snippet)
+            }
+            None => format!("↪ #{}", parent_id),
+        };
+        wrap(&[Span::raw("  ")], &[Span::styled(text, style)], width)
+    }
+
+    /// "👍 2  🎉 1", our own reactions highlighted
+    fn reactions_lines(&self, message: &ChatMessage, width: usize) -> Vec<Line<'static>> {
+        let mut spans = Vec::new();
+        for reaction in &message.reactions {
+            let style = if reaction.users.iter().any(|u| u == self.current_username) {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            spans.push(Span::styled(format!("{} {}", reaction.emoji, reaction.users.len()), style));
+            spans.push(Span::raw("  "));
+        }
+        wrap(&[Span::raw("  ")], &spans, width)
+    }
+
+    fn divider_line(width: usize) -> Line<'static> {
+        let text = format!("{:─^width$}", " new messages ", width = width);
+        Line::from(Span::styled(text, Style::default().fg(Color::Red)))
+    }
+
+    /// The message wrapped to `width`, with the divider, quote and reactions around it
+    fn message_rows(&self, idx: usize, width: usize) -> Vec<Line<'static>> {
+        let message = &self.messages[idx];
         let timestamp = message.timestamp.format("%H:%M:%S").to_string();
         let color = Self::hex_to_ratatui(&message.color);
 
-        let line = Line::from(vec![
+        let mut prefix = vec![
             Span::styled(
                 format!("[{}] ", timestamp),
                 Style::default().fg(Color::DarkGray),
             ),
-            Span::styled(
+        ];
+        if let Some(id) = message.id {
+            prefix.push(Span::styled(format!("#{} ", id), Style::default().fg(Color::DarkGray)));
+        }
+        if matches!(message.message_type, MessageType::Mention) {
+            prefix.push(Span::styled(format!("[{}] ", message.room), Style::default().fg(Color::Yellow)));
+        }
+        let is_action = matches!(message.message_type, MessageType::Action);
+        if is_action {
+            prefix.push(Span::styled(
+                format!("* {} ", message.sender_name),
+                Style::default().fg(color).add_modifier(Modifier::BOLD | Modifier::ITALIC),
+            ));
+        } else {
+            prefix.push(Span::styled(
                 format!("{}: ", message.sender_name),
                 Style::default().fg(color).add_modifier(Modifier::BOLD),
-            ),
-            Span::raw(&message.content),
-        ]);
+            ));
+        }
+
+        let mut body = Vec::new();
+        if message.deleted {
+            body.push(Span::styled(
+                "message deleted",
+                Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC),
+            ));
+        } else {
+            if is_action {
+                body.push(Span::styled(&message.content, Style::default().fg(color).add_modifier(Modifier::ITALIC)));
+            } else {
+                body.push(Span::raw(&message.content));
+            }
+            if message.edited {
+                body.push(Span::styled(" (edited)", Style::default().fg(Color::DarkGray)));
+            }
+        }
+
+        let mut lines = Vec::new();
+        if self.divider == Some(idx) {
+            lines.push(Self::divider_line(width));
+        }
+        if let Some(parent_id) = message.reply_to {
+            lines.extend(self.quote_lines(parent_id, width));
+        }
+        lines.extend(wrap(&prefix, &body, width));
+        if !message.reactions.is_empty() {
+            lines.extend(self.reactions_lines(message, width));
+        }
+        lines
+    }
+
+    fn row_style(&self, idx: usize) -> Style {
+        if Some(idx) == self.selected {
+            Style::default().bg(Color::DarkGray)
+        } else if self.messages[idx].mentions_user(self.current_username) {
+            Style::default().bg(Color::Rgb(60, 50, 0))
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Wraps every message and works out which rows are on screen: the
+    /// bottom `scroll_offset` rows are hidden, but never the selected message
+    fn layout(&self, area: Rect) -> Layout {
+        let inner = match self.title {
+            Some(_) => Block::default().borders(Borders::ALL).inner(area),
+            None => area,
+        };
+        let height = inner.height as usize;
 
-        ListItem::new(line)
+        // Wrap at the full width, and again one column narrower when a scrollbar is needed
+        let mut text = inner;
+        let mut rows = Vec::new();
+        let mut starts = Vec::new();
+        for scrollbar in [false, true] {
+            text.width = inner.width.saturating_sub(u16::from(scrollbar));
+            rows.clear();
+            starts.clear();
+            for idx in 0..self.messages.len() {
+                // Overflowing already: no need to wrap the rest at this width
+                if !scrollbar && rows.len() > height {
+                    break;
+                }
+                starts.push(rows.len());
+                let style = self.row_style(idx);
+                rows.extend(self.message_rows(idx, text.width as usize).into_iter().map(|line| (line, style)));
+            }
+            starts.push(rows.len());
+            if rows.len() <= height || scrollbar {
+                break;
+            }
+        }
+
+        let total = rows.len();
+        let max_offset = total.saturating_sub(height);
+        let mut offset = match self.divider {
+            Some(idx) if self.scroll_to_divider && idx < self.messages.len() => total - starts[idx + 1],
+            _ => self.scroll_offset,
+        };
+        offset = offset.min(max_offset);
+
+        // Keep the selection on screen
+        if let Some(selected) = self.selected.filter(|idx| *idx < self.messages.len()) {
+            let (first, end) = (starts[selected], starts[selected + 1]);
+            let bottom = total - offset;
+            if end > bottom {
+                offset = total - end;
+            } else if first < bottom.saturating_sub(height) {
+                offset = total - (first + height).min(total);
+            }
+        }
+
+        let top = (total - offset).saturating_sub(height);
+        Layout {
+            rows,
+            text,
+            scrollbar: total > height,
+            top,
+            viewport: MessageViewport { offset, max_offset, height },
+        }
+    }
+
+    /// Scroll position and size of the list when drawn in `area`, without drawing it
+    pub fn viewport(&self, area: Rect) -> MessageViewport {
+        self.layout(area).viewport
     }
 }
 
@@ -57,24 +284,122 @@ impl<'a> Widget for MessageList<'a> {
     where
         Self: Sized,
     {
-        let visible_height = area.height.saturating_sub(2) as usize; // -2 for Borders
-
-        let total_messages = self.messages.len();
-        let start_idx = total_messages.saturating_sub(visible_height + self.scroll_offset);
-        let end_idx = total_messages.saturating_sub(self.scroll_offset);
+        let Layout { rows, text, scrollbar, top, viewport } = self.layout(area);
+        if let Some(out) = self.viewport_out {
+            out.set(viewport);
+        }
 
-        let visible_messages = if start_idx < end_idx {
-            &self.messages[start_idx..end_idx]
-        } else {
-            &[]
-        };
+        if let Some(title) = self.title {
+            Block::default().borders(Borders::ALL).title(title).render(area, buf);
+        }
 
-        let items: Vec<ListItem> = visible_messages
-            .iter()
-            .map(|msg| self.format_message(msg))
+        let items: Vec<ListItem> = rows.into_iter()
+            .skip(top)
+            .take(viewport.height)
+            .map(|(line, style)| ListItem::new(line).style(style))
             .collect();
+        Widget::render(List::new(items), text, buf);
 
-        // Render the list
-        List::new(items).render(area, buf);
+        if scrollbar {
+            let mut state = ScrollbarState::new(viewport.max_offset)
+                .position(top)
+                .viewport_content_length(viewport.height);
+            let scrollbar_area = Rect { x: text.right(), width: 1, ..text };
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .render(scrollbar_area, buf, &mut state);
+        }
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn chat(id: u64, content: &str) -> ChatMessage {
+        let mut msg = ChatMessage::new(
+            content.to_string(),
+            "0.0.0.0:0".parse().unwrap(),
+            "al".to_string(),
+            "general".to_string(),
+        );
+        msg.id = Some(id);
+        msg.timestamp = Utc.with_ymd_and_hms(2026, 1, 2, 9, 30, 0).unwrap();
+        msg
+    }
+
+    /// The text on screen, one string per row
+    fn draw(list: MessageList, width: u16, height: u16) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| frame.render_widget(list, frame.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content()
+            .chunks(width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect()
+    }
+
+    #[test]
+    fn test_long_messages_wrap_under_the_text() {
+        let messages = [chat(1, "the quick brown fox jumps over the lazy dog")];
+        let screen = draw(MessageList::new(&messages, "bob", 0), 36, 4);
+        assert_eq!(screen, [
+            "[09:30:00] #1 al: the quick brown   ",
+            "                  fox jumps over the",
+            "                  lazy dog          ",
+            "                                    ",
+        ]);
+    }
+
+    #[test]
+    fn test_multi_line_messages_keep_their_lines() {
+        let messages = [chat(1, "see:\nfn main() {}"), chat(2, "ok")];
+        let screen = draw(MessageList::new(&messages, "bob", 0), 36, 3);
+        assert_eq!(screen, [
+            "[09:30:00] #1 al: see:              ",
+            "                  fn main() {}      ",
+            "[09:30:00] #2 al: ok                ",
+        ]);
+    }
+
+    #[test]
+    fn test_scroll_offset_counts_wrapped_rows() {
+        let messages: Vec<ChatMessage> = (1..=5).map(|id| chat(id, &format!("msg {}", id))).collect();
+        let list = MessageList::new(&messages, "bob", 1);
+        assert_eq!(list.viewport(Rect::new(0, 0, 30, 3)), MessageViewport { offset: 1, max_offset: 2, height: 3 });
+
+        // Drawing leaves the same viewport behind
+        let drawn = Cell::new(MessageViewport::default());
+        let screen = draw(list.report_viewport(&drawn), 30, 3);
+        assert_eq!(drawn.get(), MessageViewport { offset: 1, max_offset: 2, height: 3 });
+        assert_eq!(screen, [
+            "[09:30:00] #2 al: msg 2      ║",
+            "[09:30:00] #3 al: msg 3      █",
+            "[09:30:00] #4 al: msg 4      █",
+        ]);
+
+        // Past the top it stops at the oldest row
+        let screen = draw(MessageList::new(&messages, "bob", 50), 30, 3);
+        assert_eq!(screen[0], "[09:30:00] #1 al: msg 1      █");
+    }
+
+    #[test]
+    fn test_selection_and_divider_are_brought_into_view() {
+        let messages: Vec<ChatMessage> = (1..=6).map(|id| chat(id, &format!("msg {}", id))).collect();
+        let area = Rect::new(0, 0, 30, 3);
+
+        let list = MessageList::new(&messages, "bob", 0).selected(Some(0));
+        assert_eq!(list.viewport(area).offset, 3);
+
+        // The first unread message ends at the bottom, under its divider
+        let list = MessageList::new(&messages, "bob", 0).divider(Some(2)).scroll_to_divider(true);
+        assert_eq!(list.viewport(area).offset, 3);
+        let screen = draw(list, 30, 3);
+        assert_eq!(screen[1], "─────── new messages ────────█");
+        assert_eq!(screen[2], "[09:30:00] #3 al: msg 3      ║");
+    }
+}
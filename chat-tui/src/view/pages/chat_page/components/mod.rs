@@ -4,10 +4,20 @@ pub mod user_list;
 pub mod header;
 pub mod message_list;
 pub mod helpbar;
+pub mod typing_indicator;
+pub mod completion_popup;
+pub mod password_dialog;
+pub mod create_room_dialog;
+pub mod palette_dialog;
 
 pub use message_input_box::MessageInputBox;
 pub use room_list::RoomList;
 pub use user_list::UserList;
 pub use header::Header;
 pub use message_list::MessageList;
-pub use helpbar::HelpBar;
\ No newline at end of file
+pub use helpbar::HelpBar;
+pub use typing_indicator::TypingIndicator;
+pub use completion_popup::CompletionPopup;
+pub use password_dialog::PasswordDialog;
+pub use create_room_dialog::CreateRoomDialog;
+pub use palette_dialog::PaletteDialog;
\ No newline at end of file
@@ -0,0 +1,93 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Widget},
+};
+use crate::state::command_palette::CommandPalette;
+use crate::view::components::input_box::InputBox;
+
+const WIDTH: u16 = 70;
+const HEIGHT: u16 = 16;
+
+/// The command palette: a query box over the matching commands and actions,
+/// drawn near the top of the chat page
+pub struct PaletteDialog<'a> {
+    palette: &'a CommandPalette,
+}
+
+impl<'a> PaletteDialog<'a> {
+    pub fn new(palette: &'a CommandPalette) -> Self {
+        Self { palette }
+    }
+
+    /// Where to draw the dialog: centered across `bounds`, a little below the top
+    pub fn area(bounds: Rect) -> Rect {
+        let width = WIDTH.min(bounds.width);
+        let height = HEIGHT.min(bounds.height);
+        let y = bounds.y + ((bounds.height - height) / 4).min(3);
+        Rect::new(bounds.x + (bounds.width - width) / 2, y, width, height)
+    }
+}
+
+impl<'a> Widget for PaletteDialog<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan))
+            .title(" Commands ");
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),  // Query
+                Constraint::Min(0),     // Matches
+                Constraint::Length(1),  // Keys
+            ]).split(inner);
+
+        InputBox::new("Search", &self.palette.query).editing(true).render(chunks[0], buf);
+
+        let rows = chunks[1].height as usize;
+        if self.palette.matches.is_empty() {
+            Paragraph::new(" Nothing matches")
+                .style(Style::default().fg(Color::DarkGray))
+                .render(chunks[1], buf);
+        }
+        let first = (self.palette.selected + 1).saturating_sub(rows);
+        let items: Vec<ListItem> = self.palette.matches.iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .map(|(idx, entry)| {
+                let selected = idx == self.palette.selected;
+                // The highlight carries the colors of the selected row
+                let colored = |color| if selected { Style::default() } else { Style::default().fg(color) };
+                let mut spans = vec![
+                    Span::styled(format!(" {}", entry.label()), Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(format!(" {}", entry.hint()), colored(Color::Cyan)),
+                ];
+                if !entry.description().is_empty() {
+                    spans.push(Span::styled(format!("  {}", entry.description()), colored(Color::Gray)));
+                }
+                let item = ListItem::new(Line::from(spans));
+                if selected {
+                    item.style(Style::default().fg(Color::Black).bg(Color::Cyan))
+                } else {
+                    item
+                }
+            })
+            .collect();
+        List::new(items).render(chunks[1], buf);
+
+        Paragraph::new(" ↑/↓ choose · Enter run · Esc close")
+            .style(Style::default().fg(Color::DarkGray))
+            .render(chunks[2], buf);
+    }
+}
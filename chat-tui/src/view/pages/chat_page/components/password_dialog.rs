@@ -0,0 +1,100 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+use crate::state::password_prompt::PasswordPrompt;
+use crate::view::components::input_box::InputBox;
+
+const WIDTH: u16 = 44;
+const HEIGHT: u16 = 8;
+
+/// Masked password field for a locked room, drawn over the chat page
+pub struct PasswordDialog<'a> {
+    prompt: &'a PasswordPrompt,
+}
+
+impl<'a> PasswordDialog<'a> {
+    pub fn new(prompt: &'a PasswordPrompt) -> Self {
+        Self { prompt }
+    }
+
+    /// Where to draw the dialog: centered in `bounds`
+    pub fn area(bounds: Rect) -> Rect {
+        let width = WIDTH.min(bounds.width);
+        let height = HEIGHT.min(bounds.height);
+        Rect::new(
+            bounds.x + (bounds.width - width) / 2,
+            bounds.y + (bounds.height - height) / 2,
+            width,
+            height,
+        )
+    }
+}
+
+impl<'a> Widget for PasswordDialog<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!(" 🔒 {} ", self.prompt.room));
+        let inner = block.inner(area);
+        Clear.render(area, buf);
+        block.render(area, buf);
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),  // Reason
+                Constraint::Length(3),  // Password
+                Constraint::Length(1),  // Remember
+                Constraint::Length(1),  // Keys
+            ]).split(inner);
+
+        let reason = if self.prompt.wrong {
+            Line::styled("Wrong password, try again", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+        } else {
+            Line::from("This room needs a password")
+        };
+        Paragraph::new(reason).render(chunks[0], buf);
+
+        let masked = self.prompt.input.masked('•');
+        InputBox::new("Password", &masked).editing(true).render(chunks[1], buf);
+
+        let check = if self.prompt.remember { "[x]" } else { "[ ]" };
+        Paragraph::new(format!("{} Remember for this session (Tab)", check)).render(chunks[2], buf);
+        Paragraph::new("Enter join · Esc cancel")
+            .style(Style::default().fg(Color::DarkGray))
+            .render(chunks[3], buf);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    #[test]
+    fn test_password_is_masked() {
+        let mut prompt = PasswordPrompt::new("vault".to_string(), true, true);
+        prompt.input.set_text("hunter2");
+
+        let mut terminal = Terminal::new(TestBackend::new(44, 8)).unwrap();
+        terminal.draw(|frame| frame.render_widget(PasswordDialog::new(&prompt), frame.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        let screen: Vec<String> = buffer.content()
+            .chunks(44)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect())
+            .collect();
+        assert_eq!(screen[1], "│Wrong password, try again                 │");
+        assert_eq!(screen[3], "││•••••••                                 ││");
+        assert_eq!(screen[5], "│[x] Remember for this session (Tab)       │");
+        assert!(!screen.concat().contains("hunter2"));
+    }
+}
@@ -5,10 +5,13 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, Widget},
 };
+use crate::state::state::DirectConversation;
 
 pub struct RoomList<'a> {
     rooms: &'a [String],
     current_room: Option<&'a str>,
+    conversations: &'a [DirectConversation],
+    active_dm: Option<&'a str>,
     is_focused: bool,
 }
 
@@ -17,14 +20,33 @@ impl<'a> RoomList<'a> {
         Self{
             rooms,
             current_room,
+            conversations: &[],
+            active_dm: None,
             is_focused: false,
         }
     }
 
+    pub fn direct_messages(mut self, conversations: &'a [DirectConversation], active_dm: Option<&'a str>) -> Self {
+        self.conversations = conversations;
+        self.active_dm = active_dm;
+        self
+    }
+
     pub fn focused(mut self, is_focused: bool) -> Self {
         self.is_focused = is_focused;
         self
     }
+
+    /// Sidebar entry drawn on `row` inside the border, rooms first then DMs.
+    /// `None` for the "Direct" heading and empty rows.
+    pub fn entry_at(rooms: usize, conversations: usize, row: usize) -> Option<usize> {
+        if row < rooms {
+            return Some(row);
+        }
+        // Skip the heading above the DMs
+        let dm = row.checked_sub(rooms + 1)?;
+        (dm < conversations).then_some(rooms + dm)
+    }
 }
 
 impl<'a> Widget for RoomList<'a> {
@@ -32,14 +54,14 @@ impl<'a> Widget for RoomList<'a> {
     where
         Self: Sized,
     {
-        let items: Vec<ListItem> = self
+        let mut items: Vec<ListItem> = self
             .rooms
             .iter()
             .map(|room| {
                 let room_name_clean = room.trim_end_matches("🔒");
                 let has_lock = room.ends_with("🔒");
 
-                let is_current = Some(room_name_clean) == self.current_room;
+                let is_current = self.active_dm.is_none() && Some(room_name_clean) == self.current_room;
 
                 let style = if is_current {
                     Style::default()
@@ -59,6 +81,34 @@ impl<'a> Widget for RoomList<'a> {
             })
             .collect();
 
+        if !self.conversations.is_empty() {
+            items.push(ListItem::new(Line::from(Span::styled(
+                "Direct",
+                Style::default().fg(Color::DarkGray).add_modifier(Modifier::BOLD),
+            ))));
+        }
+
+        for conversation in self.conversations {
+            let is_active = Some(conversation.peer.as_str()) == self.active_dm;
+            let style = if is_active {
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD)
+            } else if conversation.unread > 0 {
+                Style::default().fg(Color::White).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::White)
+            };
+
+            let display_text = if conversation.unread > 0 {
+                format!("  @{} ({})", conversation.peer, conversation.unread)
+            } else {
+                format!("  @{}", conversation.peer)
+            };
+
+            items.push(ListItem::new(Line::from(Span::styled(display_text, style))));
+        }
+
         let list = List::new(items).block(
             Block::default()
                 .borders(Borders::ALL)
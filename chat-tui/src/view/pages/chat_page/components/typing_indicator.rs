@@ -0,0 +1,35 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Paragraph, Widget},
+};
+
+pub struct TypingIndicator<'a> {
+    names: &'a [&'a str],
+}
+
+impl<'a> TypingIndicator<'a> {
+    pub fn new(names: &'a [&'a str]) -> Self {
+        Self { names }
+    }
+
+    fn text(&self) -> String {
+        match self.names {
+            [] => String::new(),
+            [one] => format!("{} is typing…", one),
+            [first, second] => format!("{} and {} are typing…", first, second),
+            _ => "Several people are typing…".to_string(),
+        }
+    }
+}
+
+impl<'a> Widget for TypingIndicator<'a> {
+    fn render(self, area: Rect, buf: &mut Buffer)
+    where
+        Self: Sized,
+    {
+        let style = Style::default().fg(Color::DarkGray).add_modifier(Modifier::ITALIC);
+        Paragraph::new(self.text()).style(style).render(area, buf);
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use chat_core::client::presence::Presence;
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
@@ -9,6 +11,7 @@ use ratatui::{
 pub struct UserList<'a> {
     users: &'a [String],
     current_username: &'a str,
+    presence: Option<&'a HashMap<String, Presence>>,
 }
 
 impl<'a> UserList<'a> {
@@ -16,6 +19,21 @@ impl<'a> UserList<'a> {
         Self {
             users,
             current_username,
+            presence: None,
+        }
+    }
+
+    pub fn presence(mut self, presence: &'a HashMap<String, Presence>) -> Self {
+        self.presence = Some(presence);
+        self
+    }
+
+    /// Marker and its color: ● online, ◐ idle, ○ away
+    fn marker(presence: Option<&Presence>, online_color: Color) -> (&'static str, Color) {
+        match presence {
+            Some(Presence::Idle) => ("◐ ", Color::Yellow),
+            Some(Presence::Away(_)) => ("○ ", Color::DarkGray),
+            _ => ("● ", online_color),
         }
     }
 }
@@ -29,9 +47,11 @@ impl<'a> Widget for UserList<'a> {
             .iter()
             .map(|user| {
                 let is_self = user == self.current_username;
-                let line = if is_self {
+                let presence = self.presence.and_then(|p| p.get(user));
+                let mut line = if is_self {
+                    let (marker, color) = Self::marker(presence, Color::Green);
                     Line::from(vec![
-                        Span::styled("● ", Style::default().fg(Color::Green)),
+                        Span::styled(marker, Style::default().fg(color)),
                         Span::styled(
                             format!("{} (you)", user),
                             Style::default().fg(Color::Green)
@@ -39,14 +59,24 @@ impl<'a> Widget for UserList<'a> {
                         ),
                     ])
                 } else {
+                    let (marker, color) = Self::marker(presence, Color::Cyan);
                     Line::from(vec![
-                        Span::styled("● ", Style::default().fg(Color::Cyan)),
+                        Span::styled(marker, Style::default().fg(color)),
                         Span::styled(
                             user,
                             Style::default().fg(Color::Cyan),
                         ),
                     ])
                 };
+                match presence {
+                    Some(Presence::Away(message)) if !message.is_empty() => {
+                        line.push_span(Span::styled(format!(" – {}", message), Style::default().fg(Color::DarkGray)));
+                    }
+                    Some(Presence::Away(_)) => {
+                        line.push_span(Span::styled(" – away", Style::default().fg(Color::DarkGray)));
+                    }
+                    _ => {}
+                }
                 ListItem::new(line)
             }).collect();
 
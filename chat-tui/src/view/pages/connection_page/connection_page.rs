@@ -65,7 +65,7 @@ impl<'a> ConnectionPage<'a> {
             );
         server_input.render(chunks[0], buf);
 
-        let username = InputBox::new("Username", &self.state.username)
+        let username = InputBox::new("Username", &self.state.username_input)
             .focused(matches!(self.state.focused_field, FocusedField::Username))
             .editing(
                 matches!(self.state.focused_field, FocusedField::Username)
@@ -79,7 +79,7 @@ impl<'a> ConnectionPage<'a> {
     pub fn render_connect_button(&self, area: Rect, buf: &mut Buffer) {
         let is_focused = matches!(self.state.focused_field, FocusedField::ConnectButton);
         let can_connect = !self.state.server_address.is_empty()
-            && !self.state.username.is_empty()
+            && !self.state.username_input.is_empty()
             && matches!(self.state.connection_status, 
                 ConnectionStatus::Disconnected | ConnectionStatus::Error(_));
 
@@ -1,5 +1,7 @@
 use ratatui::Frame;
+use crate::input::keymap;
 use crate::state::state::{AppState, AppPage};
+use crate::view::components::help_overlay::HelpOverlay;
 use crate::view::pages::{
     chat_page::chat_page::ChatPage,
     connection_page::connection_page::ConnectionPage,
@@ -19,5 +21,11 @@ impl View {
                 frame.render_widget(chat_page, frame.area());
             }
         }
+        if let Some(help) = &state.help {
+            let sections = keymap::sections(&state.current_page, &help.input_mode, &help.focused_field);
+            let overlay = HelpOverlay::new(&sections).scroll(help.scroll);
+            let area = overlay.area(frame.area());
+            frame.render_widget(overlay, area);
+        }
     }
 }